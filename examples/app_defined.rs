@@ -1,8 +1,31 @@
 use cli_table::WithTitle;
+use kcheck::check::{Check, CheckContext, CheckOutcome};
 use kcheck::config::KcheckConfigBuilder;
 use kcheck::kconfig::{KconfigOption, KconfigState};
 use kcheck::kernel::KernelConfigBuilder;
-use kcheck::Kcheck;
+use kcheck::{Kcheck, KcheckResult};
+
+/// Application defined check that `/dev/kvm` can be opened.
+struct KvmAccessible;
+
+impl Check for KvmAccessible {
+    fn name(&self) -> String {
+        "/dev/kvm".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Accessible".to_string()
+    }
+
+    fn evaluate(&self, ctx: &CheckContext) -> KcheckResult<CheckOutcome> {
+        let outcome = match std::fs::File::open(ctx.path("/dev/kvm")) {
+            Ok(_) => CheckOutcome::pass("Accessible"),
+            Err(e) => CheckOutcome::fail(&e.to_string()),
+        };
+
+        Ok(outcome)
+    }
+}
 
 fn main() {
     // Kernel configs required by this application.
@@ -20,7 +43,7 @@ fn main() {
 
     let kernel = KernelConfigBuilder::default().system().build().unwrap();
 
-    let kcheck = Kcheck::new(config, kernel);
+    let kcheck = Kcheck::new(config, kernel).with_check(KvmAccessible);
     let table = kcheck
         .perform_check()
         .unwrap()
@@ -0,0 +1,62 @@
+use cli_table::WithTitle;
+use kcheck::config::KcheckConfigBuilder;
+use kcheck::kconfig::{KconfigOption, KconfigState};
+use kcheck::kernel::{KernelConfigBuilder, KernelConfigProvider, KernelConfigSource};
+use kcheck::{Kcheck, KcheckResult};
+use std::path::PathBuf;
+
+/// Kernel config captured from a serial console log.
+///
+/// The config is dumped on the target with `zcat /proc/config.gz`, so every
+/// config line in the log may be prefixed with console noise such as
+/// timestamps.
+#[derive(Debug)]
+struct SerialLog(PathBuf);
+
+impl KernelConfigProvider for SerialLog {
+    fn source(&self) -> KernelConfigSource {
+        KernelConfigSource::Custom(format!("serial log {}", self.0.display()))
+    }
+
+    fn read(&self) -> KcheckResult<String> {
+        let log = std::fs::read_to_string(&self.0)?;
+
+        let config = log
+            .lines()
+            .filter_map(|line| {
+                let start = line.find("CONFIG_")?;
+                // Keep the `# ` prefix for options that are not set
+                let start = match line[..start].ends_with("# ") {
+                    true => start - 2,
+                    false => start,
+                };
+                Some(line[start..].trim_end())
+            })
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        Ok(config)
+    }
+}
+
+fn main() {
+    let log = std::env::args()
+        .nth(1)
+        .expect("usage: custom_source <serial log>");
+
+    let config = KcheckConfigBuilder::default()
+        .kernel(vec![KconfigOption::new(
+            "CONFIG_USB_ACM",
+            KconfigState::Enabled,
+        )])
+        .build()
+        .unwrap();
+
+    let kernel = KernelConfigBuilder::default()
+        .provider(SerialLog(PathBuf::from(log)))
+        .build()
+        .unwrap();
+
+    let results = Kcheck::new(config, kernel).perform_check().unwrap();
+    println!("{}", results.with_title().display().unwrap());
+}
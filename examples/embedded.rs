@@ -0,0 +1,22 @@
+// Kernel configs required by this application, stored in the `.kcheck`
+// section of the binary. They can be checked against a kernel without running
+// the application using `kcheck-cli scan <path to binary>`.
+kcheck::embed_config!(
+    r#"
+    [[fragment]]
+    name = "usb-serial"
+    reason = "Serial USB support"
+
+    [[fragment.kernel]]
+    name = "CONFIG_USB_ACM"
+    state = "Enabled"
+
+    [[fragment.kernel]]
+    name = "CONFIG_USB_SERIAL"
+    state = "Module"
+    "#
+);
+
+fn main() {
+    println!("Hello from an application with embedded kernel requirements!");
+}
@@ -5,46 +5,419 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use cli_table::WithTitle;
-use kcheck::KcheckBuilder;
-use std::path::PathBuf;
+use kcheck::{
+    config::KcheckConfigBuilder,
+    filter::{ResultFilter, SortKey},
+    fix::{EnabledPreference, Fixer},
+    git::GitKernelConfig,
+    kconfig::Severity,
+    kernel::{DuplicatePolicy, KernelConfigBuilder},
+    report::Report,
+    target::KernelVersion,
+    tree::{KconfigTree, KconfigTreeBuilder},
+    KcheckBuilder, KcheckError,
+};
+use std::{collections::HashSet, path::PathBuf};
 
 /// A tool for developing and debugging kernel config options.
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Path to the kernel config file.
-    #[arg(short, long)]
-    kconfig: Option<PathBuf>,
+    /// Path to the kernel config file, or `git:<revision>:<path>` to read it from a git repository.
+    #[arg(short, long, global = true)]
+    kconfig: Option<String>,
+
+    /// Path to the git repository used by `git:` kernel configs.
+    #[arg(long, global = true, default_value = ".")]
+    git_repo: PathBuf,
+
+    /// Path to a kernel source tree used to expand the kernel config with Kconfig defaults.
+    #[arg(long, global = true)]
+    tree: Option<PathBuf>,
+
+    /// Target architecture of the kernel config and source tree. Defaults to the architecture
+    /// set in the kernel config, or the running system.
+    #[arg(long, global = true)]
+    arch: Option<String>,
+
+    /// Target kernel version, such as `6.6`. Defaults to the version in the kernel config header,
+    /// or the running system.
+    #[arg(long, global = true)]
+    kernel_version: Option<KernelVersion>,
 
     /// Path to Kcheck config files or fragments.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     configs: Vec<PathBuf>,
+
+    /// Only check the config fragments that belong to a named profile.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// How to handle options that are set more than once in the kernel config: `error`, `last`, or `first`.
+    #[arg(long, global = true, default_value = "error")]
+    duplicates: DuplicatePolicy,
+
+    /// Stop at the first requirement that cannot be checked instead of reporting it as an error.
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Format of the check results.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Only show requirements that failed or could not be checked.
+    #[arg(long, global = true)]
+    only_failures: bool,
+
+    /// Only show the results of a fragment. Can be used more than once.
+    #[arg(long, global = true)]
+    fragment: Vec<String>,
+
+    /// Only show options matching a name or glob, such as `CONFIG_USB_*`. Can be used more than once.
+    #[arg(long, global = true)]
+    option: Vec<String>,
+
+    /// Only show results with at least this severity: `error`, `warning`, or `info`.
+    #[arg(long, global = true)]
+    severity: Option<Severity>,
+
+    /// Sort the results by `name`, `result`, or `fragment`.
+    #[arg(long, global = true)]
+    sort: Option<SortKey>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn main() {
-    let args = Args::parse();
+/// Format of the check results.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    /// A table for the terminal.
+    Table,
+    /// JSON for other tools.
+    Json,
+    /// A Markdown report grouped by fragment.
+    Markdown,
+    /// A self-contained HTML report grouped by fragment.
+    Html,
+    /// A SARIF 2.1.0 log for code scanning tools.
+    Sarif,
+    /// TAP version 13 for test harnesses.
+    Tap,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check the kernel against requirements embedded in application binaries.
+    Scan {
+        /// Paths to ELF binaries to extract requirements from.
+        #[arg(required = true)]
+        binaries: Vec<PathBuf>,
+    },
+    /// Print the JSON Schema of the Kcheck config format.
+    Schema,
+    /// Reduce the kernel config to a minimal defconfig. Requires `--tree`.
+    Savedefconfig {
+        /// Write the defconfig to a file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Rewrite the kernel config so that it meets the hard requirements.
+    Fix {
+        /// Write the fixed config to a file instead of changing the kernel config in place.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Value used for options that have to be `Enabled`: `y` or `m`.
+        #[arg(long, default_value = "y")]
+        enabled: EnabledPreference,
+    },
+}
 
-    let builder = KcheckBuilder::default();
-    let configs = args.configs;
-    let kcheck = match args.kconfig {
-        Some(k) => builder
-            .kernel_fragments(vec![k])
-            .config_fragments(configs)
-            .build(),
-        None => builder.system_kernel().config_fragments(configs).build(),
+/// Print an error and exit.
+fn exit_with_error(context: &str, e: KcheckError) -> ! {
+    let diagnostics = e.diagnostics();
+    if diagnostics.is_empty() {
+        eprintln!("{context}: {e}");
+    } else {
+        eprintln!("{context}:");
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic.render());
+        }
+    }
+
+    std::process::exit(1);
+}
+
+/// The result filter selected on the command line.
+fn result_filter(args: &Args) -> ResultFilter {
+    let mut filter = ResultFilter::default();
+    if args.only_failures {
+        filter = filter.only_failures();
+    }
+
+    for fragment in &args.fragment {
+        filter = filter.fragment(fragment);
+    }
+
+    for option in &args.option {
+        filter = filter.option(option);
+    }
+
+    if let Some(severity) = args.severity {
+        filter = filter.severity(severity);
+    }
+
+    if let Some(sort) = args.sort {
+        filter = filter.sort(sort);
+    }
+
+    filter
+}
+
+/// Read the Kconfig tree, if one was provided.
+fn kconfig_tree(args: &Args) -> Option<KconfigTree> {
+    let tree = args.tree.as_ref()?;
+
+    let mut builder = KconfigTreeBuilder::default().source_tree(tree);
+    if let Some(arch) = &args.arch {
+        builder = builder.arch(arch);
+    }
+
+    match builder.build() {
+        Ok(tree) => Some(tree),
+        Err(e) => exit_with_error("Failed to read Kconfig tree", e),
+    }
+}
+
+fn savedefconfig(args: &Args, output: Option<&PathBuf>) {
+    let Some(tree) = kconfig_tree(args) else {
+        eprintln!("A kernel source tree is required, use `--tree`");
+        std::process::exit(1);
     };
 
-    let system = match kcheck {
-        Ok(system) => system,
-        Err(e) => {
-            eprintln!("Failed to create Kcheck system: {e}");
+    let builder = KernelConfigBuilder::default().duplicates(args.duplicates);
+    let builder = match &args.kconfig {
+        Some(k) if GitKernelConfig::is_spec(k) => {
+            match GitKernelConfig::from_spec(&args.git_repo, k) {
+                Ok(git) => builder.provider(git),
+                Err(e) => exit_with_error("Failed to read kernel config", e),
+            }
+        }
+        Some(k) => builder.user(k),
+        None => builder.system(),
+    };
+
+    let defconfig = builder
+        .build()
+        .and_then(|config| tree.savedefconfig(&config))
+        .unwrap_or_else(|e| exit_with_error("Failed to create defconfig", e));
+
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, defconfig.to_string()) {
+                exit_with_error("Failed to write defconfig", e.into());
+            }
+        }
+        None => print!("{defconfig}"),
+    }
+}
+
+fn fix(args: &Args, output: Option<&PathBuf>, enabled: EnabledPreference) {
+    let builder = KernelConfigBuilder::default().duplicates(args.duplicates);
+    let (builder, output) = match (&args.kconfig, output) {
+        (Some(k), output) if GitKernelConfig::is_spec(k) => {
+            let Some(output) = output else {
+                eprintln!("Kernel configs from git cannot be fixed in place, use `--output`");
+                std::process::exit(1);
+            };
+
+            match GitKernelConfig::from_spec(&args.git_repo, k) {
+                Ok(git) => (builder.provider(git), output.clone()),
+                Err(e) => exit_with_error("Failed to read kernel config", e),
+            }
+        }
+        (Some(k), output) => (
+            builder.user(k),
+            output.cloned().unwrap_or_else(|| PathBuf::from(k)),
+        ),
+        (None, Some(output)) => (builder.system(), output.clone()),
+        (None, None) => {
+            eprintln!("The system kernel config cannot be fixed in place, use `--output`");
             std::process::exit(1);
         }
     };
 
-    let results = system.perform_check().unwrap();
-    let table = results.with_title().display().unwrap();
-    println!("{}", table);
+    let kernel = builder
+        .build()
+        .unwrap_or_else(|e| exit_with_error("Failed to read kernel config", e));
+
+    let mut config = KcheckConfigBuilder::default()
+        .config_files(args.configs.clone())
+        .build()
+        .unwrap_or_else(|e| exit_with_error("Failed to read Kcheck config", e));
+    if let Some(profile) = &args.profile {
+        config
+            .select_profile(profile)
+            .unwrap_or_else(|e| exit_with_error("Failed to read Kcheck config", e));
+    }
+
+    let mut fixer = Fixer::new(config, kernel).with_enabled(enabled);
+    if let Some(tree) = kconfig_tree(args) {
+        fixer = fixer.with_tree(tree);
+    }
+
+    let report = fixer
+        .fix()
+        .unwrap_or_else(|e| exit_with_error("Failed to fix the kernel config", e));
+
+    let changes = report.changes();
+    if changes.is_empty() {
+        println!("No changes needed for {}", output.display());
+    } else {
+        if let Err(e) = std::fs::write(&output, report.config().to_string()) {
+            exit_with_error("Failed to write kernel config", e.into());
+        }
+
+        println!(
+            "{} change(s) written to {}",
+            changes.len(),
+            output.display()
+        );
+        for change in changes {
+            println!("  {change}");
+        }
+    }
+
+    let remaining = report.remaining();
+    for result in &remaining {
+        eprintln!(
+            "error: {} could not be fixed, expected {} but found {}",
+            result.name(),
+            result.desired_state(),
+            result.kernel_state()
+        );
+    }
+
+    if !remaining.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut builder = KcheckBuilder::default();
+    match &args.command {
+        Some(Command::Scan { binaries }) => builder = builder.binaries(binaries.clone()),
+        Some(Command::Savedefconfig { output }) => {
+            savedefconfig(&args, output.as_ref());
+            return;
+        }
+        Some(Command::Fix { output, enabled }) => {
+            fix(&args, output.as_ref(), *enabled);
+            return;
+        }
+        Some(Command::Schema) => {
+            let schema = serde_json::to_string_pretty(&kcheck::schema::schema())
+                .expect("Expected the schema to serialize");
+            println!("{schema}");
+            return;
+        }
+        None => {}
+    }
+
+    builder = match &args.kconfig {
+        Some(k) if GitKernelConfig::is_spec(k) => {
+            match GitKernelConfig::from_spec(&args.git_repo, k) {
+                Ok(git) => builder.kernel_provider(git),
+                Err(e) => exit_with_error("Failed to create Kcheck system", e),
+            }
+        }
+        Some(k) => builder.kernel_fragments(vec![PathBuf::from(k)]),
+        None => builder.system_kernel(),
+    };
+
+    if let Some(tree) = kconfig_tree(&args) {
+        builder = builder.kconfig_tree(tree);
+    }
+
+    if let Some(arch) = &args.arch {
+        builder = builder.arch(arch);
+    }
+
+    if let Some(version) = args.kernel_version {
+        builder = builder.kernel_version(version);
+    }
+
+    if let Some(profile) = &args.profile {
+        builder = builder.profile(profile);
+    }
+
+    if args.strict {
+        builder = builder.strict();
+    }
+
+    let filter = result_filter(&args);
+    let system = builder
+        .duplicates(args.duplicates)
+        .config_fragments(args.configs)
+        .build()
+        .unwrap_or_else(|e| exit_with_error("Failed to create Kcheck system", e));
+
+    let results = system
+        .perform_check()
+        .and_then(|results| filter.apply(results))
+        .unwrap_or_else(|e| exit_with_error("Failed to check the kernel config", e));
+    match args.format {
+        Format::Table => {
+            let table = results.with_title().display().unwrap();
+            println!("{}", table);
+        }
+        Format::Json => {
+            let json =
+                serde_json::to_string_pretty(&results).expect("Expected the results to serialize");
+            println!("{json}");
+        }
+        Format::Markdown => {
+            print!(
+                "{}",
+                Report::new(results.clone())
+                    .with_source(system.kernel_source())
+                    .markdown()
+            );
+        }
+        Format::Html => {
+            print!(
+                "{}",
+                Report::new(results.clone())
+                    .with_source(system.kernel_source())
+                    .html()
+            );
+        }
+        Format::Sarif => println!(
+            "{}",
+            Report::new(results.clone())
+                .with_source(system.kernel_source())
+                .sarif()
+        ),
+        Format::Tap => {
+            print!(
+                "{}",
+                Report::new(results.clone())
+                    .with_source(system.kernel_source())
+                    .tap()
+            );
+        }
+    }
+
+    // Results can share warnings, such as the members of a group, so only print each once
+    let mut seen = HashSet::new();
+    let mut warnings: Vec<String> = results.iter().flat_map(|r| r.warnings()).collect();
+    warnings.retain(|w| seen.insert(w.clone()));
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
 }
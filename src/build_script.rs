@@ -0,0 +1,263 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Validate a target kernel config from a Cargo build script.
+//!
+//! ```no_run
+//! // In `fn main` of build.rs
+//! kcheck::build_script::BuildCheck::default()
+//!     .config_files(vec!["kcheck.toml"])
+//!     .run();
+//! ```
+//!
+//! The kernel config is read from the path in the `KCHECK_KERNEL_CONFIG`
+//! environment variable. Failed requirements with a [`Severity::Error`]
+//! severity fail the build, while other failed requirements are emitted as
+//! `cargo:warning` lines.
+
+use crate::{
+    config::KcheckConfigBuilder, error::KcheckResult, kconfig::Severity,
+    kernel::KernelConfigBuilder, CheckResult, Kcheck, KcheckConfigResult,
+};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Default environment variable holding the path to the target kernel config.
+pub const KERNEL_CONFIG_ENV: &str = "KCHECK_KERNEL_CONFIG";
+
+/// Check a kernel config against `kcheck` configs from a build script.
+#[derive(Clone, Debug, Default)]
+pub struct BuildCheck {
+    /// Kcheck config files containing the requirements.
+    config_files: Vec<PathBuf>,
+    /// Path to the kernel config, overriding the environment variable.
+    kernel_config: Option<PathBuf>,
+    /// Environment variable holding the path to the kernel config.
+    kernel_env: Option<String>,
+}
+
+impl BuildCheck {
+    /// Add user provided config files containing the requirements.
+    pub fn config_files<P: AsRef<Path>>(mut self, files: Vec<P>) -> Self {
+        for item in files {
+            self.config_files.push(item.as_ref().to_path_buf());
+        }
+        self
+    }
+
+    /// Use a specific kernel config instead of reading it from the environment.
+    pub fn kernel_config<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.kernel_config = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Read the kernel config path from a different environment variable.
+    ///
+    /// Defaults to [`KERNEL_CONFIG_ENV`].
+    pub fn kernel_env(mut self, var: &str) -> Self {
+        self.kernel_env = Some(var.to_string());
+        self
+    }
+
+    /// Perform the check, writing Cargo build script instructions to `out`.
+    ///
+    /// Every config file that is read, including the files it includes or
+    /// extends, is tracked with `cargo:rerun-if-changed`. Returns the failed
+    /// requirements with an error severity. If no kernel config is provided, a
+    /// warning is emitted and the check is skipped.
+    pub fn check<W: Write>(&self, out: &mut W) -> KcheckResult<Vec<KcheckConfigResult>> {
+        for file in &self.config_files {
+            writeln!(out, "cargo:rerun-if-changed={}", file.display())?;
+        }
+
+        let kernel_config = match &self.kernel_config {
+            Some(path) => path.clone(),
+            None => {
+                let var = self.kernel_env.as_deref().unwrap_or(KERNEL_CONFIG_ENV);
+                writeln!(out, "cargo:rerun-if-env-changed={var}")?;
+
+                match std::env::var_os(var) {
+                    Some(path) => PathBuf::from(path),
+                    None => {
+                        writeln!(
+                            out,
+                            "cargo:warning={var} is not set, skipping kernel config check"
+                        )?;
+                        return Ok(Vec::new());
+                    }
+                }
+            }
+        };
+
+        // Track the files that the config files include or extend
+        let (config, files) = KcheckConfigBuilder::default()
+            .config_files(self.config_files.clone())
+            .build_with_files()?;
+        for file in files.iter().filter(|f| !self.config_files.contains(f)) {
+            writeln!(out, "cargo:rerun-if-changed={}", file.display())?;
+        }
+
+        writeln!(out, "cargo:rerun-if-changed={}", kernel_config.display())?;
+
+        let kernel = KernelConfigBuilder::default().user(kernel_config).build()?;
+        let kcheck = Kcheck::new(config, kernel);
+
+        let mut errors = Vec::new();
+        for result in kcheck.perform_check()? {
+            if !matches!(result.result(), CheckResult::Fail | CheckResult::Error) {
+                continue;
+            }
+
+            if result.severity() == Severity::Error {
+                errors.push(result);
+            } else {
+                writeln!(out, "cargo:warning={}", Self::describe(&result))?;
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Perform the check from a build script.
+    ///
+    /// Exits the build script with an error if the check could not be
+    /// performed or a hard requirement is not met.
+    pub fn run(&self) {
+        let errors = match self.check(&mut std::io::stdout()) {
+            Ok(errors) => errors,
+            Err(e) => {
+                eprintln!("error: kernel config check failed: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        if !errors.is_empty() {
+            for result in &errors {
+                eprintln!("error: {}", Self::describe(result));
+            }
+            eprintln!(
+                "error: kernel config does not meet {} requirement(s)",
+                errors.len()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    /// Describe a failed requirement on a single line.
+    fn describe(result: &KcheckConfigResult) -> String {
+        format!(
+            "{}: desired {}, kernel {}",
+            result.name(),
+            result.desired_state(),
+            result.kernel_state()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util;
+
+    const TEST_KERNEL_CONFIG: &str = "CONFIG_FOO=y\nCONFIG_BAR=m\n";
+
+    const TEST_KCHECK_CONFIG: &str = r#"
+    [[kernel]]
+    name = "CONFIG_FOO"
+    state = "On"
+
+    [[kernel]]
+    name = "CONFIG_BAR"
+    state = "On"
+    severity = "Warning"
+
+    [[kernel]]
+    name = "CONFIG_BAZ"
+    state = "Enabled"
+    "#;
+
+    fn helper_check(build_check: BuildCheck) -> (Vec<KcheckConfigResult>, String) {
+        let mut out = Vec::new();
+        let errors = build_check
+            .check(&mut out)
+            .expect("Expected to perform the check");
+        (
+            errors,
+            String::from_utf8(out).expect("Expected utf8 output"),
+        )
+    }
+
+    #[test]
+    fn success_build_check() {
+        util::run_with_tmpfile("config", TEST_KERNEL_CONFIG, |kernel_path| {
+            util::run_with_tmpfile("kcheck.toml", TEST_KCHECK_CONFIG, |kcheck_path| {
+                let build_check = BuildCheck::default()
+                    .config_files(vec![kcheck_path.clone()])
+                    .kernel_config(kernel_path.clone());
+                let (errors, out) = helper_check(build_check);
+
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].name(), "CONFIG_BAZ");
+
+                let expected = [
+                    format!("cargo:rerun-if-changed={}", kcheck_path.display()),
+                    format!("cargo:rerun-if-changed={}", kernel_path.display()),
+                    "cargo:warning=CONFIG_BAR: desired On, kernel Module".to_string(),
+                ];
+                assert_eq!(out.lines().collect::<Vec<&str>>(), expected);
+            });
+        });
+    }
+
+    #[test]
+    fn success_build_check_tracks_includes() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        let kernel_path = tmpdir.path().join("config");
+        let kcheck_path = tmpdir.path().join("kcheck.toml");
+        let common_path = tmpdir.path().join("common.toml");
+        std::fs::write(&kernel_path, TEST_KERNEL_CONFIG).unwrap();
+        std::fs::write(&kcheck_path, "include = [\"common.toml\"]").unwrap();
+        std::fs::write(
+            &common_path,
+            "kernel = [{ name = \"CONFIG_FOO\", state = \"On\" }]",
+        )
+        .unwrap();
+
+        let build_check = BuildCheck::default()
+            .config_files(vec![kcheck_path.clone()])
+            .kernel_config(kernel_path.clone());
+        let (errors, out) = helper_check(build_check);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            out.lines().collect::<Vec<&str>>(),
+            [
+                format!("cargo:rerun-if-changed={}", kcheck_path.display()),
+                format!("cargo:rerun-if-changed={}", common_path.display()),
+                format!("cargo:rerun-if-changed={}", kernel_path.display()),
+            ]
+        );
+    }
+
+    #[test]
+    fn success_build_check_env_not_set() {
+        let var = "KCHECK_TEST_KERNEL_CONFIG_NOT_SET";
+        let build_check = BuildCheck::default().kernel_env(var);
+        let (errors, out) = helper_check(build_check);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            out.lines().collect::<Vec<&str>>(),
+            [
+                format!("cargo:rerun-if-env-changed={var}"),
+                format!("cargo:warning={var} is not set, skipping kernel config check"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Application defined checks.
+//!
+//! Requirements that cannot be expressed as a kernel config option, such as
+//! "`/dev/kvm` is accessible", can be implemented with the [`Check`] trait and
+//! registered with [`Kcheck::with_check`](crate::Kcheck::with_check). Their
+//! results are reported alongside the kernel config option results.
+
+use crate::{
+    error::{KcheckError, KcheckResult},
+    kconfig::{KconfigState, Severity},
+    kernel::KernelConfig,
+    util, CheckResult,
+};
+use std::path::{Path, PathBuf};
+
+/// System information available to a [`Check`] while it is evaluated.
+#[derive(Clone, Debug)]
+pub struct CheckContext<'a> {
+    /// The kernel config being checked.
+    kernel: &'a KernelConfig,
+    /// The root of the filesystem being checked.
+    root: &'a Path,
+}
+
+impl<'a> CheckContext<'a> {
+    /// Create a new context for a kernel config and a filesystem root.
+    pub fn new(kernel: &'a KernelConfig, root: &'a Path) -> Self {
+        Self { kernel, root }
+    }
+
+    /// The kernel config being checked.
+    pub fn kernel(&self) -> &KernelConfig {
+        self.kernel
+    }
+
+    /// Get the state of a kernel config option.
+    pub fn kernel_option(&self, option: &str) -> KcheckResult<KconfigState> {
+        self.kernel.option(option)
+    }
+
+    /// Resolve an absolute path relative to the root of the filesystem being checked.
+    pub fn path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let relative = path.as_ref().strip_prefix("/").unwrap_or(path.as_ref());
+        self.root.join(relative)
+    }
+
+    /// Returns `true` if the path exists in the filesystem being checked.
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.path(path).exists()
+    }
+
+    /// Read the contents of a file in the filesystem being checked.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> KcheckResult<String> {
+        util::file_contents_as_string(self.path(path))
+    }
+
+    /// Read a runtime kernel parameter, such as `net.ipv4.ip_forward`.
+    ///
+    /// Returns `None` if the parameter does not exist.
+    pub fn sysctl(&self, name: &str) -> KcheckResult<Option<String>> {
+        let path = PathBuf::from("/proc/sys").join(name.replace('.', "/"));
+
+        match self.read_to_string(path) {
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(KcheckError::FileDoesNotExist(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The outcome of evaluating a [`Check`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckOutcome {
+    /// Whether the check passed.
+    result: CheckResult,
+    /// A description of the observed state of the system.
+    actual: String,
+}
+
+impl CheckOutcome {
+    /// Create a new outcome from a result and the observed state of the system.
+    pub fn new(result: CheckResult, actual: &str) -> Self {
+        Self {
+            result,
+            actual: actual.to_string(),
+        }
+    }
+
+    /// A passing outcome.
+    pub fn pass(actual: &str) -> Self {
+        Self::new(CheckResult::Pass, actual)
+    }
+
+    /// A failing outcome.
+    pub fn fail(actual: &str) -> Self {
+        Self::new(CheckResult::Fail, actual)
+    }
+
+    /// Whether the check passed.
+    pub fn result(&self) -> CheckResult {
+        self.result
+    }
+
+    /// A description of the observed state of the system.
+    pub fn actual(&self) -> String {
+        self.actual.clone()
+    }
+}
+
+/// An application defined requirement.
+pub trait Check {
+    /// Name of the requirement.
+    fn name(&self) -> String;
+
+    /// A short description of the desired state of the system.
+    fn description(&self) -> String;
+
+    /// The importance of the requirement being met.
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// How to fix the system when the requirement is not met, such as
+    /// `sysctl -w kernel.kptr_restrict=2` or a kernel command line argument.
+    fn remediation(&self) -> Option<String> {
+        None
+    }
+
+    /// A link to more information about the requirement.
+    fn url(&self) -> Option<String> {
+        None
+    }
+
+    /// Determine if the requirement is met.
+    fn evaluate(&self, ctx: &CheckContext) -> KcheckResult<CheckOutcome>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kernel::KernelConfigBuilder;
+
+    #[test]
+    fn success_check_context_path() {
+        let kernel = KernelConfig::default();
+        let ctx = CheckContext::new(&kernel, Path::new("/tmp/rootfs"));
+
+        assert_eq!(ctx.path("/dev/kvm"), PathBuf::from("/tmp/rootfs/dev/kvm"));
+        assert_eq!(ctx.path("dev/kvm"), PathBuf::from("/tmp/rootfs/dev/kvm"));
+    }
+
+    #[test]
+    fn success_check_context_sysctl() {
+        let kernel = KernelConfig::default();
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
+
+        let sysctl_dir = root.path().join("proc/sys/net/ipv4");
+        std::fs::create_dir_all(&sysctl_dir).expect("Failed to create sysctl dir");
+        std::fs::write(sysctl_dir.join("ip_forward"), "1\n").expect("Failed to write sysctl");
+
+        let ctx = CheckContext::new(&kernel, root.path());
+        assert_eq!(
+            ctx.sysctl("net.ipv4.ip_forward").unwrap(),
+            Some("1".to_string())
+        );
+        assert_eq!(ctx.sysctl("net.ipv4.does_not_exist").unwrap(), None);
+    }
+
+    #[test]
+    fn success_check_context_kernel_option() {
+        let kernel = KernelConfigBuilder::default()
+            .option("CONFIG_KVM", KconfigState::On)
+            .build()
+            .expect("Expected to build a kernel config");
+        let ctx = CheckContext::new(&kernel, Path::new("/"));
+
+        assert_eq!(ctx.kernel_option("CONFIG_KVM").unwrap(), KconfigState::On);
+    }
+}
@@ -6,32 +6,107 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::{
+    diagnostic::{Diagnostic, SourceMap},
     error::{KcheckError, KcheckResult},
-    kconfig::{KconfigOption, KconfigState},
+    kconfig::{KconfigOption, KconfigState, Severity},
+    schema,
+    target::When,
     util,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 use typed_builder::TypedBuilder;
 
 const ETC_KCHECK_TOML: &str = "/etc/kcheck.toml";
 const ETC_KCHECK_JSON: &str = "/etc/kcheck.json";
+#[cfg(feature = "yaml")]
+const ETC_KCHECK_YAML: &str = "/etc/kcheck.yaml";
+
+/// The line of a config file that a requirement is declared on.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Declaration {
+    file: PathBuf,
+    line: usize,
+}
+
+impl Declaration {
+    pub(crate) fn new(file: &Path, line: usize) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line,
+        }
+    }
+
+    /// The config file that declares the requirement.
+    pub fn file(&self) -> PathBuf {
+        self.file.clone()
+    }
+
+    /// Line number, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl std::fmt::Display for Declaration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file.to_string_lossy(), self.line)
+    }
+}
+
+/// The declaration of a requirement, if it was read from a file.
+///
+/// Always equal, so that requirements are equal if they describe the same
+/// requirement, wherever they are declared.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Declared(pub(crate) Option<Declaration>);
+
+impl PartialEq for Declared {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
 
 /// A fragment of a [`KcheckConfig`].
 ///
 /// A fragment represents a collection of config options that are potentially related.
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, TypedBuilder)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize, TypedBuilder)]
+#[schemars(deny_unknown_fields)]
 pub struct KcheckConfigFragment {
     /// Fragment name.
     name: Option<String>,
     /// A short description of the reason fragment options are selected.
     reason: Option<String>,
     /// A list of kernel options that are a part of this fragment.
+    #[serde(default)]
     kernel: Vec<KconfigOption>,
+    /// Only check the fragment on some architectures or kernel versions.
+    #[builder(default)]
+    when: Option<When>,
+    /// Profiles the fragment belongs to.
+    ///
+    /// A fragment without profiles belongs to every profile.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    profiles: Vec<String>,
+    /// Groups that pass if at least one of their options is in the desired state.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    any_of: Vec<KconfigGroup>,
+    /// Groups that pass if all of their options are in the desired state.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    all_of: Vec<KconfigGroup>,
+    /// Groups that pass if none of their options are in the desired state.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    none_of: Vec<KconfigGroup>,
 }
 
 impl KcheckConfigFragment {
@@ -40,12 +115,38 @@ impl KcheckConfigFragment {
             name: Some(name),
             reason: Some(reason),
             kernel,
+            ..Default::default()
         }
     }
 
+    /// Only check the fragment when the condition is met.
+    pub fn with_when(mut self, when: When) -> Self {
+        self.when = Some(when);
+        self
+    }
+
+    /// Only check the fragment when one of the profiles is selected.
+    pub fn with_profiles(mut self, profiles: &[&str]) -> Self {
+        self.profiles = profiles.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// Add a group of kernel options that is checked with a [`Quantifier`].
+    pub fn with_group(mut self, quantifier: Quantifier, group: KconfigGroup) -> Self {
+        match quantifier {
+            Quantifier::AnyOf => self.any_of.push(group),
+            Quantifier::AllOf => self.all_of.push(group),
+            Quantifier::NoneOf => self.none_of.push(group),
+        }
+        self
+    }
+
     /// Check if the fragment is empty.
     pub fn is_empty(&self) -> bool {
-        self.name.is_none() && self.reason.is_none() && self.kernel.is_empty()
+        self.name.is_none()
+            && self.reason.is_none()
+            && self.kernel.is_empty()
+            && self.groups().is_empty()
     }
 
     /// Fragment name.
@@ -62,6 +163,135 @@ impl KcheckConfigFragment {
     pub fn kernel(&self) -> Vec<KconfigOption> {
         self.kernel.clone()
     }
+
+    /// The condition under which the fragment is checked.
+    pub fn when(&self) -> Option<When> {
+        self.when.clone()
+    }
+
+    /// Profiles the fragment belongs to.
+    pub fn profiles(&self) -> Vec<String> {
+        self.profiles.clone()
+    }
+
+    /// Returns `true` if the fragment is checked when `profile` is selected.
+    pub fn in_profile(&self, profile: &str) -> bool {
+        self.profiles.is_empty() || self.profiles.iter().any(|p| p == profile)
+    }
+
+    /// All groups of kernel options in the fragment along with their quantifier.
+    pub fn groups(&self) -> Vec<(Quantifier, &KconfigGroup)> {
+        let any_of = self.any_of.iter().map(|g| (Quantifier::AnyOf, g));
+        let all_of = self.all_of.iter().map(|g| (Quantifier::AllOf, g));
+        let none_of = self.none_of.iter().map(|g| (Quantifier::NoneOf, g));
+
+        any_of.chain(all_of).chain(none_of).collect()
+    }
+}
+
+/// How many options of a [`KconfigGroup`] must be in their desired state.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantifier {
+    /// At least one option must be in the desired state.
+    AnyOf,
+    /// Every option must be in the desired state.
+    AllOf,
+    /// No option may be in the desired state.
+    NoneOf,
+}
+
+impl Quantifier {
+    /// Returns `true` if the number of passing options satisfies the quantifier.
+    pub fn satisfied(&self, passed: usize, total: usize) -> bool {
+        match self {
+            Quantifier::AnyOf => passed > 0,
+            Quantifier::AllOf => passed == total,
+            Quantifier::NoneOf => passed == 0,
+        }
+    }
+}
+
+impl std::fmt::Display for Quantifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Quantifier::AnyOf => "Any of",
+            Quantifier::AllOf => "All of",
+            Quantifier::NoneOf => "None of",
+        };
+
+        write!(f, "{text}")
+    }
+}
+
+/// A group of alternative or related kernel options that is checked as a single requirement.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[schemars(deny_unknown_fields)]
+pub struct KconfigGroup {
+    /// Group name.
+    name: Option<String>,
+    /// The kernel options that are a part of this group.
+    kernel: Vec<KconfigOption>,
+    /// The importance of the group being satisfied.
+    ///
+    /// Defaults to [`Severity::Error`] when not provided.
+    severity: Option<Severity>,
+    /// Where the group is declared, if it was read from a file.
+    #[serde(skip)]
+    #[schemars(skip)]
+    declaration: Declared,
+}
+
+impl KconfigGroup {
+    /// Create a new `KconfigGroup`.
+    pub fn new(kernel: Vec<KconfigOption>) -> Self {
+        KconfigGroup {
+            kernel,
+            ..Default::default()
+        }
+    }
+
+    /// Set the name of the group.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set the severity of the group.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    /// Get the name of the group.
+    ///
+    /// Unnamed groups are named after their options.
+    pub fn name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self
+                .kernel
+                .iter()
+                .map(|o| o.name())
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    }
+
+    /// Get the kernel options that are a part of this group.
+    pub fn kernel(&self) -> Vec<KconfigOption> {
+        self.kernel.clone()
+    }
+
+    /// Get the severity of the group.
+    pub fn severity(&self) -> Severity {
+        self.severity.unwrap_or_default()
+    }
+
+    /// Get the line of the config file that declares the group.
+    pub fn declaration(&self) -> Option<Declaration> {
+        self.declaration.0.clone()
+    }
 }
 
 #[derive(Clone, Debug, Default, TypedBuilder)]
@@ -71,6 +301,8 @@ pub struct KcheckConfigBuilder {
     fragment: Option<Vec<KcheckConfigFragment>>,
     use_sys_cfg: bool,
     user_cfg_files: Vec<PathBuf>,
+    #[cfg(feature = "elf")]
+    binaries: Vec<PathBuf>,
 }
 
 impl KcheckConfigBuilder {
@@ -106,8 +338,29 @@ impl KcheckConfigBuilder {
         self
     }
 
+    /// Add configs embedded in ELF binaries to build [`KcheckConfig`].
+    ///
+    /// Binaries that do not contain an embedded config are ignored.
+    #[cfg(feature = "elf")]
+    pub fn binaries<P: AsRef<Path>>(mut self, files: Vec<P>) -> Self {
+        for item in files {
+            self.binaries.push(item.as_ref().to_path_buf());
+        }
+        self
+    }
+
     /// Build a [`KcheckConfig`] object from the provided configuration.
     pub fn build(self) -> KcheckResult<KcheckConfig> {
+        self.build_with_files().map(|(config, _)| config)
+    }
+
+    /// Build a [`KcheckConfig`] object and return every config file that was read,
+    /// including the files that were included or extended.
+    ///
+    /// A file that is included more than once is only read and merged once.
+    pub fn build_with_files(self) -> KcheckResult<(KcheckConfig, Vec<PathBuf>)> {
+        let mut loader = Loader::default();
+
         // Collection of config files and fragments
         let mut collection: Vec<KcheckConfig> = Vec::new();
 
@@ -118,6 +371,11 @@ impl KcheckConfigBuilder {
             Vec::new()
         };
 
+        #[cfg(feature = "yaml")]
+        if self.use_sys_cfg {
+            fragments.push(ETC_KCHECK_YAML.to_owned());
+        }
+
         // Collect all fragments into a single vector
         for item in self.user_cfg_files {
             let item_path = item.to_string_lossy().to_string();
@@ -130,7 +388,7 @@ impl KcheckConfigBuilder {
         }
 
         for fragment in fragments {
-            match KcheckConfig::try_from_file(fragment) {
+            match KcheckConfig::load(Path::new(&fragment), &mut loader) {
                 Ok(cfg) => collection.push(cfg),
                 Err(e) => match e {
                     KcheckError::FileDoesNotExist(_) => continue,
@@ -139,6 +397,14 @@ impl KcheckConfigBuilder {
             }
         }
 
+        // Process configs embedded in binaries
+        #[cfg(feature = "elf")]
+        for binary in self.binaries {
+            if let Some(cfg) = crate::embed::scan_elf(binary)? {
+                collection.push(cfg);
+            }
+        }
+
         // Process API based fragments
         if self.name.is_some() || self.kernel.is_some() || self.fragment.is_some() {
             let mut api_fragment = KcheckConfig::default();
@@ -166,38 +432,295 @@ impl KcheckConfigBuilder {
                 combined.append(&mut item);
             }
 
-            Ok(combined)
+            Ok((combined, loader.files))
         } else {
             Err(KcheckError::NoConfig)
         }
     }
 }
 
+/// Files seen while loading config files and the files they include or extend.
+#[derive(Debug, Default)]
+struct Loader {
+    /// Canonical paths of the files currently being loaded, used to detect cycles.
+    stack: Vec<PathBuf>,
+    /// Canonical paths of the files that were loaded.
+    loaded: Vec<PathBuf>,
+    /// Paths of the files that were loaded, as they were read.
+    files: Vec<PathBuf>,
+}
+
 /// A structure representing a desired kernel checking configuration.
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[schemars(deny_unknown_fields)]
 pub struct KcheckConfig {
+    /// The JSON Schema that editors use to validate the file.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub(crate) schema: Option<String>,
     /// Global `kcheck` config name.
     pub(crate) name: Option<String>,
     /// Global `kcheck` kernel options that have not been grouped into fragments.
     pub(crate) kernel: Option<Vec<KconfigOption>>,
     /// Groups of kernel options that are related.
     pub(crate) fragment: Option<Vec<KcheckConfigFragment>>,
+    /// Config files that are merged into this config.
+    ///
+    /// Relative paths are resolved from the directory of the including file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<PathBuf>,
+    /// A base config that this config inherits its options from.
+    ///
+    /// Options in this config replace base options with the same name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) extends: Option<PathBuf>,
+    /// Names of options inherited from the base config that are not checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) remove: Vec<String>,
 }
 
 impl KcheckConfig {
+    /// Read a [`KcheckConfig`] from a TOML or JSON file, or a YAML file with the `yaml` feature.
+    ///
+    /// Configs that are included or extended by the file are read and merged
+    /// into the returned config.
     pub fn try_from_file<P: AsRef<Path>>(path: P) -> KcheckResult<Self> {
-        let contents = util::file_contents_as_string(path.as_ref())?;
+        Self::load(path.as_ref(), &mut Loader::default())
+    }
 
-        let cfg: KcheckConfig = match path.as_ref().extension().and_then(OsStr::to_str) {
-            Some("toml") => toml::from_str(&contents)?,
-            Some("json") => serde_json::from_str(&contents)?,
-            Some(f) => return Err(KcheckError::UnknownFileType(f.to_string())),
-            None => return Err(KcheckError::MissingFileExtension),
+    /// Write the config in the YAML format read by [`try_from_file`](Self::try_from_file).
+    ///
+    /// States that carry a value are written with tags, such as `state: !Text "box"`.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> String {
+        use yaml_rust2::YamlEmitter;
+
+        let value = serde_json::to_value(self).expect("Expected the config to serialize");
+
+        // The emitter does not write tags, so tagged states are written as
+        // placeholders that are replaced afterwards
+        let json = value.to_string();
+        let mut prefix = "kcheck_tag_".to_string();
+        while json.contains(&prefix) {
+            prefix.insert(0, '_');
+        }
+
+        let mut tags = Vec::new();
+        let yaml = json_to_yaml(value, &prefix, &mut tags);
+
+        let mut out = String::new();
+        YamlEmitter::new(&mut out)
+            .dump(&yaml)
+            .expect("Expected the config to serialize");
+        for (i, tag) in tags.iter().enumerate().rev() {
+            out = out.replace(&format!("{prefix}{i}"), tag);
+        }
+
+        out.push('\n');
+        out
+    }
+
+    /// Read a single config file without resolving `include` or `extends`.
+    ///
+    /// The file is validated against the [schema](crate::schema) before it is deserialized.
+    fn parse_file(path: &Path) -> KcheckResult<Self> {
+        let contents = util::file_contents_as_string(path)?;
+
+        let toml_error = |e: toml::de::Error| {
+            KcheckError::TomlParseError(Diagnostic::from_toml(&e, &contents).with_file(path))
+        };
+        let json_error = |e: serde_json::Error| {
+            KcheckError::JsonParseError(Diagnostic::from_json(&e, &contents).with_file(path))
         };
 
+        let (mut cfg, source_map): (KcheckConfig, SourceMap) =
+            match path.extension().and_then(OsStr::to_str) {
+                Some("toml") => {
+                    let value: toml::Value = toml::from_str(&contents).map_err(toml_error)?;
+                    let source_map = SourceMap::toml(&contents);
+                    Self::validate(&serde_json::to_value(value)?, path, &source_map)?;
+                    (toml::from_str(&contents).map_err(toml_error)?, source_map)
+                }
+                Some("json") => {
+                    let value = serde_json::from_str(&contents).map_err(json_error)?;
+                    let source_map = SourceMap::json(&contents);
+                    Self::validate(&value, path, &source_map)?;
+                    (
+                        serde_json::from_str(&contents).map_err(json_error)?,
+                        source_map,
+                    )
+                }
+                #[cfg(feature = "yaml")]
+                Some("yaml" | "yml") => {
+                    let (value, source_map) = SourceMap::yaml(&contents).map_err(|e| {
+                        KcheckError::YamlParseError(
+                            Diagnostic::from_yaml(&e, &contents).with_file(path),
+                        )
+                    })?;
+                    Self::validate(&value, path, &source_map)?;
+                    let cfg = serde_json::from_value(value).map_err(|e| {
+                        KcheckError::YamlParseError(Diagnostic::from(e).with_file(path))
+                    })?;
+                    (cfg, source_map)
+                }
+                Some(f) => return Err(KcheckError::UnknownFileType(f.to_string())),
+                None => return Err(KcheckError::MissingFileExtension),
+            };
+
+        cfg.declare(path, &source_map);
         Ok(cfg)
     }
 
+    /// Record the line of `path` that declares each option and group.
+    fn declare(&mut self, path: &Path, source_map: &SourceMap) {
+        let declaration = |p: String| {
+            source_map
+                .locate(&p)
+                .map(|l| Declaration::new(path, l.line()))
+        };
+
+        for (i, option) in self.kernel.iter_mut().flatten().enumerate() {
+            option.set_declaration(declaration(format!("kernel[{i}]")));
+        }
+
+        for (i, fragment) in self.fragment.iter_mut().flatten().enumerate() {
+            for (j, option) in fragment.kernel.iter_mut().enumerate() {
+                option.set_declaration(declaration(format!("fragment[{i}].kernel[{j}]")));
+            }
+
+            let groups = [
+                ("any_of", &mut fragment.any_of),
+                ("all_of", &mut fragment.all_of),
+                ("none_of", &mut fragment.none_of),
+            ];
+            for (key, groups) in groups {
+                for (j, group) in groups.iter_mut().enumerate() {
+                    let group_path = format!("fragment[{i}].{key}[{j}]");
+                    group.declaration = Declared(declaration(group_path.clone()));
+
+                    for (k, option) in group.kernel.iter_mut().enumerate() {
+                        option.set_declaration(declaration(format!("{group_path}.kernel[{k}]")));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Validate a config against the [schema](crate::schema) and locate the errors in the file.
+    fn validate(
+        value: &serde_json::Value,
+        path: &Path,
+        source_map: &SourceMap,
+    ) -> KcheckResult<()> {
+        schema::validate(value).map_err(|e| match e {
+            KcheckError::SchemaValidation(errors) => KcheckError::SchemaValidation(
+                errors
+                    .into_iter()
+                    .map(|d| {
+                        let location = source_map.locate(&d.path().unwrap_or_default());
+                        let d = d.with_file(path);
+                        match location {
+                            Some(location) => d.with_location(location),
+                            None => d,
+                        }
+                    })
+                    .collect(),
+            ),
+            e => e,
+        })
+    }
+
+    /// Read a config file and resolve the configs it includes or extends.
+    ///
+    /// Files that `loader` has already loaded, such as a file included by two
+    /// other files, are only merged once. A base named by `extends` is read
+    /// again for every config that extends it, so that each config applies its
+    /// own overrides and removals to a complete copy of the base.
+    fn load(path: &Path, loader: &mut Loader) -> KcheckResult<Self> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if loader.stack.contains(&canonical) {
+            let cycle = loader
+                .stack
+                .iter()
+                .skip_while(|p| **p != canonical)
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            return Err(KcheckError::IncludeCycle(cycle));
+        }
+
+        if loader.loaded.contains(&canonical) {
+            return Ok(Self::default());
+        }
+
+        let mut cfg = Self::parse_file(path)?;
+        if !cfg.remove.is_empty() && cfg.extends.is_none() {
+            return Err(KcheckError::RemoveWithoutExtends(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+        loader.loaded.push(canonical.clone());
+        loader.files.push(path.to_path_buf());
+        loader.stack.push(canonical);
+
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let resolve = |p: &Path| -> KcheckResult<PathBuf> {
+            let file = dir.join(p);
+            match file.exists() {
+                true => Ok(file),
+                false => Err(KcheckError::IncludeNotFound(
+                    file.to_string_lossy().to_string(),
+                )),
+            }
+        };
+
+        // Included configs come before the options of the including file
+        let mut combined = KcheckConfig::default();
+        for include in std::mem::take(&mut cfg.include) {
+            let mut included = Self::load(&resolve(&include)?, loader)?;
+            combined.append(&mut included);
+        }
+        combined.name = cfg.name.take();
+        combined.append(&mut cfg);
+
+        if let Some(extends) = cfg.extends.take() {
+            let mut base = Self::load_base(&resolve(&extends)?, loader)?;
+
+            let mut removed = std::mem::take(&mut cfg.remove);
+            removed.extend(combined.option_names());
+            base.remove_options(&removed);
+
+            combined.name = combined.name.or(base.name.take());
+            base.append(&mut combined);
+            base.name = combined.name;
+            combined = base;
+        }
+
+        loader.stack.pop();
+        Ok(combined)
+    }
+
+    /// Read the base of a config that extends it, regardless of which files
+    /// `loader` has already loaded.
+    ///
+    /// The base and its includes are loaded with their own set of loaded files
+    /// while still sharing the stack of `loader` to detect cycles.
+    fn load_base(path: &Path, loader: &mut Loader) -> KcheckResult<Self> {
+        let mut scoped = Loader {
+            stack: std::mem::take(&mut loader.stack),
+            ..Default::default()
+        };
+        let base = Self::load(path, &mut scoped);
+        loader.stack = scoped.stack;
+
+        for file in scoped.files {
+            if !loader.files.contains(&file) {
+                loader.files.push(file);
+            }
+        }
+        base
+    }
+
     /// Move all the configuration data from `other` into `self`.
     ///
     /// The resulting [`KcheckConfig`] object will have the global name from
@@ -210,6 +733,78 @@ impl KcheckConfig {
         self.fragment = new_fragment;
     }
 
+    /// Names of every kernel option, including the members of groups.
+    fn option_names(&self) -> Vec<String> {
+        let members = self
+            .fragment
+            .iter()
+            .flatten()
+            .flat_map(|f| f.groups())
+            .flat_map(|(_, g)| g.kernel.iter().map(|o| o.name()));
+
+        self.options()
+            .iter()
+            .map(|o| o.name())
+            .chain(members)
+            .collect()
+    }
+
+    /// Remove kernel options by name, including the members of groups.
+    ///
+    /// Groups and fragments that are left without any requirements are removed as well.
+    fn remove_options(&mut self, names: &[String]) {
+        if let Some(kernel) = &mut self.kernel {
+            kernel.retain(|o| !names.contains(&o.name()));
+        }
+
+        if let Some(fragments) = &mut self.fragment {
+            for fragment in fragments.iter_mut() {
+                fragment.kernel.retain(|o| !names.contains(&o.name()));
+
+                for groups in [
+                    &mut fragment.any_of,
+                    &mut fragment.all_of,
+                    &mut fragment.none_of,
+                ] {
+                    for group in groups.iter_mut() {
+                        group.kernel.retain(|o| !names.contains(&o.name()));
+                    }
+                    groups.retain(|g| !g.kernel.is_empty());
+                }
+            }
+
+            fragments.retain(|f| !f.kernel.is_empty() || !f.groups().is_empty());
+        }
+    }
+
+    /// All profiles declared by the fragments of the config.
+    pub fn profiles(&self) -> Vec<String> {
+        let mut profiles: Vec<String> = self
+            .fragment
+            .iter()
+            .flatten()
+            .flat_map(|f| f.profiles.iter().cloned())
+            .collect();
+        profiles.sort();
+        profiles.dedup();
+        profiles
+    }
+
+    /// Only keep the fragments that belong to `profile`.
+    ///
+    /// Kernel options that are not a part of a fragment are always kept.
+    pub fn select_profile(&mut self, profile: &str) -> KcheckResult<()> {
+        if !self.profiles().iter().any(|p| p == profile) {
+            return Err(KcheckError::UnknownProfile(profile.to_string()));
+        }
+
+        if let Some(fragments) = &mut self.fragment {
+            fragments.retain(|f| f.in_profile(profile));
+        }
+
+        Ok(())
+    }
+
     /// Returns `true` if the [`KcheckConfig`] is empty.
     ///
     /// An empty [`KcheckConfig`] has no name, kernel options, or fragments.
@@ -226,6 +821,93 @@ impl KcheckConfig {
 
         self.name.is_none() && kernel_is_empty && fragment_is_empty
     }
+
+    /// Returns all kernel options along with the fragment they are grouped into.
+    pub(crate) fn requirements(&self) -> Vec<(Option<&KcheckConfigFragment>, &KconfigOption)> {
+        let mut requirements: Vec<(Option<&KcheckConfigFragment>, &KconfigOption)> = self
+            .kernel
+            .iter()
+            .flatten()
+            .map(|option| (None, option))
+            .collect();
+
+        for fragment in self.fragment.iter().flatten() {
+            requirements.extend(
+                fragment
+                    .kernel
+                    .iter()
+                    .map(|option| (Some(fragment), option)),
+            );
+        }
+
+        requirements
+    }
+
+    /// Returns all kernel options, including the options grouped into fragments.
+    pub fn options(&self) -> Vec<KconfigOption> {
+        let mut kernel: Vec<KconfigOption> = self.kernel.clone().unwrap_or_default();
+
+        if let Some(fragments) = &self.fragment {
+            kernel.extend(fragments.iter().flat_map(|f| f.kernel.iter().cloned()));
+        }
+
+        kernel
+    }
+}
+
+/// Convert a JSON value to YAML for [`KcheckConfig::to_yaml`].
+///
+/// Unset fields are left out. States with a value, such as `{ "Text": "box" }`,
+/// are replaced with a placeholder starting with `prefix`, and the tagged state
+/// is added to `tags`.
+#[cfg(feature = "yaml")]
+fn json_to_yaml(
+    value: serde_json::Value,
+    prefix: &str,
+    tags: &mut Vec<String>,
+) -> yaml_rust2::Yaml {
+    use serde_json::Value;
+    use yaml_rust2::{yaml::Hash, Yaml};
+
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::String(s) => Yaml::String(s),
+        Value::Array(items) => Yaml::Array(
+            items
+                .into_iter()
+                .map(|v| json_to_yaml(v, prefix, tags))
+                .collect(),
+        ),
+        Value::Object(map) => {
+            let mut hash = Hash::new();
+            for (key, value) in map.into_iter().filter(|(_, v)| !v.is_null()) {
+                let value = match (key.as_str(), value) {
+                    ("state", Value::Object(state)) if state.len() == 1 => {
+                        let (tag, value) = state.into_iter().next().expect("Expected a state");
+                        tags.push(format!("!{tag} {value}"));
+                        Yaml::String(format!("{prefix}{}", tags.len() - 1))
+                    }
+                    (_, value) => json_to_yaml(value, prefix, tags),
+                };
+                hash.insert(Yaml::String(key), value);
+            }
+            Yaml::Hash(hash)
+        }
+    }
+}
+
+impl FromStr for KcheckConfig {
+    type Err = KcheckError;
+
+    /// Parse a TOML formatted [`KcheckConfig`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| KcheckError::TomlParseError(Diagnostic::from_toml(&e, s)))
+    }
 }
 
 impl IntoIterator for KcheckConfig {
@@ -233,15 +915,7 @@ impl IntoIterator for KcheckConfig {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        let mut kernel: Vec<KconfigOption> = self.kernel.unwrap_or_default();
-
-        let fragments = match self.fragment {
-            Some(f) => f.into_iter().flat_map(|f| f.kernel.into_iter()).collect(),
-            None => Vec::new(),
-        };
-
-        kernel.extend(fragments);
-        kernel
+        self.options()
             .iter()
             .map(|f| (f.name().clone(), f.state()))
             .collect::<Vec<(String, KconfigState)>>()
@@ -329,6 +1003,7 @@ mod test {
                 vec![TEST_FRAGMENT_MODULE.clone()],
             ),
         ]),
+        ..Default::default()
     });
 
     static EXPECTED_KCHECK_CONFIG_MULTIPLE_FILES: LazyLock<KcheckConfig> =
@@ -352,6 +1027,7 @@ mod test {
                     vec![TEST_FRAGMENT_ENABLED.clone()],
                 ),
             ]),
+            ..Default::default()
         });
 
     #[test]
@@ -441,6 +1117,567 @@ mod test {
         });
     }
 
+    #[test]
+    fn success_kcheck_config_declarations() {
+        let contents =
+            format!("[[kernel]]\nname = \"CONFIG_HZ\"\nstate = \"On\"\n{TEST_GROUP_CONTENTS}");
+
+        util::run_with_tmpfile("test.toml", &contents, |file_path| {
+            let cfg =
+                KcheckConfig::try_from_file(&file_path).expect("Failed to build config from file");
+
+            let option = &cfg.kernel.as_ref().expect("Expected kernel options")[0];
+            let declaration = option.declaration().expect("Expected a declaration");
+            assert_eq!(declaration.file(), file_path);
+            assert_eq!(declaration.line(), 1);
+
+            let fragment = &cfg.fragment.as_ref().expect("Expected fragments")[0];
+            let groups = fragment.groups();
+            assert_eq!(groups[0].1.declaration().map(|d| d.line()), Some(8));
+            assert_eq!(
+                groups[0].1.kernel()[1].declaration().map(|d| d.line()),
+                Some(12)
+            );
+            assert_eq!(
+                groups[1].1.kernel()[0].declaration().map(|d| d.line()),
+                Some(17)
+            );
+        });
+
+        // Configs that are not read from a file have no declarations
+        let cfg = KcheckConfig::from_str(&contents).expect("Failed to parse config");
+        assert_eq!(cfg.options()[0].declaration(), None);
+    }
+
+    const TEST_GROUP_CONTENTS: &str = r#"
+    [[fragment]]
+    name = "lsm"
+
+    [[fragment.any_of]]
+    name = "security-module"
+    kernel = [
+        { name = "CONFIG_SECURITY_SELINUX", state = "On" },
+        { name = "CONFIG_SECURITY_APPARMOR", state = "On" },
+    ]
+
+    [[fragment.none_of]]
+    severity = "Warning"
+    kernel = [{ name = "CONFIG_DEVMEM", state = "Enabled" }]
+    "#;
+
+    #[test]
+    fn success_kcheck_config_groups() {
+        let cfg = KcheckConfig::from_str(TEST_GROUP_CONTENTS).expect("Failed to parse config");
+        let fragment = cfg.fragment.as_ref().expect("Expected fragments")[0].clone();
+
+        let expected = KcheckConfigFragment::builder()
+            .name(Some("lsm".to_string()))
+            .reason(None)
+            .kernel(Vec::new())
+            .build()
+            .with_group(
+                Quantifier::AnyOf,
+                KconfigGroup::new(vec![
+                    KconfigOption::new("CONFIG_SECURITY_SELINUX", KconfigState::On),
+                    KconfigOption::new("CONFIG_SECURITY_APPARMOR", KconfigState::On),
+                ])
+                .with_name("security-module"),
+            )
+            .with_group(
+                Quantifier::NoneOf,
+                KconfigGroup::new(vec![KconfigOption::new(
+                    "CONFIG_DEVMEM",
+                    KconfigState::Enabled,
+                )])
+                .with_severity(Severity::Warning),
+            );
+        assert_eq!(fragment, expected);
+        assert!(!fragment.is_empty());
+
+        let groups = fragment.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, Quantifier::AnyOf);
+        assert_eq!(groups[0].1.name(), "security-module");
+        assert_eq!(groups[1].0, Quantifier::NoneOf);
+        assert_eq!(groups[1].1.name(), "CONFIG_DEVMEM");
+        assert_eq!(groups[1].1.severity(), Severity::Warning);
+    }
+
+    const TEST_BASE_CONTENTS: &str = r#"
+    name = "base"
+    include = ["common/usb.toml"]
+
+    [[fragment]]
+    name = "hardening"
+
+    [[fragment.kernel]]
+    name = "CONFIG_STRICT_DEVMEM"
+    state = "On"
+
+    [[fragment.kernel]]
+    name = "CONFIG_DEVMEM"
+    state = "Off"
+    "#;
+
+    const TEST_USB_CONTENTS: &str = r#"
+    [[fragment]]
+    name = "usb"
+
+    [[fragment.kernel]]
+    name = "CONFIG_USB_ACM"
+    state = "On"
+    "#;
+
+    const TEST_BOARD_CONTENTS: &str = r#"
+    name = "board"
+    extends = "base.toml"
+    remove = ["CONFIG_USB_ACM"]
+
+    [[kernel]]
+    name = "CONFIG_DEVMEM"
+    state = "On"
+    "#;
+
+    fn helper_write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (name, contents) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().expect("Expected a parent directory"))
+                .expect("Failed to create directory");
+            std::fs::write(path, contents).expect("Failed to write file");
+        }
+    }
+
+    #[test]
+    fn success_kcheck_config_include() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        helper_write_files(
+            tmpdir.path(),
+            &[
+                ("base.toml", TEST_BASE_CONTENTS),
+                ("common/usb.toml", TEST_USB_CONTENTS),
+            ],
+        );
+
+        let cfg = KcheckConfig::try_from_file(tmpdir.path().join("base.toml"))
+            .expect("Failed to build config from file");
+        assert_eq!(cfg.name, Some("base".to_string()));
+        assert!(cfg.include.is_empty());
+
+        let names: Vec<String> = cfg.options().iter().map(|o| o.name()).collect();
+        assert_eq!(
+            names,
+            ["CONFIG_USB_ACM", "CONFIG_STRICT_DEVMEM", "CONFIG_DEVMEM"]
+        );
+    }
+
+    #[test]
+    fn success_kcheck_config_extends() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        helper_write_files(
+            tmpdir.path(),
+            &[
+                ("base.toml", TEST_BASE_CONTENTS),
+                ("common/usb.toml", TEST_USB_CONTENTS),
+                (
+                    "boards/board.toml",
+                    TEST_BOARD_CONTENTS
+                        .replace("base.toml", "../base.toml")
+                        .as_str(),
+                ),
+            ],
+        );
+
+        let cfg = KcheckConfig::try_from_file(tmpdir.path().join("boards/board.toml"))
+            .expect("Failed to build config from file");
+        assert_eq!(cfg.name, Some("board".to_string()));
+        assert!(cfg.extends.is_none() && cfg.remove.is_empty());
+
+        let options = cfg.options();
+        assert_eq!(
+            options,
+            [
+                KconfigOption::new("CONFIG_DEVMEM", KconfigState::On),
+                KconfigOption::new("CONFIG_STRICT_DEVMEM", KconfigState::On),
+            ]
+        );
+
+        // The usb fragment has no options left and is removed
+        let fragments: Vec<Option<String>> =
+            cfg.fragment.iter().flatten().map(|f| f.name()).collect();
+        assert_eq!(fragments, [Some("hardening".to_string())]);
+    }
+
+    #[test]
+    fn success_kcheck_config_include_once() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        helper_write_files(
+            tmpdir.path(),
+            &[
+                ("a.toml", "include = [\"b.toml\", \"c.toml\"]"),
+                ("b.toml", "include = [\"d.toml\"]"),
+                ("c.toml", "include = [\"d.toml\"]"),
+                ("d.toml", TEST_USB_CONTENTS),
+            ],
+        );
+
+        let (cfg, files) = KcheckConfigBuilder::default()
+            .config_files(vec![tmpdir.path().join("a.toml")])
+            .build_with_files()
+            .expect("Failed to build config from file");
+
+        let names: Vec<String> = cfg.options().iter().map(|o| o.name()).collect();
+        assert_eq!(names, ["CONFIG_USB_ACM"]);
+
+        let files: Vec<String> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, ["a.toml", "b.toml", "d.toml", "c.toml"]);
+    }
+
+    #[test]
+    fn success_kcheck_config_extends_shared_base() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        helper_write_files(
+            tmpdir.path(),
+            &[
+                ("base.toml", TEST_USB_CONTENTS),
+                ("common.toml", "include = [\"base.toml\"]"),
+                (
+                    "board-a.toml",
+                    "extends = \"base.toml\"\nremove = [\"CONFIG_USB_ACM\"]",
+                ),
+                (
+                    "board-b.toml",
+                    r#"
+                    extends = "base.toml"
+
+                    [[kernel]]
+                    name = "CONFIG_USB_ACM"
+                    state = "Module"
+                    "#,
+                ),
+            ],
+        );
+
+        // Each config that extends the base applies its own changes, even after
+        // the base was already loaded by an include
+        let (cfg, files) = KcheckConfigBuilder::default()
+            .config_files(vec![
+                tmpdir.path().join("common.toml"),
+                tmpdir.path().join("board-a.toml"),
+                tmpdir.path().join("board-b.toml"),
+            ])
+            .build_with_files()
+            .expect("Failed to build config from file");
+
+        assert_eq!(
+            cfg.options(),
+            [
+                KconfigOption::new("CONFIG_USB_ACM", KconfigState::Module),
+                KconfigOption::new("CONFIG_USB_ACM", KconfigState::On),
+            ]
+        );
+
+        let files: Vec<String> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            files,
+            ["common.toml", "base.toml", "board-a.toml", "board-b.toml"]
+        );
+    }
+
+    #[test]
+    fn success_kcheck_config_extends_group_member() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        helper_write_files(
+            tmpdir.path(),
+            &[
+                (
+                    "base.toml",
+                    r#"
+                    [[fragment]]
+                    name = "lsm"
+
+                    [[fragment.any_of]]
+                    kernel = [
+                        { name = "CONFIG_SECURITY_SELINUX", state = "On" },
+                        { name = "CONFIG_SECURITY_APPARMOR", state = "On" },
+                    ]
+
+                    [[fragment.none_of]]
+                    kernel = [{ name = "CONFIG_DEVMEM", state = "On" }]
+                    "#,
+                ),
+                (
+                    "board.toml",
+                    r#"
+                    extends = "base.toml"
+
+                    [[kernel]]
+                    name = "CONFIG_SECURITY_APPARMOR"
+                    state = "Off"
+
+                    [[kernel]]
+                    name = "CONFIG_DEVMEM"
+                    state = "Off"
+                    "#,
+                ),
+            ],
+        );
+
+        let cfg = KcheckConfig::try_from_file(tmpdir.path().join("board.toml"))
+            .expect("Failed to build config from file");
+
+        // The redefined member is replaced, and the group left empty is removed
+        let fragments = cfg.fragment.expect("Expected fragments");
+        let groups = fragments[0].groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].1.name(), "CONFIG_SECURITY_SELINUX");
+    }
+
+    #[test]
+    fn fail_kcheck_config_include_cycle() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        helper_write_files(
+            tmpdir.path(),
+            &[
+                ("a.toml", "include = [\"b.toml\"]"),
+                ("b.toml", "extends = \"a.toml\""),
+            ],
+        );
+
+        let result = KcheckConfig::try_from_file(tmpdir.path().join("a.toml"));
+        assert!(matches!(result, Err(KcheckError::IncludeCycle(_))));
+    }
+
+    #[test]
+    fn fail_kcheck_config_remove_without_extends() {
+        util::run_with_tmpfile("test.toml", "remove = [\"CONFIG_DEVMEM\"]", |file_path| {
+            let result = KcheckConfig::try_from_file(&file_path);
+            assert_eq!(
+                result,
+                Err(KcheckError::RemoveWithoutExtends(
+                    file_path.to_string_lossy().to_string()
+                ))
+            );
+        });
+    }
+
+    #[test]
+    fn fail_kcheck_config_include_not_found() {
+        util::run_with_tmpfile("test.toml", "include = [\"missing.toml\"]", |file_path| {
+            let result = KcheckConfig::try_from_file(&file_path);
+            let expected = file_path.with_file_name("missing.toml");
+            assert_eq!(
+                result,
+                Err(KcheckError::IncludeNotFound(
+                    expected.to_string_lossy().to_string()
+                ))
+            );
+        });
+    }
+
+    const TEST_PROFILE_CONTENTS: &str = r#"
+    [[kernel]]
+    name = "CONFIG_MODULES"
+    state = "On"
+
+    [[fragment]]
+    name = "debug"
+    profiles = ["development"]
+    kernel = [{ name = "CONFIG_DEBUG_INFO", state = "On" }]
+
+    [[fragment]]
+    name = "hardening"
+    profiles = ["production", "certification"]
+    kernel = [{ name = "CONFIG_DEBUG_INFO", state = "Disabled" }]
+
+    [[fragment]]
+    name = "common"
+    kernel = [{ name = "CONFIG_USB_ACM", state = "On" }]
+    "#;
+
+    #[test]
+    fn success_kcheck_config_select_profile() {
+        let cfg = KcheckConfig::from_str(TEST_PROFILE_CONTENTS).expect("Failed to parse config");
+        assert_eq!(
+            cfg.profiles(),
+            ["certification", "development", "production"]
+        );
+
+        let mut production = cfg.clone();
+        production
+            .select_profile("production")
+            .expect("Expected a known profile");
+        let fragments: Vec<Option<String>> = production
+            .fragment
+            .iter()
+            .flatten()
+            .map(|f| f.name())
+            .collect();
+        assert_eq!(
+            fragments,
+            [Some("hardening".to_string()), Some("common".to_string())]
+        );
+        assert_eq!(production.kernel, cfg.kernel);
+
+        let mut development = cfg;
+        development
+            .select_profile("development")
+            .expect("Expected a known profile");
+        assert_eq!(
+            development.options(),
+            [
+                KconfigOption::new("CONFIG_MODULES", KconfigState::On),
+                KconfigOption::new("CONFIG_DEBUG_INFO", KconfigState::On),
+                KconfigOption::new("CONFIG_USB_ACM", KconfigState::On),
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_kcheck_config_unknown_profile() {
+        let mut cfg =
+            KcheckConfig::from_str(TEST_PROFILE_CONTENTS).expect("Failed to parse config");
+        assert_eq!(
+            cfg.select_profile("staging"),
+            Err(KcheckError::UnknownProfile("staging".to_string()))
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    const TEST_YAML_CONTENTS: &str = r#"
+    name: GLOBAL_TEST
+    fragment:
+      - name: TEST_FRAGMENT
+        reason: Testing
+        kernel:
+          - name: CONFIG_TEST_OPTION_ON
+            state: On
+          - name: CONFIG_TEST_OPTION_OFF
+            state: Off
+      - name: TEST_FRAGMENT_TWO
+        reason: Testing
+        kernel:
+          - name: CONFIG_TEST_OPTION_MODULE
+            state: Module
+    "#;
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn success_kcheck_config_try_from_yaml_file() {
+        for filename in ["test.yaml", "test.yml"] {
+            util::run_with_tmpfile(filename, TEST_YAML_CONTENTS, |file_path| {
+                let cfg = KcheckConfig::try_from_file(file_path)
+                    .expect("Failed to build config from file");
+                assert_eq!(cfg, *EXPECTED_KCHECK_CONFIG);
+            });
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    const TEST_YAML_TAGGED_CONTENTS: &str = r#"
+    kernel:
+      - name: CONFIG_LOCALVERSION
+        state: !Text -test
+        when:
+          arch: [arm64]
+          kernel: ">=6.1"
+        if: CONFIG_MODULES
+      - name: CONFIG_HZ
+        state: !Regex "[0-9]+"
+    fragment:
+      - name: lsm
+        any_of:
+          - name: security-module
+            kernel:
+              - { name: CONFIG_SECURITY_SELINUX, state: On }
+              - { name: CONFIG_SECURITY_APPARMOR, state: On }
+        none_of:
+          - severity: Warning
+            kernel: [{ name: CONFIG_DEVMEM, state: Enabled }]
+    "#;
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn success_kcheck_config_yaml_tagged_states() {
+        let mut expected =
+            KcheckConfig::from_str(TEST_GROUP_CONTENTS).expect("Failed to parse config");
+        expected.kernel = Some(vec![
+            KconfigOption::new(
+                "CONFIG_LOCALVERSION",
+                KconfigState::Text("-test".to_string()),
+            )
+            .with_when(When::default().arch(&["arm64"]).kernel(">=6.1").unwrap())
+            .with_condition("CONFIG_MODULES".parse().unwrap()),
+            KconfigOption::new("CONFIG_HZ", KconfigState::Regex("[0-9]+".to_string())),
+        ]);
+
+        util::run_with_tmpfile("test.yaml", TEST_YAML_TAGGED_CONTENTS, |file_path| {
+            let parsed =
+                KcheckConfig::try_from_file(file_path).expect("Failed to build config from file");
+            assert_eq!(parsed, expected);
+        });
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn success_kcheck_config_yaml_round_trip() {
+        let contents = format!("{TEST_GROUP_CONTENTS}\n{TEST_PROFILE_CONTENTS}");
+        let mut cfg = KcheckConfig::from_str(&contents).expect("Failed to parse config");
+        cfg.kernel = Some(vec![
+            KconfigOption::new(
+                "CONFIG_LOCALVERSION",
+                KconfigState::Text("-test: \"quoted\"".to_string()),
+            )
+            .with_when(When::default().arch(&["arm64"]).kernel(">=6.1").unwrap())
+            .with_condition("CONFIG_MODULES".parse().unwrap()),
+            KconfigOption::new("CONFIG_HZ", KconfigState::Regex("[0-9]+".to_string())),
+        ]);
+
+        let yaml = cfg.to_yaml();
+        assert!(yaml.contains("state: !Regex \"[0-9]+\""));
+
+        util::run_with_tmpfile("test.yaml", &yaml, |file_path| {
+            let parsed =
+                KcheckConfig::try_from_file(file_path).expect("Failed to build config from file");
+            assert_eq!(parsed, cfg);
+        });
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn fail_kcheck_config_try_from_yaml_file() {
+        util::run_with_tmpfile("test.yaml", "fragment: [", |file_path| {
+            let result = KcheckConfig::try_from_file(file_path);
+            assert!(matches!(result, Err(KcheckError::YamlParseError(_))));
+        });
+    }
+
+    #[test]
+    fn fail_kcheck_config_try_from_file_schema() {
+        let contents =
+            "[[fragment]]\nname = \"test\"\nkernel = [{ name = \"CONFIG_A\", stat = \"On\" }]";
+        util::run_with_tmpfile("test.toml", contents, |file_path| {
+            let errors = match KcheckConfig::try_from_file(&file_path) {
+                Err(KcheckError::SchemaValidation(errors)) => errors,
+                result => panic!("Expected schema errors, found {result:?}"),
+            };
+
+            assert_eq!(errors.len(), 2);
+            assert_eq!(errors[0].path().as_deref(), Some("fragment[0].kernel[0]"));
+            assert_eq!(errors[0].message(), "missing field `state`");
+            assert_eq!(errors[0].file(), Some(file_path.clone()));
+
+            let location = errors[1].location().expect("Expected a location");
+            assert_eq!((location.line(), location.column()), (3, 39));
+            assert_eq!(location.snippet(), contents.lines().nth(2).unwrap());
+        });
+    }
+
     #[test]
     fn fail_kcheck_config_try_from_file_does_not_exist() {
         let result = KcheckConfig::try_from_file(PathBuf::from("kcheck-no-exist.toml"));
@@ -0,0 +1,864 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Source-located diagnostics for config file errors.
+//!
+//! A [`Diagnostic`] describes a problem in a config file along with the file,
+//! line, and column where it was found. Diagnostics can be rendered in the
+//! style of compiler errors with [`Diagnostic::render`].
+
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "yaml")]
+use yaml_rust2::{scanner::Marker, ScanError};
+
+/// A position in a source file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// Line number, starting at 1.
+    line: usize,
+    /// Column number in characters, starting at 1.
+    column: usize,
+    /// Number of characters to underline.
+    len: usize,
+    /// The contents of the line.
+    snippet: String,
+}
+
+impl Location {
+    /// Create a location that underlines a whole line.
+    pub(crate) fn whole_line(line: usize, snippet: &str) -> Self {
+        let snippet = snippet.trim_end().to_string();
+        Self {
+            line,
+            column: 1,
+            len: snippet.chars().count().max(1),
+            snippet,
+        }
+    }
+
+    /// Find the location of a byte range in `contents`.
+    pub(crate) fn from_span(contents: &str, span: Range<usize>) -> Self {
+        let start = floor_char_boundary(contents, span.start);
+        let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = contents[start..]
+            .find('\n')
+            .map_or(contents.len(), |i| start + i);
+        let end = floor_char_boundary(contents, span.end.clamp(start, line_end));
+
+        Self {
+            line: contents[..start].matches('\n').count() + 1,
+            column: contents[line_start..start].chars().count() + 1,
+            len: contents[start..end].chars().count().max(1),
+            snippet: contents[line_start..line_end].trim_end().to_string(),
+        }
+    }
+
+    /// Find the location of a line and column (both starting at 1) in `contents`.
+    ///
+    /// The rest of the line is underlined.
+    #[cfg(feature = "yaml")]
+    pub(crate) fn from_line_column(contents: &str, line: usize, column: usize) -> Self {
+        let snippet = contents
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or_default()
+            .trim_end()
+            .to_string();
+        let len = snippet.chars().count().saturating_sub(column - 1).max(1);
+
+        Self {
+            line,
+            column,
+            len,
+            snippet,
+        }
+    }
+
+    /// Line number, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column number, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The contents of the line.
+    pub fn snippet(&self) -> String {
+        self.snippet.clone()
+    }
+}
+
+/// A problem found in a config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    message: String,
+    file: Option<PathBuf>,
+    location: Option<Box<Location>>,
+    path: Option<String>,
+}
+
+impl Diagnostic {
+    /// Create a new diagnostic without a location.
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            file: None,
+            location: None,
+            path: None,
+        }
+    }
+
+    /// Set the file the diagnostic was found in.
+    pub fn with_file<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.file = Some(file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the location of the diagnostic in its file.
+    pub fn with_location(mut self, location: Location) -> Self {
+        self.location = Some(Box::new(location));
+        self
+    }
+
+    /// Set the path to the offending value, such as `fragment[0].kernel[1].state`.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// A description of the problem.
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// The file the problem was found in.
+    pub fn file(&self) -> Option<PathBuf> {
+        self.file.clone()
+    }
+
+    /// The position of the problem in the file.
+    pub fn location(&self) -> Option<Location> {
+        self.location.as_deref().cloned()
+    }
+
+    /// The path to the offending value, for problems found by schema validation.
+    pub fn path(&self) -> Option<String> {
+        self.path.clone()
+    }
+
+    /// Render the diagnostic with the offending line and a caret under the problem.
+    ///
+    /// ```text
+    /// error: unknown variant `Yes`, expected one of `On`, `Off`
+    ///  --> kcheck.toml:7:9
+    ///   |
+    /// 7 | state = "Yes"
+    ///   |         ^^^^^
+    ///   = note: at `fragment[0].kernel[1].state`
+    /// ```
+    pub fn render(&self) -> String {
+        let mut out = format!("error: {}\n", self.message);
+        let file = self
+            .file
+            .as_ref()
+            .map_or("<input>".to_string(), |f| f.to_string_lossy().to_string());
+
+        let width = self
+            .location
+            .as_ref()
+            .map_or(0, |l| l.line.to_string().len());
+        let pad = " ".repeat(width);
+
+        match &self.location {
+            Some(l) => {
+                out += &format!("{pad}--> {file}:{}:{}\n", l.line, l.column);
+                out += &format!("{pad} |\n");
+                out += &format!("{} | {}\n", l.line, l.snippet);
+                out += &format!(
+                    "{pad} | {}{}\n",
+                    " ".repeat(l.column - 1),
+                    "^".repeat(l.len)
+                );
+            }
+            None if self.file.is_some() => out += &format!("{pad}--> {file}\n"),
+            None => {}
+        }
+
+        if let Some(path) = &self.path {
+            out += &format!("{pad} = note: at `{path}`\n");
+        }
+
+        out
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.to_string_lossy())?;
+        }
+
+        if let Some(l) = &self.location {
+            write!(f, "{}:{}:", l.line, l.column)?;
+        }
+
+        if self.file.is_some() || self.location.is_some() {
+            write!(f, " ")?;
+        }
+
+        match &self.path {
+            Some(path) => write!(f, "{path}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<toml::de::Error> for Diagnostic {
+    fn from(e: toml::de::Error) -> Self {
+        Diagnostic::new(e.message())
+    }
+}
+
+impl From<serde_json::Error> for Diagnostic {
+    fn from(e: serde_json::Error) -> Self {
+        Diagnostic::new(&e.to_string())
+    }
+}
+
+impl Diagnostic {
+    /// Create a diagnostic from a TOML error in `contents`.
+    pub(crate) fn from_toml(e: &toml::de::Error, contents: &str) -> Self {
+        let diagnostic = Diagnostic::new(e.message());
+        match e.span() {
+            Some(span) => diagnostic.with_location(Location::from_span(contents, span)),
+            None => diagnostic,
+        }
+    }
+
+    /// Create a diagnostic from a JSON error in `contents`.
+    pub(crate) fn from_json(e: &serde_json::Error, contents: &str) -> Self {
+        // The message of a JSON error ends with its location
+        let message = e.to_string();
+        let message = match message.rfind(" at line ") {
+            Some(i) => &message[..i],
+            None => &message,
+        };
+
+        // JSON errors count columns in bytes
+        let diagnostic = Diagnostic::new(message);
+        match e.line() {
+            0 => diagnostic,
+            line => {
+                let line_start: usize = contents
+                    .split_inclusive('\n')
+                    .take(line - 1)
+                    .map(str::len)
+                    .sum();
+                let start = line_start + e.column().saturating_sub(1);
+                diagnostic.with_location(Location::from_span(contents, start..start + 1))
+            }
+        }
+    }
+
+    /// Create a diagnostic from a YAML error in `contents`.
+    #[cfg(feature = "yaml")]
+    pub(crate) fn from_yaml(e: &ScanError, contents: &str) -> Self {
+        let mark = e.marker();
+        Diagnostic::new(e.info()).with_location(Location::from_line_column(
+            contents,
+            mark.line(),
+            mark.col() + 1,
+        ))
+    }
+}
+
+/// A segment of a path such as `fragment[0].kernel`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Split a path such as `fragment[0].kernel[1].state` into its segments.
+fn segments(path: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|p| !p.is_empty()) {
+        let (key, indexes) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+
+        for index in indexes.split('[').filter_map(|i| i.strip_suffix(']')) {
+            if let Ok(i) = index.parse() {
+                segments.push(Segment::Index(i));
+            }
+        }
+    }
+
+    segments
+}
+
+/// Where a value starts in a config file.
+#[derive(Clone, Debug)]
+enum Position {
+    /// A range of bytes.
+    Span(Range<usize>),
+    /// A line and a column in characters, both starting at 1.
+    #[cfg(feature = "yaml")]
+    LineColumn(usize, usize),
+}
+
+/// The locations of the values in a config file.
+///
+/// The file is parsed once and the location of every value is recorded, so that
+/// any number of paths can be looked up.
+#[derive(Debug)]
+pub(crate) struct SourceMap<'a> {
+    contents: &'a str,
+    nodes: HashMap<Vec<Segment>, Position>,
+}
+
+impl<'a> SourceMap<'a> {
+    /// Record the locations of the values in a TOML document.
+    pub(crate) fn toml(contents: &'a str) -> Self {
+        use toml::{
+            de::{DeTable, DeValue},
+            Spanned,
+        };
+
+        fn visit(
+            nodes: &mut HashMap<Vec<Segment>, Position>,
+            path: &mut Vec<Segment>,
+            value: &Spanned<DeValue>,
+        ) {
+            nodes.insert(path.clone(), Position::Span(value.span()));
+            match value.get_ref() {
+                DeValue::Table(table) => {
+                    for (key, value) in table.iter() {
+                        path.push(Segment::Key(key.get_ref().to_string()));
+                        visit(nodes, path, value);
+                        path.pop();
+                    }
+                }
+                DeValue::Array(array) => {
+                    for (i, value) in array.iter().enumerate() {
+                        path.push(Segment::Index(i));
+                        visit(nodes, path, value);
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut nodes = HashMap::new();
+        if let Ok(table) = DeTable::parse(contents) {
+            let (span, table) = (table.span(), table.into_inner());
+            let root = Spanned::new(span, DeValue::Table(table));
+            visit(&mut nodes, &mut Vec::new(), &root);
+        }
+
+        Self { contents, nodes }
+    }
+
+    /// Record the locations of the values in a JSON document.
+    pub(crate) fn json(contents: &'a str) -> Self {
+        use jsonc_parser::{
+            ast::Value, common::Ranged, parse_to_ast, CollectOptions, ParseOptions,
+        };
+
+        fn visit(
+            nodes: &mut HashMap<Vec<Segment>, Position>,
+            path: &mut Vec<Segment>,
+            value: &Value,
+        ) {
+            let range = value.range();
+            nodes.insert(path.clone(), Position::Span(range.start..range.end));
+            match value {
+                Value::Object(object) => {
+                    for property in &object.properties {
+                        path.push(Segment::Key(property.name.as_str().to_string()));
+                        visit(nodes, path, &property.value);
+                        path.pop();
+                    }
+                }
+                Value::Array(array) => {
+                    for (i, value) in array.elements.iter().enumerate() {
+                        path.push(Segment::Index(i));
+                        visit(nodes, path, value);
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut nodes = HashMap::new();
+        let parsed = parse_to_ast(
+            contents,
+            &CollectOptions::default(),
+            &ParseOptions::default(),
+        );
+        if let Some(value) = parsed.ok().and_then(|p| p.value) {
+            visit(&mut nodes, &mut Vec::new(), &value);
+        }
+
+        Self { contents, nodes }
+    }
+
+    /// Parse a YAML document into a JSON value and record the locations of its values.
+    ///
+    /// Tagged values such as `!Text value` are converted to the `{ "Text": value }`
+    /// form used by other formats.
+    #[cfg(feature = "yaml")]
+    pub(crate) fn yaml(contents: &'a str) -> Result<(serde_json::Value, Self), ScanError> {
+        let mut receiver = YamlReceiver::default();
+        yaml_rust2::parser::Parser::new_from_str(contents).load(&mut receiver, false)?;
+        if let Some(e) = receiver.error {
+            return Err(e);
+        }
+
+        let value = receiver
+            .value
+            .unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new()));
+        let nodes = receiver.nodes;
+        Ok((value, Self { contents, nodes }))
+    }
+
+    /// Find the location of the value at `path`, such as `fragment[0].kernel[1].state`.
+    ///
+    /// Falls back to the closest parent if the value has no location, such as a
+    /// missing key.
+    pub(crate) fn locate(&self, path: &str) -> Option<Location> {
+        let mut path = segments(path);
+        loop {
+            if let Some(position) = self.nodes.get(&path) {
+                return Some(match position {
+                    Position::Span(span) => Location::from_span(self.contents, span.clone()),
+                    #[cfg(feature = "yaml")]
+                    Position::LineColumn(line, column) => {
+                        Location::from_line_column(self.contents, *line, *column)
+                    }
+                });
+            }
+
+            path.pop()?;
+        }
+    }
+}
+
+/// Builds a JSON value from the events of a YAML document and records the
+/// position of every node.
+#[cfg(feature = "yaml")]
+#[derive(Default)]
+struct YamlReceiver {
+    /// The value of the document, once it is complete.
+    value: Option<serde_json::Value>,
+    /// Path and position of each node.
+    nodes: HashMap<Vec<Segment>, Position>,
+    /// The containers that are currently open.
+    stack: Vec<YamlFrame>,
+    /// Values with an anchor, by anchor id.
+    anchors: HashMap<usize, serde_json::Value>,
+    /// The first problem found that the parser does not report.
+    error: Option<yaml_rust2::ScanError>,
+}
+
+/// A YAML container that is currently open.
+#[cfg(feature = "yaml")]
+struct YamlFrame {
+    /// The path of the values in the container.
+    path: Vec<Segment>,
+    /// The anchor id of the container, or 0.
+    anchor: usize,
+    /// The local tag of the container, such as `Text` for `!Text`.
+    tag: Option<String>,
+    /// The contents of the container.
+    value: YamlContainer,
+}
+
+#[cfg(feature = "yaml")]
+enum YamlContainer {
+    Mapping {
+        map: serde_json::Map<String, serde_json::Value>,
+        key: Option<String>,
+    },
+    Sequence(Vec<serde_json::Value>),
+}
+
+#[cfg(feature = "yaml")]
+impl YamlReceiver {
+    /// The path of the next node, or `None` if the next node is a mapping key.
+    fn path(&self) -> Option<Vec<Segment>> {
+        let Some(frame) = self.stack.last() else {
+            return Some(Vec::new());
+        };
+
+        let mut path = frame.path.clone();
+        match &frame.value {
+            YamlContainer::Mapping { key: None, .. } => return None,
+            YamlContainer::Mapping { key: Some(key), .. } => path.push(Segment::Key(key.clone())),
+            YamlContainer::Sequence(items) => path.push(Segment::Index(items.len())),
+        }
+
+        Some(path)
+    }
+
+    /// Record the position of a node, and of the value inside its tag.
+    fn node(&mut self, path: &[Segment], tag: Option<&str>, position: Position) {
+        if let Some(tag) = tag {
+            let mut tagged = path.to_vec();
+            tagged.push(Segment::Key(tag.to_string()));
+            self.nodes.insert(tagged, position.clone());
+        }
+        self.nodes.insert(path.to_vec(), position);
+    }
+
+    /// Add a complete value to the innermost container.
+    fn push(&mut self, value: serde_json::Value, anchor: usize, tag: Option<String>) {
+        let value = match tag {
+            Some(tag) => serde_json::Value::Object(serde_json::Map::from_iter([(tag, value)])),
+            None => value,
+        };
+
+        if anchor > 0 {
+            self.anchors.insert(anchor, value.clone());
+        }
+
+        match self.stack.last_mut().map(|f| &mut f.value) {
+            Some(YamlContainer::Mapping { map, key }) => {
+                if let Some(key) = key.take() {
+                    map.insert(key, value);
+                }
+            }
+            Some(YamlContainer::Sequence(items)) => items.push(value),
+            None => self.value = Some(value),
+        }
+    }
+
+    /// Open a container.
+    fn open(&mut self, anchor: usize, tag: Option<String>, value: YamlContainer, mark: Marker) {
+        let Some(mut path) = self.path() else {
+            self.error = Some(ScanError::new(mark, "mapping keys must be scalars"));
+            return;
+        };
+
+        self.node(&path, tag.as_deref(), position(mark));
+        if let Some(tag) = &tag {
+            path.push(Segment::Key(tag.clone()));
+        }
+
+        self.stack.push(YamlFrame {
+            path,
+            anchor,
+            tag,
+            value,
+        });
+    }
+
+    /// Close the innermost container.
+    fn close(&mut self) {
+        if let Some(frame) = self.stack.pop() {
+            let value = match frame.value {
+                YamlContainer::Mapping { map, .. } => serde_json::Value::Object(map),
+                YamlContainer::Sequence(items) => serde_json::Value::Array(items),
+            };
+            self.push(value, frame.anchor, frame.tag);
+        }
+    }
+}
+
+/// The position of a YAML marker.
+#[cfg(feature = "yaml")]
+fn position(mark: Marker) -> Position {
+    Position::LineColumn(mark.line(), mark.col() + 1)
+}
+
+/// The local tag of a YAML node, such as `Text` for `!Text`.
+#[cfg(feature = "yaml")]
+fn local_tag(tag: &Option<yaml_rust2::parser::Tag>) -> Option<String> {
+    tag.as_ref()
+        .filter(|t| t.handle == "!")
+        .map(|t| t.suffix.clone())
+}
+
+/// Convert a YAML scalar to JSON, resolving the type of plain scalars.
+#[cfg(feature = "yaml")]
+fn yaml_scalar(
+    value: String,
+    style: yaml_rust2::scanner::TScalarStyle,
+    tag: &Option<yaml_rust2::parser::Tag>,
+) -> serde_json::Value {
+    use yaml_rust2::{scanner::TScalarStyle, Yaml};
+
+    let is_str = tag
+        .as_ref()
+        .is_some_and(|t| t.handle == "!!" && t.suffix == "str");
+    if style != TScalarStyle::Plain || is_str {
+        return serde_json::Value::String(value);
+    }
+
+    match Yaml::from_str(&value) {
+        Yaml::Integer(i) => i.into(),
+        Yaml::Real(r) => r
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map_or(serde_json::Value::String(r), serde_json::Value::Number),
+        Yaml::Boolean(b) => b.into(),
+        Yaml::Null => serde_json::Value::Null,
+        _ => serde_json::Value::String(value),
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl yaml_rust2::parser::MarkedEventReceiver for YamlReceiver {
+    fn on_event(&mut self, ev: yaml_rust2::Event, mark: Marker) {
+        use yaml_rust2::Event;
+
+        if self.error.is_some() {
+            return;
+        }
+
+        match ev {
+            Event::Scalar(value, style, anchor, tag) => match self.path() {
+                Some(path) => {
+                    let local = local_tag(&tag);
+                    self.node(&path, local.as_deref(), position(mark));
+                    let value = yaml_scalar(value, style, &tag);
+                    self.push(value, anchor, local);
+                }
+                None => {
+                    if let Some(YamlContainer::Mapping { key, .. }) =
+                        self.stack.last_mut().map(|f| &mut f.value)
+                    {
+                        *key = Some(value);
+                    }
+                }
+            },
+            Event::Alias(anchor) => match self.path() {
+                Some(path) => {
+                    self.node(&path, None, position(mark));
+                    let value = self.anchors.get(&anchor).cloned().unwrap_or_default();
+                    self.push(value, 0, None);
+                }
+                None => self.error = Some(ScanError::new(mark, "mapping keys must be scalars")),
+            },
+            Event::MappingStart(anchor, tag) => {
+                let map = YamlContainer::Mapping {
+                    map: serde_json::Map::new(),
+                    key: None,
+                };
+                self.open(anchor, local_tag(&tag), map, mark);
+            }
+            Event::SequenceStart(anchor, tag) => {
+                self.open(
+                    anchor,
+                    local_tag(&tag),
+                    YamlContainer::Sequence(Vec::new()),
+                    mark,
+                );
+            }
+            Event::MappingEnd | Event::SequenceEnd => self.close(),
+            _ => {}
+        }
+    }
+}
+
+/// Round a byte index down to the nearest character boundary.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOML_CONTENTS: &str = r#"name = "test"
+
+[[fragment]]
+name = "lsm"
+
+[[fragment.kernel]]
+name = "CONFIG_A"
+state = "Yes"
+"#;
+
+    const JSON_CONTENTS: &str = r#"{
+  "kernel": [
+    { "name": "CONFIG_A", "state": "On" },
+    { "name": "CONFIG_B", "state": "Yes" }
+  ]
+}
+"#;
+
+    #[test]
+    fn success_render() {
+        let diagnostic = Diagnostic::new("unknown variant `Yes`")
+            .with_file("kcheck.toml")
+            .with_location(Location::from_span(TOML_CONTENTS, 88..93))
+            .with_path("fragment[0].kernel[0].state");
+
+        assert_eq!(
+            diagnostic.render(),
+            "error: unknown variant `Yes`\n \
+             --> kcheck.toml:8:9\n  \
+             |\n\
+             8 | state = \"Yes\"\n  \
+             |         ^^^^^\n  \
+             = note: at `fragment[0].kernel[0].state`\n"
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "kcheck.toml:8:9: fragment[0].kernel[0].state: unknown variant `Yes`"
+        );
+    }
+
+    #[test]
+    fn success_render_without_location() {
+        let diagnostic = Diagnostic::new("missing field `state`").with_path("kernel[0]");
+        assert_eq!(
+            diagnostic.render(),
+            "error: missing field `state`\n = note: at `kernel[0]`\n"
+        );
+        assert_eq!(diagnostic.to_string(), "kernel[0]: missing field `state`");
+    }
+
+    #[test]
+    fn success_locate_toml() {
+        let location = SourceMap::toml(TOML_CONTENTS)
+            .locate("fragment[0].kernel[0].state")
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (8, 9));
+        assert_eq!(location.snippet(), "state = \"Yes\"");
+
+        let location = SourceMap::toml(TOML_CONTENTS)
+            .locate("fragment[0]")
+            .expect("Expected a location");
+        assert_eq!(location.line(), 3);
+    }
+
+    #[test]
+    fn success_locate_json() {
+        let location = SourceMap::json(JSON_CONTENTS)
+            .locate("kernel[1].state")
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (4, 36));
+        assert_eq!(
+            location.snippet(),
+            r#"    { "name": "CONFIG_B", "state": "Yes" }"#
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn success_locate_yaml() {
+        let contents =
+            "kernel:\n  - name: CONFIG_A\n    state: On\n  - name: CONFIG_B\n    state: Yes\n";
+        let (_, source_map) = SourceMap::yaml(contents).expect("Expected valid YAML");
+        let location = source_map
+            .locate("kernel[1].state")
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (5, 12));
+        assert_eq!(location.snippet(), "    state: Yes");
+    }
+
+    #[test]
+    fn success_locate_json_escaped_key() {
+        let contents = "{\n  \"n\\u00e4me\": \"ä\", \"kernel\": [{ \"state\": \"Yes\" }]\n}\n";
+        let location = SourceMap::json(contents)
+            .locate("näme")
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (2, 16));
+
+        // Columns are counted in characters
+        let location = SourceMap::json(contents)
+            .locate("kernel[0].state")
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (2, 43));
+    }
+
+    #[test]
+    fn success_locate_parent() {
+        let location = SourceMap::json(JSON_CONTENTS)
+            .locate("kernel[1].missing")
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (4, 5));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn success_source_map_yaml_value() {
+        let contents = "base: &base\n  name: CONFIG_A\n  state: !Text \"on\"\nother: *base\ncount: 10\nenabled: true\nmissing: ~\nquoted: '10'\n";
+        let (value, map) = SourceMap::yaml(contents).expect("Expected valid YAML");
+
+        let base = serde_json::json!({ "name": "CONFIG_A", "state": { "Text": "on" } });
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "base": base,
+                "other": base,
+                "count": 10,
+                "enabled": true,
+                "missing": null,
+                "quoted": "10",
+            })
+        );
+
+        let location = map.locate("base.state.Text").expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (3, 16));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn fail_source_map_yaml() {
+        let contents = "kernel: [\n";
+        let e = SourceMap::yaml(contents).expect_err("Expected invalid YAML");
+        let location = Diagnostic::from_yaml(&e, contents)
+            .location()
+            .expect("Expected a location");
+        assert_eq!(location.line(), 2);
+    }
+
+    #[test]
+    fn success_from_toml() {
+        let contents = "[[kernel]\nname = \"CONFIG_A\"\n";
+        let e = toml::from_str::<toml::Value>(contents).unwrap_err();
+        let location = Diagnostic::from_toml(&e, contents)
+            .location()
+            .expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (1, 10));
+    }
+
+    #[test]
+    fn success_from_json() {
+        let contents = "{\n  \"name\": \"test\"\n  \"kernel\": []\n}\n";
+        let e = serde_json::from_str::<serde_json::Value>(contents).unwrap_err();
+        let diagnostic = Diagnostic::from_json(&e, contents);
+        let location = diagnostic.location().expect("Expected a location");
+        assert_eq!((location.line(), location.column()), (3, 3));
+        assert_eq!(diagnostic.message(), "expected `,` or `}`");
+
+        // JSON errors count columns in bytes, locations count characters
+        let contents = "{ \"ä\": 1 \"b\": 2 }";
+        let e = serde_json::from_str::<serde_json::Value>(contents).unwrap_err();
+        let location = Diagnostic::from_json(&e, contents)
+            .location()
+            .expect("Expected a location");
+        assert_eq!(location.column(), 10);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Embed `kcheck` requirements in application binaries.
+//!
+//! The [`embed_config!`](crate::embed_config) macro places a TOML formatted
+//! [`KcheckConfig`] in a dedicated section of the binary being built. Each
+//! embedded config is stored as a NUL terminated record, so a binary that
+//! links several crates using the macro will contain several records in the
+//! same section. The records can be recovered from a binary without running it
+//! using [`scan_elf`] (requires the `elf` feature).
+
+use crate::{
+    config::KcheckConfig,
+    error::{KcheckError, KcheckResult},
+};
+use std::str::FromStr;
+
+/// Name of the section used to store embedded configs in ELF binaries.
+pub const SECTION: &str = ".kcheck";
+
+/// Copy a config string into a NUL terminated byte array.
+///
+/// Used by [`embed_config!`](crate::embed_config) to build the record stored in the
+/// binary. `N` must be one byte larger than the config.
+#[doc(hidden)]
+pub const fn to_record<const N: usize>(config: &str) -> [u8; N] {
+    let bytes = config.as_bytes();
+    assert!(
+        bytes.len() + 1 == N,
+        "record must be one byte larger than the config"
+    );
+
+    let mut record = [0u8; N];
+    let mut i = 0;
+    while i < bytes.len() {
+        record[i] = bytes[i];
+        i += 1;
+    }
+
+    record
+}
+
+/// Embed a TOML formatted `kcheck` config in the binary.
+///
+/// The config is stored in the `.kcheck` section so that it can be extracted
+/// later with `kcheck-cli scan` or [`scan_elf`](crate::embed::scan_elf).
+///
+/// ```
+/// kcheck::embed_config!(
+///     r#"
+///     [[fragment]]
+///     name = "usb-serial"
+///     reason = "Serial USB support"
+///
+///     [[fragment.kernel]]
+///     name = "CONFIG_USB_ACM"
+///     state = "On"
+///     "#
+/// );
+/// ```
+///
+/// Any constant string expression can be used, such as `include_str!("kcheck.toml")`.
+#[macro_export]
+macro_rules! embed_config {
+    ($config:expr) => {
+        const _: () = {
+            const KCHECK_RECORD_LEN: usize = $config.len() + 1;
+
+            #[used]
+            #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__kcheck")]
+            #[cfg_attr(not(target_vendor = "apple"), link_section = ".kcheck")]
+            static KCHECK_RECORD: [u8; KCHECK_RECORD_LEN] =
+                $crate::embed::to_record::<KCHECK_RECORD_LEN>($config);
+        };
+    };
+}
+
+/// Parse the contents of an embedded config section.
+///
+/// Empty records are ignored since linkers may pad the section between records.
+pub fn parse_records(data: &[u8]) -> KcheckResult<Vec<KcheckConfig>> {
+    let mut configs = Vec::new();
+
+    for record in data.split(|b| *b == 0) {
+        if record.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let contents = std::str::from_utf8(record)
+            .map_err(|e| KcheckError::InvalidFile(format!("embedded config: {e}")))?;
+        configs.push(KcheckConfig::from_str(contents)?);
+    }
+
+    Ok(configs)
+}
+
+/// Extract the embedded configs from an ELF binary.
+///
+/// Returns `None` if the file is not an object file, such as a shell script, or
+/// does not contain a `.kcheck` section. Object files that cannot be parsed are
+/// an error.
+#[cfg(feature = "elf")]
+pub fn scan_elf<P: AsRef<std::path::Path>>(path: P) -> KcheckResult<Option<KcheckConfig>> {
+    use object::{FileKind, Object, ObjectSection};
+
+    let path_string = path.as_ref().to_string_lossy().to_string();
+    let contents = crate::util::file_contents_as_bytes(path.as_ref())?;
+    if FileKind::parse(&*contents).is_err() {
+        return Ok(None);
+    }
+
+    let file = object::File::parse(&*contents)
+        .map_err(|e| KcheckError::InvalidFile(format!("{path_string}: {e}")))?;
+
+    let data = match file.section_by_name(SECTION) {
+        Some(section) => section
+            .data()
+            .map_err(|e| KcheckError::InvalidFile(format!("{path_string}: {e}")))?,
+        None => return Ok(None),
+    };
+
+    let mut configs = parse_records(data)?;
+    if configs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut combined = configs.remove(0);
+    for mut item in configs {
+        combined.append(&mut item);
+    }
+
+    Ok(Some(combined))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kconfig::{KconfigOption, KconfigState};
+
+    const TEST_RECORD: &str = r#"
+    [[kernel]]
+    name = "CONFIG_EMBED_TEST"
+    state = "On"
+    "#;
+
+    crate::embed_config!(TEST_RECORD);
+
+    #[test]
+    fn success_to_record() {
+        let record = to_record::<4>("abc");
+        assert_eq!(record, *b"abc\0");
+    }
+
+    #[test]
+    fn success_parse_records() {
+        let data = format!("{TEST_RECORD}\0\0\0{TEST_RECORD}\0");
+        let configs = parse_records(data.as_bytes()).expect("Expected to parse records");
+
+        assert_eq!(configs.len(), 2);
+        for cfg in configs {
+            assert_eq!(
+                cfg.kernel,
+                Some(vec![KconfigOption::new(
+                    "CONFIG_EMBED_TEST",
+                    KconfigState::On
+                )])
+            );
+        }
+    }
+
+    #[test]
+    fn fail_parse_records_invalid_toml() {
+        let result = parse_records(b"not = [valid\0");
+        assert!(matches!(result, Err(KcheckError::TomlParseError(_))));
+    }
+
+    #[cfg(all(feature = "elf", target_os = "linux"))]
+    #[test]
+    fn success_scan_elf_current_exe() {
+        let exe = std::env::current_exe().expect("Expected to find the test binary");
+        let cfg = scan_elf(exe)
+            .expect("Expected to scan the test binary")
+            .expect("Expected the test binary to contain an embedded config");
+
+        let kernel = cfg.kernel.expect("Expected embedded kernel options");
+        assert!(kernel.contains(&KconfigOption::new("CONFIG_EMBED_TEST", KconfigState::On)));
+    }
+
+    #[cfg(feature = "elf")]
+    #[test]
+    fn success_scan_elf_not_object() {
+        crate::util::run_with_tmpfile("not-elf", "#!/bin/sh\n", |path| {
+            assert_eq!(scan_elf(path), Ok(None));
+        });
+    }
+
+    #[cfg(all(feature = "elf", target_os = "linux"))]
+    #[test]
+    fn success_scan_binaries_with_scripts() {
+        let exe = std::env::current_exe().expect("Expected to find the test binary");
+        crate::util::run_with_tmpfile("script.sh", "#!/bin/sh\necho hello\n", |script| {
+            let cfg = crate::config::KcheckConfigBuilder::default()
+                .binaries(vec![script, exe.clone()])
+                .build()
+                .expect("Expected scripts to be skipped");
+
+            let kernel = cfg.kernel.expect("Expected embedded kernel options");
+            assert!(kernel.contains(&KconfigOption::new("CONFIG_EMBED_TEST", KconfigState::On)));
+        });
+    }
+
+    #[cfg(feature = "elf")]
+    #[test]
+    fn fail_scan_elf_corrupt() {
+        let mut contents = b"\x7fELF\x02\x01\x01".to_vec();
+        contents.resize(32, 0xff);
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("corrupt");
+        std::fs::write(&path, contents).unwrap();
+
+        assert!(matches!(scan_elf(&path), Err(KcheckError::InvalidFile(_))));
+    }
+}
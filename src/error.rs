@@ -5,6 +5,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::diagnostic::Diagnostic;
 use thiserror::Error;
 
 pub type KcheckResult<T> = Result<T, KcheckError>;
@@ -17,28 +18,54 @@ pub enum KcheckError {
     FileDoesNotExist(String),
     #[error("File is not a valid: {0}")]
     InvalidFile(String),
+    #[error("Git error: {0}")]
+    Git(String),
+    #[error("Config include cycle: {0}")]
+    IncludeCycle(String),
+    #[error("Included config does not exist: {0}")]
+    IncludeNotFound(String),
+    #[error("Invalid condition: {0}")]
+    InvalidCondition(String),
     #[error("IO Error: {0}")]
     IoError(String),
     #[error("Error parsing json file: {0}")]
-    JsonParseError(String),
+    JsonParseError(Diagnostic),
     #[error("Error building KernelConfig: {0}")]
     KernelConfigBuildError(String),
+    #[error("Kconfig parse error: {0}")]
+    KconfigParseError(String),
     #[error("Kernel config not found")]
     KernelConfigNotFound,
-    #[error("Kernel config parse error")]
-    KernelConfigParseError,
+    #[error("Kernel config parse error: {0}")]
+    KernelConfigParseError(Diagnostic),
     #[error("No file extension found")]
     MissingFileExtension,
     #[error("Could not find a config file")]
     NoConfig,
     #[error("Regex error: {0}")]
     Regex(#[from] regex::Error),
+    #[error("Config removes options without extending a base config: {0}")]
+    RemoveWithoutExtends(String),
+    #[error("Config does not match the schema:{}", .0.iter().map(|e| format!("\n  {e}")).collect::<String>())]
+    SchemaValidation(Vec<Diagnostic>),
     #[error("Error parsing toml file: {0}")]
-    TomlParseError(#[from] toml::de::Error),
+    TomlParseError(Diagnostic),
+    #[error("Unknown duplicate policy: {0}, expected one of `error`, `last`, `first`")]
+    UnknownDuplicatePolicy(String),
+    #[error("Unknown enabled preference: {0}, expected one of `y`, `m`")]
+    UnknownEnabledPreference(String),
     #[error("Unknown file type: {0}")]
     UnknownFileType(String),
+    #[error("Unknown profile: {0}")]
+    UnknownProfile(String),
     #[error("Unknown kernel config option: {0}")]
     UnknownKernelConfigOption(String),
+    #[error("Unknown severity: {0}, expected one of `error`, `warning`, `info`")]
+    UnknownSeverity(String),
+    #[error("Unknown sort key: {0}, expected one of `name`, `result`, `fragment`")]
+    UnknownSortKey(String),
+    #[error("Error parsing yaml file: {0}")]
+    YamlParseError(Diagnostic),
 }
 
 impl From<std::io::Error> for KcheckError {
@@ -49,6 +76,26 @@ impl From<std::io::Error> for KcheckError {
 
 impl From<serde_json::Error> for KcheckError {
     fn from(e: serde_json::Error) -> Self {
-        KcheckError::JsonParseError(e.to_string())
+        KcheckError::JsonParseError(e.into())
+    }
+}
+
+impl From<toml::de::Error> for KcheckError {
+    fn from(e: toml::de::Error) -> Self {
+        KcheckError::TomlParseError(e.into())
+    }
+}
+
+impl KcheckError {
+    /// The source-located diagnostics of a parse or validation error.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            KcheckError::JsonParseError(d)
+            | KcheckError::KernelConfigParseError(d)
+            | KcheckError::TomlParseError(d)
+            | KcheckError::YamlParseError(d) => vec![d.clone()],
+            KcheckError::SchemaValidation(d) => d.clone(),
+            _ => Vec::new(),
+        }
     }
 }
@@ -0,0 +1,300 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Filter and sort check results.
+//!
+//! Frontends apply a [`ResultFilter`] to the results of
+//! [`Kcheck::perform_check`](crate::Kcheck::perform_check) before printing them, so that
+//! a large set of requirements can be narrowed down to the results of interest.
+
+use crate::{
+    error::{KcheckError, KcheckResult},
+    kconfig::{self, Severity},
+    CheckResult, KcheckConfigResult,
+};
+use std::str::FromStr;
+
+/// The order to sort results in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    /// By option or check name.
+    Name,
+    /// Errors first, then failures, skipped results, and passes.
+    Result,
+    /// By fragment name, results without a fragment last.
+    Fragment,
+}
+
+impl FromStr for SortKey {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortKey::Name),
+            "result" => Ok(SortKey::Result),
+            "fragment" => Ok(SortKey::Fragment),
+            s => Err(KcheckError::UnknownSortKey(s.to_string())),
+        }
+    }
+}
+
+/// Select and order check results.
+///
+/// Results have to match every filter that is set. Filters that take several
+/// values, such as [`ResultFilter::fragment`], match a result if any value matches.
+#[derive(Clone, Debug, Default)]
+pub struct ResultFilter {
+    only_failures: bool,
+    fragments: Vec<String>,
+    options: Vec<String>,
+    severity: Option<Severity>,
+    sort: Option<SortKey>,
+}
+
+impl ResultFilter {
+    /// Only keep results that failed or could not be checked.
+    pub fn only_failures(mut self) -> Self {
+        self.only_failures = true;
+        self
+    }
+
+    /// Only keep results of a fragment.
+    pub fn fragment(mut self, name: &str) -> Self {
+        self.fragments.push(name.to_string());
+        self
+    }
+
+    /// Only keep results of options matching a name, a glob such as `CONFIG_USB_*`,
+    /// or a regular expression enclosed in slashes.
+    ///
+    /// Groups match if their name or the name of any of their options matches.
+    pub fn option(mut self, pattern: &str) -> Self {
+        self.options.push(pattern.to_string());
+        self
+    }
+
+    /// Only keep results with at least this severity.
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    /// Sort the results. The order of results that compare equal is kept.
+    pub fn sort(mut self, key: SortKey) -> Self {
+        self.sort = Some(key);
+        self
+    }
+
+    /// Filter and sort results.
+    ///
+    /// Returns an error if an option pattern is not a valid regular expression.
+    pub fn apply(&self, results: Vec<KcheckConfigResult>) -> KcheckResult<Vec<KcheckConfigResult>> {
+        let patterns = self
+            .options
+            .iter()
+            .map(|o| Ok((o.as_str(), kconfig::name_pattern(o)?)))
+            .collect::<KcheckResult<Vec<_>>>()?;
+
+        let matches_option = |name: String| {
+            patterns.iter().any(|(option, pattern)| match pattern {
+                Some(pattern) => pattern.is_match(&name),
+                None => name == *option,
+            })
+        };
+
+        let mut results: Vec<KcheckConfigResult> = results
+            .into_iter()
+            .filter(|r| {
+                !self.only_failures || matches!(r.result(), CheckResult::Fail | CheckResult::Error)
+            })
+            .filter(|r| {
+                self.fragments.is_empty()
+                    || r.fragment().is_some_and(|f| self.fragments.contains(&f))
+            })
+            .filter(|r| {
+                patterns.is_empty()
+                    || matches_option(r.name())
+                    || r.members().into_iter().any(|m| matches_option(m.name()))
+            })
+            .filter(|r| self.severity.is_none_or(|s| r.severity() >= s))
+            .collect();
+
+        match self.sort {
+            Some(SortKey::Name) => results.sort_by_key(|r| r.name()),
+            Some(SortKey::Result) => results.sort_by_key(|r| rank(r.result())),
+            Some(SortKey::Fragment) => {
+                results.sort_by_key(|r| (r.fragment().is_none(), r.fragment()))
+            }
+            None => {}
+        }
+
+        Ok(results)
+    }
+}
+
+/// The position of a check result when sorting by result, most severe first.
+fn rank(result: CheckResult) -> u8 {
+    match result {
+        CheckResult::Error => 0,
+        CheckResult::Fail => 1,
+        CheckResult::Skipped => 2,
+        CheckResult::Pass => 3,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::KcheckConfig, kernel::KernelConfig, Kcheck};
+
+    const TEST_KCHECK_CONFIG: &str = r#"
+        [[kernel]]
+        name = "CONFIG_LOCALVERSION"
+        state = { Text = "-board" }
+        severity = "Info"
+
+        [[fragment]]
+        name = "usb"
+        kernel = [
+            { name = "CONFIG_USB", state = "On" },
+            { name = "CONFIG_USB_ACM", state = "Enabled" },
+        ]
+
+        [[fragment.any_of]]
+        name = "usb-host"
+        kernel = [
+            { name = "CONFIG_USB_EHCI", state = "On" },
+            { name = "CONFIG_USB_XHCI", state = "On" },
+        ]
+
+        [[fragment]]
+        name = "debug"
+        kernel = [{ name = "CONFIG_DEBUG", state = "Off", severity = "Warning" }]
+    "#;
+
+    const TEST_KERNEL_CONFIG: &str = "\
+CONFIG_USB=y
+# CONFIG_USB_ACM is not set
+CONFIG_USB_XHCI=y
+CONFIG_DEBUG=y
+";
+
+    fn helper_results() -> Vec<KcheckConfigResult> {
+        let config = KcheckConfig::from_str(TEST_KCHECK_CONFIG).expect("Expected a config");
+        let kernel = KernelConfig::from_str(TEST_KERNEL_CONFIG).expect("Expected a kernel config");
+        Kcheck::new(config, kernel)
+            .perform_check()
+            .expect("Expected to check the kernel config")
+    }
+
+    fn helper_names(filter: ResultFilter) -> Vec<String> {
+        filter
+            .apply(helper_results())
+            .expect("Expected to filter the results")
+            .iter()
+            .map(|r| r.name())
+            .collect()
+    }
+
+    #[test]
+    fn success_filter() {
+        assert_eq!(helper_names(ResultFilter::default()).len(), 5);
+        assert_eq!(
+            helper_names(ResultFilter::default().only_failures()),
+            ["CONFIG_LOCALVERSION", "CONFIG_USB_ACM", "CONFIG_DEBUG"]
+        );
+        assert_eq!(
+            helper_names(ResultFilter::default().fragment("usb")),
+            ["CONFIG_USB", "CONFIG_USB_ACM", "usb-host"]
+        );
+        assert_eq!(
+            helper_names(ResultFilter::default().severity(Severity::Warning)),
+            ["CONFIG_USB", "CONFIG_USB_ACM", "CONFIG_DEBUG", "usb-host"]
+        );
+        assert_eq!(
+            helper_names(
+                ResultFilter::default()
+                    .fragment("usb")
+                    .fragment("debug")
+                    .only_failures()
+            ),
+            ["CONFIG_USB_ACM", "CONFIG_DEBUG"]
+        );
+    }
+
+    #[test]
+    fn success_filter_option() {
+        assert_eq!(
+            helper_names(ResultFilter::default().option("CONFIG_USB")),
+            ["CONFIG_USB"]
+        );
+        assert_eq!(
+            helper_names(ResultFilter::default().option("CONFIG_USB_*")),
+            ["CONFIG_USB_ACM", "usb-host"]
+        );
+        assert_eq!(
+            helper_names(
+                ResultFilter::default()
+                    .option("/CONFIG_(DEBUG|LOCALVERSION)/")
+                    .option("usb-host")
+            ),
+            ["CONFIG_LOCALVERSION", "CONFIG_DEBUG", "usb-host"]
+        );
+    }
+
+    #[test]
+    fn success_filter_sort() {
+        assert_eq!(
+            helper_names(ResultFilter::default().sort(SortKey::Name)),
+            [
+                "CONFIG_DEBUG",
+                "CONFIG_LOCALVERSION",
+                "CONFIG_USB",
+                "CONFIG_USB_ACM",
+                "usb-host"
+            ]
+        );
+        assert_eq!(
+            helper_names(ResultFilter::default().sort(SortKey::Result)),
+            [
+                "CONFIG_LOCALVERSION",
+                "CONFIG_USB_ACM",
+                "CONFIG_DEBUG",
+                "CONFIG_USB",
+                "usb-host"
+            ]
+        );
+        assert_eq!(
+            helper_names(ResultFilter::default().sort(SortKey::Fragment)),
+            [
+                "CONFIG_DEBUG",
+                "CONFIG_USB",
+                "CONFIG_USB_ACM",
+                "usb-host",
+                "CONFIG_LOCALVERSION"
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_filter() {
+        let result = ResultFilter::default()
+            .option("/(/")
+            .apply(helper_results());
+        assert!(matches!(result, Err(KcheckError::Regex(_))));
+
+        assert_eq!(
+            SortKey::from_str("size"),
+            Err(KcheckError::UnknownSortKey("size".to_string()))
+        );
+        assert_eq!(
+            Severity::from_str("fatal"),
+            Err(KcheckError::UnknownSeverity("fatal".to_string()))
+        );
+        assert_eq!(Severity::from_str("Warning"), Ok(Severity::Warning));
+    }
+}
@@ -0,0 +1,594 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Rewrite a kernel config so that it meets the hard requirements of a config.
+//!
+//! Lines are changed in place, so comments and the order of options are kept.
+//! Options that are not in the kernel config are appended to the end. When a
+//! [`KconfigTree`] is provided, the symbols that a changed option depends on are
+//! enabled as well.
+
+use crate::{
+    config::{KcheckConfig, KcheckConfigFragment, Quantifier},
+    error::{KcheckError, KcheckResult},
+    kconfig::{KconfigOption, KconfigState, Severity},
+    kernel::KernelConfig,
+    target::Target,
+    tree::{KconfigTree, SymbolType, Tristate, CONFIG_PREFIX},
+    CheckResult, Kcheck, KcheckConfigResult,
+};
+use std::str::FromStr;
+
+/// The value used for options that have to be `Enabled`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EnabledPreference {
+    /// Build the option into the kernel with `y`.
+    #[default]
+    BuiltIn,
+    /// Build the option as a module with `m`.
+    Module,
+}
+
+impl FromStr for EnabledPreference {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "y" | "builtin" => Ok(EnabledPreference::BuiltIn),
+            "m" | "module" => Ok(EnabledPreference::Module),
+            s => Err(KcheckError::UnknownEnabledPreference(s.to_string())),
+        }
+    }
+}
+
+/// A line of the kernel config that was changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixChange {
+    /// Name of the changed option.
+    option: String,
+    /// The line that was replaced, if the option was set.
+    old: Option<String>,
+    /// The new line of the option.
+    new: String,
+    /// The option that needed this option as a dependency.
+    required_by: Option<String>,
+}
+
+impl FixChange {
+    /// Name of the changed option.
+    pub fn option(&self) -> String {
+        self.option.clone()
+    }
+
+    /// The line that was replaced, or `None` if the option was added.
+    pub fn old_line(&self) -> Option<String> {
+        self.old.clone()
+    }
+
+    /// The new line of the option.
+    pub fn new_line(&self) -> String {
+        self.new.clone()
+    }
+
+    /// The option that needed this option as a dependency.
+    pub fn required_by(&self) -> Option<String> {
+        self.required_by.clone()
+    }
+}
+
+impl std::fmt::Display for FixChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.old {
+            Some(old) => write!(f, "{}: `{old}` -> `{}`", self.option, self.new)?,
+            None => write!(f, "{}: added `{}`", self.option, self.new)?,
+        }
+
+        if let Some(option) = &self.required_by {
+            write!(f, " (required by {option})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The fixed kernel config and the changes that were made.
+#[derive(Clone, Debug)]
+pub struct FixReport {
+    config: KernelConfig,
+    changes: Vec<FixChange>,
+    remaining: Vec<KcheckConfigResult>,
+}
+
+impl FixReport {
+    /// The fixed kernel config.
+    pub fn config(&self) -> &KernelConfig {
+        &self.config
+    }
+
+    /// The lines that were changed, in the order they were changed.
+    pub fn changes(&self) -> Vec<FixChange> {
+        self.changes.clone()
+    }
+
+    /// Hard requirements that are still not met by the fixed config, such as
+    /// options with a `Regex` state or options that the Kconfig tree does not allow.
+    pub fn remaining(&self) -> Vec<KcheckConfigResult> {
+        self.remaining.clone()
+    }
+}
+
+/// Fix a kernel config so that the requirements with an `Error` severity are met.
+#[derive(Default)]
+pub struct Fixer {
+    /// The desired kernel configuration options.
+    config: KcheckConfig,
+
+    /// The kernel configuration to fix.
+    kernel: KernelConfig,
+
+    /// Kconfig tree used to resolve the kernel config and find dependencies.
+    tree: Option<KconfigTree>,
+
+    /// The value used for options that have to be `Enabled`.
+    enabled: EnabledPreference,
+
+    /// Target used to evaluate conditional requirements.
+    target: Option<Target>,
+}
+
+impl Fixer {
+    /// Create a new [`Fixer`] for a kernel config.
+    pub fn new(config: KcheckConfig, kernel: KernelConfig) -> Self {
+        Self {
+            config,
+            kernel,
+            ..Default::default()
+        }
+    }
+
+    /// Resolve the kernel config with a Kconfig tree and enable the dependencies of
+    /// changed options.
+    pub fn with_tree(mut self, tree: KconfigTree) -> Self {
+        self.tree = Some(tree);
+        self
+    }
+
+    /// Set the value used for options that have to be `Enabled`.
+    ///
+    /// Defaults to [`EnabledPreference::BuiltIn`].
+    pub fn with_enabled(mut self, enabled: EnabledPreference) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set the target used to evaluate conditional requirements.
+    ///
+    /// Defaults to the target detected from the kernel config with [`Target::from_kernel`].
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Fix the kernel config.
+    pub fn fix(&self) -> KcheckResult<FixReport> {
+        let target = match &self.target {
+            Some(t) => t.clone(),
+            None => Target::from_kernel(&self.kernel),
+        };
+
+        let mut fix = Fix {
+            fixer: self,
+            resolved: self.resolve(&self.kernel)?,
+            config: self.kernel.clone(),
+            changes: Vec::new(),
+        };
+
+        for (fragment, option) in self.config.requirements() {
+            if option.severity() == Severity::Error && fix.applies(fragment, option, &target) {
+                fix.option(option)?;
+            }
+        }
+
+        for fragment in self.config.fragment.iter().flatten() {
+            for (quantifier, group) in fragment.groups() {
+                if group.severity() == Severity::Error {
+                    let kernel = group.kernel();
+                    let members: Vec<&KconfigOption> = kernel
+                        .iter()
+                        .filter(|o| fix.applies(Some(fragment), o, &target))
+                        .collect();
+                    fix.group(quantifier, &members)?;
+                }
+            }
+        }
+
+        let remaining = Kcheck::new(self.config.clone(), self.resolve(&fix.config)?)
+            .with_target(target)
+            .perform_check()?
+            .into_iter()
+            .filter(|r| matches!(r.result(), CheckResult::Fail | CheckResult::Error))
+            .filter(|r| r.severity() == Severity::Error)
+            .collect();
+
+        Ok(FixReport {
+            config: fix.config,
+            changes: fix.changes,
+            remaining,
+        })
+    }
+
+    /// The kernel config that is checked, resolved with the Kconfig tree if one was provided.
+    fn resolve(&self, kernel: &KernelConfig) -> KcheckResult<KernelConfig> {
+        match &self.tree {
+            Some(tree) => tree.resolve(kernel),
+            None => Ok(kernel.clone()),
+        }
+    }
+}
+
+/// The state of a fix in progress.
+struct Fix<'a> {
+    fixer: &'a Fixer,
+    /// The config being fixed, resolved with the Kconfig tree.
+    resolved: KernelConfig,
+    /// The config being fixed.
+    config: KernelConfig,
+    changes: Vec<FixChange>,
+}
+
+impl Fix<'_> {
+    /// Returns `true` if a requirement applies to the target and kernel config.
+    fn applies(
+        &self,
+        fragment: Option<&KcheckConfigFragment>,
+        option: &KconfigOption,
+        target: &Target,
+    ) -> bool {
+        let when = fragment
+            .and_then(|f| f.when())
+            .into_iter()
+            .chain(option.when());
+        let condition = option.condition();
+
+        when.into_iter().all(|w| w.matches(target))
+            && condition.is_none_or(|c| c.matches(&self.resolved))
+    }
+
+    /// Returns `true` if an option is in its desired state.
+    fn passes(&self, name: &str, state: &KconfigState) -> bool {
+        self.resolved.option(name).is_ok_and(|s| state.check(s))
+    }
+
+    /// The options that a requirement applies to.
+    fn names(&self, option: &KconfigOption) -> KcheckResult<Vec<String>> {
+        let Some(pattern) = option.name_pattern()? else {
+            return Ok(vec![option.name()]);
+        };
+
+        Ok(self
+            .resolved
+            .entries()
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| pattern.is_match(name))
+            .collect())
+    }
+
+    /// Fix every option that a requirement applies to.
+    fn option(&mut self, option: &KconfigOption) -> KcheckResult<()> {
+        for name in self.names(option)? {
+            if !self.passes(&name, &option.state()) {
+                self.set(&name, &option.state(), None)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fix a group of options.
+    ///
+    /// Every failing member of an `AllOf` group is fixed, the first member of an
+    /// `AnyOf` group is fixed if no member passes, and members of a `NoneOf` group
+    /// that are enabled are disabled.
+    fn group(&mut self, quantifier: Quantifier, members: &[&KconfigOption]) -> KcheckResult<()> {
+        match quantifier {
+            Quantifier::AllOf => {
+                for option in members {
+                    self.option(option)?;
+                }
+            }
+            Quantifier::AnyOf => {
+                let mut names = Vec::new();
+                for option in members {
+                    names.extend(self.names(option)?.into_iter().map(|n| (n, option.state())));
+                }
+
+                if !names.iter().any(|(name, state)| self.passes(name, state)) {
+                    if let Some(option) = members.first() {
+                        self.option(option)?;
+                    }
+                }
+            }
+            Quantifier::NoneOf => {
+                for option in members {
+                    let disabled = matches!(
+                        option.state(),
+                        KconfigState::On | KconfigState::Module | KconfigState::Enabled
+                    );
+
+                    for name in self.names(option)? {
+                        if disabled && self.passes(&name, &option.state()) {
+                            self.set(&name, &KconfigState::NotSet, None)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set an option to a state, and enable the options it depends on.
+    ///
+    /// States that do not describe a single value, such as `Regex`, are left
+    /// unchanged and reported as remaining failures.
+    fn set(
+        &mut self,
+        name: &str,
+        state: &KconfigState,
+        required_by: Option<&str>,
+    ) -> KcheckResult<()> {
+        let state = match (state, self.fixer.enabled) {
+            (KconfigState::Enabled, EnabledPreference::Module) => KconfigState::Module,
+            (KconfigState::Enabled, EnabledPreference::BuiltIn) => KconfigState::On,
+            (state, _) => state.clone(),
+        };
+
+        let Some(line) = state.config_line(name) else {
+            return Ok(());
+        };
+
+        let old = self.config.set_line(name, &line);
+        self.changes.push(FixChange {
+            option: name.to_string(),
+            old,
+            new: line,
+            required_by: required_by.map(str::to_string),
+        });
+        self.resolved = self.fixer.resolve(&self.config)?;
+
+        let value = match state {
+            KconfigState::On => Tristate::Yes,
+            KconfigState::Module => Tristate::Module,
+            _ => return Ok(()),
+        };
+
+        if let Some(tree) = &self.fixer.tree {
+            let mut dependencies = tree.dependencies(name);
+            if let (Tristate::Module, Some(modules)) = (value, &tree.modules) {
+                dependencies.push(format!("{CONFIG_PREFIX}{modules}"));
+            }
+
+            for dependency in dependencies {
+                let (kind, current) = match tree.symbol(&dependency) {
+                    Some(symbol) => (symbol.kind(), self.resolved.option(&dependency)),
+                    None => continue,
+                };
+
+                let dependency_state = match kind {
+                    SymbolType::Tristate if value == Tristate::Module => KconfigState::Enabled,
+                    SymbolType::Bool | SymbolType::Tristate => KconfigState::On,
+                    _ => continue,
+                };
+
+                if !current.is_ok_and(|s| dependency_state.check(s)) {
+                    let dependency_state = match dependency_state {
+                        KconfigState::Enabled => KconfigState::Module,
+                        s => s,
+                    };
+                    self.set(&dependency, &dependency_state, Some(name))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tree::KconfigTreeBuilder;
+
+    const TEST_KERNEL_CONFIG: &str = "\
+# Generated by hand
+CONFIG_FOO=y
+# CONFIG_BAR is not set
+
+# USB
+CONFIG_USB=m
+CONFIG_DEBUG=y
+";
+
+    const TEST_KCHECK_CONFIG: &str = r#"
+        [[kernel]]
+        name = "CONFIG_BAR"
+        state = "Enabled"
+
+        [[kernel]]
+        name = "CONFIG_USB"
+        state = "On"
+
+        [[kernel]]
+        name = "CONFIG_DEBUG"
+        state = "Off"
+
+        [[kernel]]
+        name = "CONFIG_NEW"
+        state = { Text = "value" }
+
+        [[kernel]]
+        name = "CONFIG_SOFT"
+        state = "On"
+        severity = "Warning"
+
+        [[kernel]]
+        name = "CONFIG_LOCALVERSION"
+        state = { Regex = "-board.*" }
+    "#;
+
+    fn helper_fix(enabled: EnabledPreference) -> FixReport {
+        let config = KcheckConfig::from_str(TEST_KCHECK_CONFIG).expect("Expected a config");
+        let kernel = KernelConfig::from_str(TEST_KERNEL_CONFIG).expect("Expected a kernel config");
+
+        Fixer::new(config, kernel)
+            .with_enabled(enabled)
+            .fix()
+            .expect("Expected to fix the kernel config")
+    }
+
+    #[test]
+    fn success_fix() {
+        let report = helper_fix(EnabledPreference::BuiltIn);
+
+        assert_eq!(
+            report.config().to_string(),
+            "\
+# Generated by hand
+CONFIG_FOO=y
+CONFIG_BAR=y
+
+# USB
+CONFIG_USB=y
+# CONFIG_DEBUG is not set
+CONFIG_NEW=\"value\"
+"
+        );
+
+        let changes: Vec<String> = report.changes().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            [
+                "CONFIG_BAR: `# CONFIG_BAR is not set` -> `CONFIG_BAR=y`",
+                "CONFIG_USB: `CONFIG_USB=m` -> `CONFIG_USB=y`",
+                "CONFIG_DEBUG: `CONFIG_DEBUG=y` -> `# CONFIG_DEBUG is not set`",
+                "CONFIG_NEW: added `CONFIG_NEW=\"value\"`",
+            ]
+        );
+
+        let remaining = report.remaining();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name(), "CONFIG_LOCALVERSION");
+    }
+
+    #[test]
+    fn success_fix_enabled_preference() {
+        let report = helper_fix(EnabledPreference::Module);
+        assert_eq!(report.changes()[0].new_line(), "CONFIG_BAR=m");
+        assert_eq!(
+            EnabledPreference::from_str("m"),
+            Ok(EnabledPreference::Module)
+        );
+        assert_eq!(
+            EnabledPreference::from_str("x"),
+            Err(KcheckError::UnknownEnabledPreference("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn success_fix_groups() {
+        let config = KcheckConfig::from_str(
+            r#"
+            [[fragment]]
+            name = "groups"
+
+            [[fragment.any_of]]
+            kernel = [
+                { name = "CONFIG_QUX", state = "On" },
+                { name = "CONFIG_BAZ", state = "On" },
+            ]
+
+            [[fragment.none_of]]
+            kernel = [{ name = "CONFIG_FOO", state = "Enabled" }]
+            "#,
+        )
+        .expect("Expected a config");
+        let kernel = KernelConfig::from_str(TEST_KERNEL_CONFIG).expect("Expected a kernel config");
+
+        let report = Fixer::new(config, kernel)
+            .fix()
+            .expect("Expected to fix the kernel config");
+
+        let changes: Vec<(String, String)> = report
+            .changes()
+            .iter()
+            .map(|c| (c.option(), c.new_line()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("CONFIG_QUX".to_string(), "CONFIG_QUX=y".to_string()),
+                (
+                    "CONFIG_FOO".to_string(),
+                    "# CONFIG_FOO is not set".to_string()
+                ),
+            ]
+        );
+        assert!(report.remaining().is_empty());
+    }
+
+    const TEST_KCONFIG: &str = r#"
+config MODULES
+	bool "Enable loadable module support"
+	modules
+
+config NET
+	bool "Networking support"
+
+config INET
+	bool "TCP/IP networking"
+	depends on NET
+
+config NETFILTER
+	tristate "Network packet filtering"
+	depends on INET && (NET || MODULES)
+"#;
+
+    #[test]
+    fn success_fix_dependencies() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        std::fs::write(dir.path().join("Kconfig"), TEST_KCONFIG).unwrap();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("x86_64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let config =
+            KcheckConfig::from_str("[[kernel]]\nname = \"CONFIG_NETFILTER\"\nstate = \"Module\"\n")
+                .expect("Expected a config");
+        let kernel =
+            KernelConfig::from_str("# CONFIG_NET is not set\n").expect("Expected a kernel config");
+
+        let report = Fixer::new(config, kernel)
+            .with_tree(tree)
+            .fix()
+            .expect("Expected to fix the kernel config");
+
+        let changes: Vec<String> = report.changes().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            [
+                "CONFIG_NETFILTER: added `CONFIG_NETFILTER=m`",
+                "CONFIG_INET: added `CONFIG_INET=y` (required by CONFIG_NETFILTER)",
+                "CONFIG_NET: `# CONFIG_NET is not set` -> `CONFIG_NET=y` (required by CONFIG_INET)",
+                "CONFIG_MODULES: added `CONFIG_MODULES=y` (required by CONFIG_NETFILTER)",
+            ]
+        );
+        assert!(report.remaining().is_empty(), "{:?}", report.remaining());
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Read kernel configs from a revision of a git repository.
+//!
+//! Configs are read directly from the object database, so the revision does
+//! not need to be checked out.
+
+use crate::{
+    error::{KcheckError, KcheckResult},
+    kernel::{KernelConfigProvider, KernelConfigSource},
+};
+use std::path::{Path, PathBuf};
+
+/// A kernel config stored at a revision of a local git repository.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitKernelConfig {
+    /// Path to the repository, or any directory inside of it.
+    repo: PathBuf,
+    /// Any revision understood by `git rev-parse`, such as a tag or commit.
+    revision: String,
+    /// Path to the config file relative to the root of the repository.
+    path: PathBuf,
+}
+
+impl GitKernelConfig {
+    /// Prefix used by [`GitKernelConfig::from_spec`].
+    pub const SPEC_PREFIX: &'static str = "git:";
+
+    /// Read the config file at `path` from `revision` of the repository.
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(repo: P, revision: &str, path: Q) -> Self {
+        Self {
+            repo: repo.as_ref().to_path_buf(),
+            revision: revision.to_string(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Read `arch/<arch>/configs/<name>_defconfig` from `revision` of a kernel tree.
+    pub fn defconfig<P: AsRef<Path>>(repo: P, revision: &str, arch: &str, name: &str) -> Self {
+        let path = PathBuf::from("arch")
+            .join(arch)
+            .join("configs")
+            .join(format!("{name}_defconfig"));
+
+        Self::new(repo, revision, path)
+    }
+
+    /// Parse a `git:<revision>:<path>` spec, such as `git:v6.6:arch/arm64/configs/defconfig`.
+    pub fn from_spec<P: AsRef<Path>>(repo: P, spec: &str) -> KcheckResult<Self> {
+        let invalid = || {
+            KcheckError::KernelConfigBuildError(format!(
+                "Invalid git spec `{spec}`, expected `git:<revision>:<path>`"
+            ))
+        };
+
+        let rest = spec.strip_prefix(Self::SPEC_PREFIX).ok_or_else(invalid)?;
+        let (revision, path) = rest.split_once(':').ok_or_else(invalid)?;
+
+        if revision.is_empty() || path.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self::new(repo, revision, path))
+    }
+
+    /// Returns `true` if the string is a `git:` spec.
+    pub fn is_spec(spec: &str) -> bool {
+        spec.starts_with(Self::SPEC_PREFIX)
+    }
+}
+
+impl KernelConfigProvider for GitKernelConfig {
+    fn source(&self) -> KernelConfigSource {
+        KernelConfigSource::Git {
+            revision: self.revision.clone(),
+            path: self.path.clone(),
+        }
+    }
+
+    fn read(&self) -> KcheckResult<String> {
+        let git_error = |e: &dyn std::fmt::Display| KcheckError::Git(e.to_string());
+
+        let repo = gix::discover(&self.repo).map_err(|e| git_error(&e))?;
+
+        // Git always uses `/` as a path separator
+        let path = self.path.to_string_lossy().replace('\\', "/");
+        let spec = format!("{}:{}", self.revision, path);
+
+        let object = repo
+            .rev_parse_single(spec.as_str())
+            .map_err(|e| git_error(&e))?
+            .object()
+            .map_err(|e| git_error(&e))?;
+
+        if object.kind != gix::object::Kind::Blob {
+            return Err(KcheckError::Git(format!("{spec} is not a file")));
+        }
+
+        String::from_utf8(object.data.clone()).map_err(|e| git_error(&e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{kconfig::KconfigState, kernel::KernelConfigBuilder};
+    use std::process::Command;
+
+    fn helper_git(repo: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args([
+                "-c",
+                "user.name=kcheck",
+                "-c",
+                "user.email=kcheck@example.com",
+            ])
+            .args(args)
+            .current_dir(repo)
+            .status()
+            .expect("Expected to run git");
+        assert!(status.success());
+    }
+
+    /// Create a repository with two revisions of an arm64 defconfig.
+    fn helper_create_repo() -> tempfile::TempDir {
+        let repo = tempfile::tempdir().expect("Failed to create temp dir");
+        let configs = repo.path().join("arch/arm64/configs");
+        std::fs::create_dir_all(&configs).expect("Failed to create configs dir");
+
+        helper_git(repo.path(), &["init", "-q"]);
+        std::fs::write(configs.join("test_defconfig"), "CONFIG_TEST=y\n").unwrap();
+        helper_git(repo.path(), &["add", "."]);
+        helper_git(repo.path(), &["commit", "-q", "-m", "first"]);
+        helper_git(repo.path(), &["tag", "v1"]);
+
+        std::fs::write(configs.join("test_defconfig"), "CONFIG_TEST=m\n").unwrap();
+        helper_git(repo.path(), &["commit", "-q", "-a", "-m", "second"]);
+
+        repo
+    }
+
+    #[test]
+    fn success_git_from_spec() {
+        let cfg = GitKernelConfig::from_spec(".", "git:v6.6:arch/arm64/configs/defconfig")
+            .expect("Expected to parse a git spec");
+        assert_eq!(
+            cfg,
+            GitKernelConfig::new(".", "v6.6", "arch/arm64/configs/defconfig")
+        );
+    }
+
+    #[test]
+    fn fail_git_from_spec() {
+        for spec in ["v6.6:defconfig", "git:v6.6", "git::defconfig", "git:v6.6:"] {
+            assert!(GitKernelConfig::from_spec(".", spec).is_err());
+        }
+    }
+
+    #[test]
+    fn success_git_read_revision() {
+        let repo = helper_create_repo();
+
+        let old = GitKernelConfig::defconfig(repo.path(), "v1", "arm64", "test");
+        let cfg = KernelConfigBuilder::default()
+            .provider(old)
+            .build()
+            .expect("Expected to read a config from git");
+        assert_eq!(cfg.option("CONFIG_TEST").unwrap(), KconfigState::On);
+
+        let head = GitKernelConfig::defconfig(repo.path(), "HEAD", "arm64", "test");
+        let cfg = KernelConfigBuilder::default()
+            .provider(head)
+            .build()
+            .expect("Expected to read a config from git");
+        assert_eq!(cfg.option("CONFIG_TEST").unwrap(), KconfigState::Module);
+        assert_eq!(
+            cfg.source(),
+            KernelConfigSource::Git {
+                revision: "HEAD".to_string(),
+                path: PathBuf::from("arch/arm64/configs/test_defconfig"),
+            }
+        );
+    }
+
+    #[test]
+    fn fail_git_read_missing() {
+        let repo = helper_create_repo();
+
+        let missing = GitKernelConfig::new(repo.path(), "v1", "does/not/exist");
+        assert!(matches!(missing.read(), Err(KcheckError::Git(_))));
+
+        let tree = GitKernelConfig::new(repo.path(), "v1", "arch");
+        assert!(matches!(tree.read(), Err(KcheckError::Git(_))));
+    }
+}
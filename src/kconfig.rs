@@ -5,7 +5,15 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::{
+    config::{Declaration, Declared},
+    error::{KcheckError, KcheckResult},
+    target::{Condition, When},
+};
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 /// The state of a kernel config option.
 ///
@@ -17,7 +25,7 @@ use serde::{Deserialize, Serialize};
 /// For example, there could be a requirement that a kernel config option be
 /// `Enabled` meaning that is present in the system but there is no desire to
 /// force it to be set to `y` or `m`.
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
 pub enum KconfigState {
     /// Kernel config option is not found
     #[default]
@@ -36,6 +44,11 @@ pub enum KconfigState {
     Enabled,
     /// Kernel config is set to a text string
     Text(String),
+    /// Kernel config value matches a regular expression
+    ///
+    /// The expression must match the whole value. Tristate options are matched
+    /// against `y`, `m`, or `n`.
+    Regex(String),
 }
 
 impl KconfigState {
@@ -58,8 +71,65 @@ impl KconfigState {
                     || other == KconfigState::Enabled
             }
             KconfigState::Text(t) => other == KconfigState::Text(t.clone()),
+            KconfigState::Regex(r) => {
+                let value = match &other {
+                    KconfigState::On => "y",
+                    KconfigState::Module => "m",
+                    KconfigState::Off => "n",
+                    KconfigState::Text(t) => t,
+                    _ => return false,
+                };
+
+                Self::regex(r).is_ok_and(|r| r.is_match(value))
+            }
         }
     }
+
+    /// The line of a `.config` file that sets an option to this state.
+    ///
+    /// Returns `None` for a `Regex` state, which does not describe a single value.
+    /// Numbers are written without quotes, like the kernel build does.
+    pub fn config_line(&self, option: &str) -> Option<String> {
+        let line = match self {
+            KconfigState::NotFound
+            | KconfigState::NotSet
+            | KconfigState::Off
+            | KconfigState::Disabled => format!("# {option} is not set"),
+            KconfigState::On | KconfigState::Enabled => format!("{option}=y"),
+            KconfigState::Module => format!("{option}=m"),
+            KconfigState::Text(t) if Self::is_number(t) => format!("{option}={t}"),
+            KconfigState::Text(t) => {
+                let escaped = t.replace('\\', "\\\\").replace('"', "\\\"");
+                format!("{option}=\"{escaped}\"")
+            }
+            KconfigState::Regex(_) => return None,
+        };
+
+        Some(line)
+    }
+
+    /// Returns `true` if a value is a decimal or hexadecimal number.
+    fn is_number(value: &str) -> bool {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        match digits.strip_prefix("0x") {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()),
+        }
+    }
+
+    /// Check that a `Regex` state holds a valid regular expression.
+    pub fn validate(&self) -> KcheckResult<()> {
+        if let KconfigState::Regex(r) = self {
+            Self::regex(r)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compile a regular expression that has to match a whole value.
+    fn regex(pattern: &str) -> KcheckResult<Regex> {
+        Ok(Regex::new(&format!("^(?:{pattern})$"))?)
+    }
 }
 
 impl std::fmt::Display for KconfigState {
@@ -73,6 +143,60 @@ impl std::fmt::Display for KconfigState {
             KconfigState::Module => "Module",
             KconfigState::Enabled => "Enabled (On or Module)",
             KconfigState::Text(t) => t,
+            KconfigState::Regex(r) => return write!(f, "Matches {r}"),
+        };
+
+        write!(f, "{text}")
+    }
+}
+
+/// The importance of a requirement.
+///
+/// Failed requirements with an `Error` severity are hard failures, while
+/// `Warning` and `Info` requirements are only reported.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    JsonSchema,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub enum Severity {
+    /// Informational requirement
+    Info,
+    /// Failing the requirement should be reported but is not fatal
+    Warning,
+    /// Failing the requirement is fatal
+    #[default]
+    Error,
+}
+
+impl FromStr for Severity {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            "info" => Ok(Severity::Info),
+            _ => Err(KcheckError::UnknownSeverity(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
         };
 
         write!(f, "{text}")
@@ -82,12 +206,32 @@ impl std::fmt::Display for KconfigState {
 /// A Kconfig option.
 ///
 /// Used to describe the desired state or value of kernel config options.
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[schemars(deny_unknown_fields)]
 pub struct KconfigOption {
     /// The name of the kernel config option.
     name: String,
     /// A state representing the value of the kernel config option.
     state: KconfigState,
+    /// The importance of the option being in the desired state.
+    ///
+    /// Defaults to [`Severity::Error`] when not provided.
+    severity: Option<Severity>,
+    /// Only check the option on some architectures or kernel versions.
+    when: Option<When>,
+    /// Only check the option when an expression on other options is true.
+    #[serde(rename = "if", alias = "requires")]
+    condition: Option<Condition>,
+    /// How to fix the option when it is not in the desired state.
+    ///
+    /// Replaces the `.config` line that is suggested by default.
+    fix: Option<String>,
+    /// A link to more information about the option.
+    url: Option<String>,
+    /// Where the option is declared, if it was read from a file.
+    #[serde(skip)]
+    #[schemars(skip)]
+    declaration: Declared,
 }
 
 impl std::fmt::Display for KconfigOption {
@@ -102,9 +246,45 @@ impl KconfigOption {
         KconfigOption {
             name: name.to_string(),
             state,
+            severity: None,
+            when: None,
+            condition: None,
+            fix: None,
+            url: None,
+            declaration: Declared::default(),
         }
     }
 
+    /// Set the severity of the kernel config option.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    /// Only check the kernel config option when the condition is met.
+    pub fn with_when(mut self, when: When) -> Self {
+        self.when = Some(when);
+        self
+    }
+
+    /// Only check the kernel config option when the condition on other options is true.
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Set how to fix the kernel config option when it is not in the desired state.
+    pub fn with_fix(mut self, fix: &str) -> Self {
+        self.fix = Some(fix.to_string());
+        self
+    }
+
+    /// Set a link to more information about the kernel config option.
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
     /// Get the name of the kernel config option.
     pub fn name(&self) -> String {
         self.name.clone()
@@ -114,6 +294,75 @@ impl KconfigOption {
     pub fn state(&self) -> KconfigState {
         self.state.clone()
     }
+
+    /// Get the severity of the kernel config option.
+    pub fn severity(&self) -> Severity {
+        self.severity.unwrap_or_default()
+    }
+
+    /// Get the condition under which the kernel config option is checked.
+    pub fn when(&self) -> Option<When> {
+        self.when.clone()
+    }
+
+    /// Get the condition on other options under which the kernel config option is checked.
+    pub fn condition(&self) -> Option<Condition> {
+        self.condition.clone()
+    }
+
+    /// Get how to fix the kernel config option when it is not in the desired state.
+    pub fn fix(&self) -> Option<String> {
+        self.fix.clone()
+    }
+
+    /// Get a link to more information about the kernel config option.
+    pub fn url(&self) -> Option<String> {
+        self.url.clone()
+    }
+
+    /// Get the line of the config file that declares the option.
+    pub fn declaration(&self) -> Option<Declaration> {
+        self.declaration.0.clone()
+    }
+
+    /// Record the line of the config file that declares the option.
+    pub(crate) fn set_declaration(&mut self, declaration: Option<Declaration>) {
+        self.declaration = Declared(declaration);
+    }
+
+    /// Get a pattern matching kernel config option names if the name is not a single option.
+    ///
+    /// Names enclosed in slashes such as `/CONFIG_.*_DEBUG/` are regular expressions and
+    /// names containing `*` or `?` are globs. Both have to match the whole option name.
+    pub fn name_pattern(&self) -> KcheckResult<Option<Regex>> {
+        name_pattern(&self.name)
+    }
+}
+
+/// Get a pattern matching kernel config option names if `name` is not a single option.
+///
+/// Names enclosed in slashes are regular expressions and names containing `*` or `?`
+/// are globs.
+pub(crate) fn name_pattern(name: &str) -> KcheckResult<Option<Regex>> {
+    let pattern = if let Some(r) = name
+        .strip_prefix('/')
+        .and_then(|n| n.strip_suffix('/'))
+        .filter(|r| !r.is_empty())
+    {
+        r.to_string()
+    } else if name.contains(['*', '?']) {
+        name.chars()
+            .map(|c| match c {
+                '*' => ".*".to_string(),
+                '?' => ".".to_string(),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect()
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(Regex::new(&format!("^(?:{pattern})$"))?))
 }
 
 #[cfg(test)]
@@ -140,4 +389,94 @@ mod test {
             insta::assert_snapshot!(kconfig_option);
         }
     }
+
+    #[test]
+    fn success_config_line() {
+        let test_array = [
+            (KconfigState::NotFound, Some("# CONFIG_TEST is not set")),
+            (KconfigState::Disabled, Some("# CONFIG_TEST is not set")),
+            (KconfigState::On, Some("CONFIG_TEST=y")),
+            (KconfigState::Enabled, Some("CONFIG_TEST=y")),
+            (KconfigState::Module, Some("CONFIG_TEST=m")),
+            (
+                KconfigState::Text("a \"b\"".to_string()),
+                Some(r#"CONFIG_TEST="a \"b\"""#),
+            ),
+            (
+                KconfigState::Text("0x10".to_string()),
+                Some("CONFIG_TEST=0x10"),
+            ),
+            (KconfigState::Text("-1".to_string()), Some("CONFIG_TEST=-1")),
+            (KconfigState::Regex("y|m".to_string()), None),
+        ];
+
+        for (state, expected) in test_array {
+            assert_eq!(
+                state.config_line("CONFIG_TEST"),
+                expected.map(str::to_string)
+            );
+        }
+    }
+
+    #[test]
+    fn success_severity() {
+        let option = KconfigOption::new("CONFIG_TEST", KconfigState::On);
+        assert_eq!(option.severity(), Severity::Error);
+
+        let option = option.with_severity(Severity::Warning);
+        assert_eq!(option.severity(), Severity::Warning);
+
+        let option: KconfigOption =
+            toml::from_str("name = \"CONFIG_TEST\"\nstate = \"On\"\nseverity = \"Info\"")
+                .expect("Expected to parse an option with a severity");
+        assert_eq!(option.severity(), Severity::Info);
+        assert!(Severity::Info < Severity::Warning && Severity::Warning < Severity::Error);
+    }
+
+    #[test]
+    fn success_regex_state() {
+        let state = KconfigState::Regex(r"-ourco-\d+".to_string());
+        assert!(state.validate().is_ok());
+        assert!(state.check(KconfigState::Text("-ourco-42".to_string())));
+        assert!(!state.check(KconfigState::Text("-ourco-42-dirty".to_string())));
+        assert!(!state.check(KconfigState::NotFound));
+        assert!(KconfigState::Regex("y|m".to_string()).check(KconfigState::Module));
+        assert_eq!(state.to_string(), r"Matches -ourco-\d+");
+
+        let option: KconfigOption =
+            toml::from_str("name = \"CONFIG_LOCALVERSION\"\nstate = { Regex = \"-ourco-\\\\d+\" }")
+                .expect("Expected to parse an option with a regex state");
+        assert_eq!(option.state(), state);
+
+        assert!(KconfigState::Regex("(".to_string()).validate().is_err());
+    }
+
+    #[test]
+    fn success_name_pattern() {
+        let test_array = [
+            ("CONFIG_*_DEBUG*", "CONFIG_SLUB_DEBUG_ON", true),
+            ("CONFIG_*_DEBUG*", "CONFIG_DEBUG_INFO", false),
+            ("CONFIG_USB_?CM", "CONFIG_USB_ACM", true),
+            ("CONFIG_USB_?CM", "CONFIG_USB_ACM_X", false),
+            ("/CONFIG_(KASAN|UBSAN)/", "CONFIG_KASAN", true),
+            ("/CONFIG_(KASAN|UBSAN)/", "CONFIG_KASAN_INLINE", false),
+        ];
+
+        for (name, option, expected) in test_array {
+            let pattern = KconfigOption::new(name, KconfigState::Disabled)
+                .name_pattern()
+                .expect("Expected a valid pattern")
+                .expect("Expected a pattern");
+            assert_eq!(pattern.is_match(option), expected, "{name} {option}");
+        }
+
+        let option = KconfigOption::new("CONFIG_USB_ACM", KconfigState::On);
+        assert!(option
+            .name_pattern()
+            .expect("Expected a valid name")
+            .is_none());
+        assert!(KconfigOption::new("/(/", KconfigState::On)
+            .name_pattern()
+            .is_err());
+    }
 }
@@ -6,18 +6,46 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::{
+    diagnostic::{Diagnostic, Location},
     error::{KcheckError, KcheckResult},
     kconfig::KconfigState,
+    target::KernelVersion,
     util,
 };
 use nix::sys::utsname::uname;
 use regex::Regex;
+use serde::Serialize;
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
 };
 
+/// A provider of raw kernel config contents.
+///
+/// Implement this trait to read kernel configs from locations that are not
+/// supported directly, such as a serial console log or an archive, and pass
+/// the provider to [`KernelConfigBuilder::provider`].
+pub trait KernelConfigProvider: std::fmt::Debug {
+    /// Describe where the kernel config is read from.
+    fn source(&self) -> KernelConfigSource;
+
+    /// Read the raw kernel config contents.
+    fn read(&self) -> KcheckResult<String>;
+}
+
+impl<T: KernelConfigProvider + ?Sized> KernelConfigProvider for Arc<T> {
+    fn source(&self) -> KernelConfigSource {
+        (**self).source()
+    }
+
+    fn read(&self) -> KcheckResult<String> {
+        (**self).read()
+    }
+}
+
 /// Meta file information for a kernel config file.
 #[derive(Clone, Debug, Default)]
 struct KernelConfigFileInfo(PathBuf, RequiresInflate);
@@ -114,12 +142,33 @@ impl KernelConfigFileInfo {
     }
 }
 
-#[derive(Clone, Debug, Default, PartialEq)]
+impl KernelConfigProvider for KernelConfigFileInfo {
+    fn source(&self) -> KernelConfigSource {
+        self.0.clone().into()
+    }
+
+    /// Opens the file and inflates it if necessary.
+    fn read(&self) -> KcheckResult<String> {
+        match self.1 {
+            RequiresInflate::True => util::inflate_gzip_file(&self.0),
+            RequiresInflate::False => util::file_contents_as_string(&self.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 pub enum KernelConfigSource {
     #[default]
     String,
     File(PathBuf),
     Stdin,
+    /// A file at a revision of a git repository.
+    Git {
+        revision: String,
+        path: PathBuf,
+    },
+    /// A source described by a [`KernelConfigProvider`].
+    Custom(String),
 }
 
 impl From<PathBuf> for KernelConfigSource {
@@ -128,21 +177,79 @@ impl From<PathBuf> for KernelConfigSource {
     }
 }
 
-impl FromStr for KernelConfig {
-    type Err = KcheckError;
+impl KernelConfigSource {
+    /// Returns `true` if the source is the kernel config of the running system,
+    /// read from `/proc/config.gz`, `/boot/config`, or `/boot/config-$(uname -r)`.
+    pub fn is_system(&self) -> bool {
+        let KernelConfigSource::File(path) = self else {
+            return false;
+        };
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let all_lines = s.lines();
-        let mut lines: Vec<String> = Vec::new();
+        let release = uname().ok().map(|u| {
+            format!(
+                "{}-{}",
+                KernelConfigFileInfo::BOOT_CONFIG,
+                u.release().to_string_lossy()
+            )
+        });
+        path == Path::new(KernelConfigFileInfo::PROC_CONFIG_GZ)
+            || path == Path::new(KernelConfigFileInfo::BOOT_CONFIG)
+            || release.is_some_and(|r| path == Path::new(&r))
+    }
+}
 
-        for line in all_lines {
-            lines.push(line.to_string());
+impl std::fmt::Display for KernelConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KernelConfigSource::String => write!(f, "<string>"),
+            KernelConfigSource::File(path) => write!(f, "{}", path.to_string_lossy()),
+            KernelConfigSource::Stdin => write!(f, "<stdin>"),
+            KernelConfigSource::Git { revision, path } => {
+                write!(f, "{revision}:{}", path.to_string_lossy())
+            }
+            KernelConfigSource::Custom(s) => write!(f, "{s}"),
         }
+    }
+}
+
+/// The line of a kernel config that an option was read from.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Provenance {
+    source: KernelConfigSource,
+    line: usize,
+}
 
-        Ok(KernelConfig {
-            src: KernelConfigSource::default(),
+impl Provenance {
+    pub(crate) fn new(source: KernelConfigSource, line: usize) -> Self {
+        Self { source, line }
+    }
+
+    /// Where the kernel config was read from.
+    pub fn source(&self) -> KernelConfigSource {
+        self.source.clone()
+    }
+
+    /// Line number, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.source, self.line)
+    }
+}
+
+impl FromStr for KernelConfig {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().map(str::to_string).collect();
+        Ok(KernelConfig::from_source(
+            KernelConfigSource::default(),
             lines,
-        })
+        ))
     }
 }
 
@@ -154,37 +261,52 @@ pub(crate) enum RequiresInflate {
     False,
 }
 
+/// How to handle an option that is set more than once in a kernel config.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Report a [`KcheckError::DuplicateConfig`] error.
+    #[default]
+    Error,
+    /// Use the last value with a warning, like the kernel build does.
+    WarnLast,
+    /// Use the first value with a warning.
+    WarnFirst,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(DuplicatePolicy::Error),
+            "last" => Ok(DuplicatePolicy::WarnLast),
+            "first" => Ok(DuplicatePolicy::WarnFirst),
+            s => Err(KcheckError::UnknownDuplicatePolicy(s.to_string())),
+        }
+    }
+}
+
 /// Construct a [`KernelConfig`] object.
 #[derive(Clone, Debug, Default)]
 pub struct KernelConfigBuilder {
-    /// Path to the user provided kernel config file.
-    usr_cfg_file: Option<PathBuf>,
+    /// Paths to the user provided kernel config files, merged in order.
+    usr_cfg_files: Vec<PathBuf>,
     /// Flag indicating that the system kernel config should be used.
     sys_cfg_flag: bool,
-    /// Meta file information for a kernel config file.
-    file_info: Option<KernelConfigFileInfo>,
+    /// User provided source of the kernel config.
+    provider: Option<Arc<dyn KernelConfigProvider>>,
     /// Raw kernel config file lines.
     lines: Vec<String>,
+    /// How to handle options that are set more than once.
+    duplicates: DuplicatePolicy,
 }
 
 impl KernelConfigBuilder {
-    /// Create a new kernel config struct from meta file information
-    ///
-    /// Opens the file and inflates it if necessary.
-    fn try_from_file_info(info: KernelConfigFileInfo) -> KcheckResult<KernelConfig> {
-        let path = info.0;
-        let inflate = info.1;
-
-        let contents = match inflate {
-            RequiresInflate::True => util::inflate_gzip_file(path.clone())?,
-            RequiresInflate::False => util::file_contents_as_string(path.clone())?,
-        };
-
-        let mut config = KernelConfig::from_str(contents.as_str())?;
-
-        // Set the source type to a file
-        config.src = path.into();
-        Ok(config)
+    /// Create a new kernel config struct from a provider.
+    fn try_from_provider<P: KernelConfigProvider>(provider: &P) -> KcheckResult<KernelConfig> {
+        let contents = provider.read()?;
+        let lines = contents.lines().map(str::to_string).collect();
+        Ok(KernelConfig::from_source(provider.source(), lines))
     }
 
     /// Indicate that the system kernel config should be used.
@@ -194,17 +316,28 @@ impl KernelConfigBuilder {
     }
 
     /// Indicate that the user provided kernel config should be used.
+    ///
+    /// Calling this multiple times merges the configs in order, like
+    /// `scripts/kconfig/merge_config.sh`. Options set by a later config override
+    /// the same options in earlier configs.
     pub fn user<P: AsRef<Path>>(mut self, path: P) -> Self {
-        self.usr_cfg_file = Some(path.as_ref().to_path_buf());
+        self.usr_cfg_files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Indicate that a user provided [`KernelConfigProvider`] should be used.
+    pub fn provider<P: KernelConfigProvider + 'static>(mut self, provider: P) -> Self {
+        self.provider = Some(Arc::new(provider));
         self
     }
 
     /// Add an option to the kernel config directly.
     ///
-    /// Mutually exclusive operation to `system` or `user`.
+    /// Mutually exclusive operation to `system` or `user`. A `Regex` state does not
+    /// describe a single value and is treated like `NotFound`.
     pub fn option(mut self, option: &str, state: KconfigState) -> Self {
         let line = match state {
-            KconfigState::NotFound => String::default(),
+            KconfigState::NotFound | KconfigState::Regex(_) => String::default(),
             KconfigState::NotSet => format!("# {option} is not set"),
             KconfigState::Off | KconfigState::Disabled => format!("{option}=n"),
             KconfigState::On | KconfigState::Enabled => format!("{option}=y"),
@@ -216,6 +349,14 @@ impl KernelConfigBuilder {
         self
     }
 
+    /// Set how options that are set more than once are handled.
+    ///
+    /// Defaults to [`DuplicatePolicy::Error`].
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
     /// Add multiple options to the kernel config directly.
     pub fn options(mut self, options: &[(&str, KconfigState)]) -> Self {
         for (option, state) in options {
@@ -226,40 +367,59 @@ impl KernelConfigBuilder {
     }
 
     /// Consume the builder object and produce a `KernelConfig` object.
-    pub fn build(mut self) -> KcheckResult<KernelConfig> {
-        if !self.lines.is_empty() && (self.sys_cfg_flag || self.usr_cfg_file.is_some()) {
+    pub fn build(self) -> KcheckResult<KernelConfig> {
+        let duplicates = self.duplicates;
+        self.build_config().map(|config| KernelConfig {
+            duplicates,
+            ..config
+        })
+    }
+
+    /// Read the kernel config from the configured source.
+    fn build_config(self) -> KcheckResult<KernelConfig> {
+        let sources = [
+            self.sys_cfg_flag,
+            !self.usr_cfg_files.is_empty(),
+            self.provider.is_some(),
+        ];
+
+        if !self.lines.is_empty() && sources.contains(&true) {
             return Err(KcheckError::KernelConfigBuildError(
                 "Cannot set options manually when another builder method is used".to_string(),
             ));
         }
 
-        if self.sys_cfg_flag && self.usr_cfg_file.is_some() {
+        if sources.iter().filter(|s| **s).count() > 1 {
             return Err(KcheckError::KernelConfigBuildError(
-                "Both system and user config build methods are set".to_string(),
+                "Multiple system, user, or provider config build methods are set".to_string(),
             ));
         }
 
-        if let Some(path) = self.usr_cfg_file {
-            self.file_info = Some(KernelConfigFileInfo::try_from_user(path)?);
+        if let Some(provider) = self.provider {
+            return Self::try_from_provider(&provider);
         }
 
         if self.sys_cfg_flag {
-            self.file_info = Some(KernelConfigFileInfo::try_from_system()?);
+            return Self::try_from_provider(&KernelConfigFileInfo::try_from_system()?);
         }
 
-        match self.file_info {
-            Some(info) => Self::try_from_file_info(info),
+        let mut configs = self
+            .usr_cfg_files
+            .iter()
+            .map(|path| Self::try_from_provider(&KernelConfigFileInfo::try_from_user(path)?));
+
+        match configs.next() {
+            Some(first) => configs.try_fold(first?, |config, next| Ok(config.merge(next?))),
             None => {
                 if self.lines.is_empty() {
                     Err(KcheckError::KernelConfigBuildError(
                         "No config file information found".to_string(),
                     ))
                 } else {
-                    let lines = self.lines;
-                    Ok(KernelConfig {
-                        lines,
-                        ..Default::default()
-                    })
+                    Ok(KernelConfig::from_source(
+                        KernelConfigSource::default(),
+                        self.lines,
+                    ))
                 }
             }
         }
@@ -271,49 +431,328 @@ impl KernelConfigBuilder {
 pub struct KernelConfig {
     src: KernelConfigSource,
     lines: Vec<String>,
+    /// Where each line was read from, if it was not generated.
+    provenance: Vec<Option<Provenance>>,
+    /// How to handle options that are set more than once.
+    duplicates: DuplicatePolicy,
 }
 
 impl KernelConfig {
+    /// Create a kernel config from the lines read from a source.
+    fn from_source(src: KernelConfigSource, lines: Vec<String>) -> Self {
+        let provenance = (1..=lines.len())
+            .map(|line| Some(Provenance::new(src.clone(), line)))
+            .collect();
+
+        Self {
+            src,
+            lines,
+            provenance,
+            duplicates: DuplicatePolicy::default(),
+        }
+    }
+
+    /// Create a kernel config from lines that were generated rather than read.
+    pub(crate) fn from_lines(src: KernelConfigSource, lines: Vec<String>) -> Self {
+        Self {
+            src,
+            provenance: vec![None; lines.len()],
+            lines,
+            duplicates: DuplicatePolicy::default(),
+        }
+    }
+
+    /// Describe where the kernel config was read from.
+    ///
+    /// For merged configs, this is the source of the first config.
+    pub fn source(&self) -> KernelConfigSource {
+        self.src.clone()
+    }
+
+    /// Merge another kernel config into this one, like `scripts/kconfig/merge_config.sh`.
+    ///
+    /// Options set by the other config override the same options in this config: the
+    /// first line that sets the option is replaced in place and takes the provenance of
+    /// the new line, and any other lines that set it are removed. Other lines are
+    /// appended. Options that are set more than once within the other config are kept
+    /// as duplicates.
+    pub fn merge(mut self, other: KernelConfig) -> Self {
+        self.provenance.resize(self.lines.len(), None);
+
+        let mut existing: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            if let Some((name, _)) = Self::entry(line) {
+                existing.entry(name).or_default().push(index);
+            }
+        }
+
+        let mut removed = Vec::new();
+        let provenance = other.provenance.into_iter().chain(std::iter::repeat(None));
+        for (line, provenance) in other.lines.into_iter().zip(provenance) {
+            let replaced = Self::entry(&line).and_then(|(name, _)| existing.remove(&name));
+            match replaced.as_deref() {
+                Some([first, duplicates @ ..]) => {
+                    self.lines[*first] = line;
+                    self.provenance[*first] = provenance;
+                    removed.extend_from_slice(duplicates);
+                }
+                _ => {
+                    self.lines.push(line);
+                    self.provenance.push(provenance);
+                }
+            }
+        }
+
+        removed.sort_unstable();
+        for index in removed.into_iter().rev() {
+            self.lines.remove(index);
+            self.provenance.remove(index);
+        }
+
+        self
+    }
+
+    /// Copy the provenance of options from the config a generated config was derived from.
+    ///
+    /// Only options with the same value in both configs keep their provenance.
+    pub(crate) fn with_provenance_from(mut self, config: &KernelConfig) -> Self {
+        let origins: HashMap<(String, String), Provenance> = config
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| Some((Self::entry(line)?, config.provenance_at(index)?)))
+            .collect();
+
+        self.provenance = self
+            .lines
+            .iter()
+            .map(|line| origins.get(&Self::entry(line)?).cloned())
+            .collect();
+        self
+    }
+
+    /// Set the line of an option, keeping its position in the config.
+    ///
+    /// The first line that sets the option is replaced and any other lines that set
+    /// it are removed. Options that are not set are appended. Returns the replaced
+    /// line.
+    pub(crate) fn set_line(&mut self, option: &str, line: &str) -> Option<String> {
+        let sets_option = |l: &String| {
+            l.trim() == option || Self::entry(l).is_some_and(|(name, _)| name == option)
+        };
+
+        self.provenance.resize(self.lines.len(), None);
+        let indices: Vec<usize> = (0..self.lines.len())
+            .filter(|i| sets_option(&self.lines[*i]))
+            .collect();
+
+        let Some((first, duplicates)) = indices.split_first() else {
+            self.lines.push(line.to_string());
+            self.provenance.push(None);
+            return None;
+        };
+
+        for index in duplicates.iter().rev() {
+            self.lines.remove(*index);
+            self.provenance.remove(*index);
+        }
+
+        self.provenance[*first] = None;
+        Some(std::mem::replace(&mut self.lines[*first], line.to_string()))
+    }
+
+    /// The provenance of a line, if it was read from a source.
+    fn provenance_at(&self, index: usize) -> Option<Provenance> {
+        self.provenance.get(index).cloned().flatten()
+    }
+
+    /// All options set in the kernel config and their raw values.
+    ///
+    /// Options that are "not set" have the value `n` and quotes are removed
+    /// from string values. Each option is listed once, in the order it is first
+    /// set, with the value chosen by the [`DuplicatePolicy`] so that every reader
+    /// of the config sees the same value as [`KernelConfig::option`]. The
+    /// [`DuplicatePolicy::Error`] policy uses the first value here, checking the
+    /// option reports the error.
+    pub(crate) fn entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        for (name, value) in self.lines.iter().filter_map(|l| Self::entry(l)) {
+            match index.get(&name) {
+                Some(i) if self.duplicates == DuplicatePolicy::WarnLast => entries[*i].1 = value,
+                Some(_) => {}
+                None => {
+                    index.insert(name.clone(), entries.len());
+                    entries.push((name, value));
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// Parse a line of the kernel config into an option and its raw value.
+    fn entry(line: &str) -> Option<(String, String)> {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            let name = comment.trim().strip_suffix("is not set")?.trim();
+            return Some((name.to_string(), "n".to_string()));
+        }
+
+        let (name, value) = line.split_once('=')?;
+        Some((name.trim().to_string(), Self::unquote(value)))
+    }
+
+    /// The kernel version from the header of a generated config.
+    ///
+    /// Configs generated by the kernel build start with a line such as
+    /// `# Linux/arm64 6.6.0 Kernel Configuration`.
+    pub fn header_version(&self) -> Option<KernelVersion> {
+        self.lines.iter().take(10).find_map(|line| {
+            let rest = line.strip_prefix("# Linux/")?;
+            let rest = rest.strip_suffix("Kernel Configuration")?;
+            let version = rest.split_whitespace().nth(1)?;
+            KernelVersion::from_str(version).ok()
+        })
+    }
+
     /// Get the state of a kernel config option.
     pub fn option(&self, option: &str) -> KcheckResult<KconfigState> {
+        self.option_with_provenance(option).map(|(state, _)| state)
+    }
+
+    /// Get the state of a kernel config option and the line it was read from.
+    ///
+    /// The provenance is `None` if the option is not found or was generated,
+    /// such as by expanding the config with a [`KconfigTree`](crate::tree::KconfigTree).
+    pub fn option_with_provenance(
+        &self,
+        option: &str,
+    ) -> KcheckResult<(KconfigState, Option<Provenance>)> {
+        self.resolve(option)
+            .map(|(state, provenance, _)| (state, provenance))
+    }
+
+    /// Get the state of a kernel config option, the line it was read from, and how
+    /// duplicates of it were resolved, from a single scan of the config.
+    pub(crate) fn resolve(
+        &self,
+        option: &str,
+    ) -> KcheckResult<(KconfigState, Option<Provenance>, Option<String>)> {
+        let mut found_state = self.find(option)?;
+        let warning = self.warning(option, &found_state);
+
+        // Parse results
+        let (index, state) = match (found_state.len(), self.duplicates) {
+            (0, _) => return Ok((KconfigState::NotFound, None, None)),
+            (1, _) | (_, DuplicatePolicy::WarnFirst) => found_state.remove(0),
+            (_, DuplicatePolicy::WarnLast) => found_state.pop().expect("Expected an option"),
+            (_, DuplicatePolicy::Error) => {
+                return Err(KcheckError::DuplicateConfig(option.to_string()))
+            }
+        };
+
+        Ok((state?, self.provenance_at(index), warning))
+    }
+
+    /// Describe how duplicates of a kernel config option were resolved.
+    ///
+    /// Returns `None` if the option is set at most once or the [`DuplicatePolicy`]
+    /// reports duplicates as errors.
+    pub fn duplicate_warning(&self, option: &str) -> KcheckResult<Option<String>> {
+        Ok(self.warning(option, &self.find(option)?))
+    }
+
+    /// Describe how the lines found for an option were resolved, if there is more than one.
+    fn warning(
+        &self,
+        option: &str,
+        found: &[(usize, KcheckResult<KconfigState>)],
+    ) -> Option<String> {
+        let (index, value) = match (found.len(), self.duplicates) {
+            (0 | 1, _) | (_, DuplicatePolicy::Error) => return None,
+            (_, DuplicatePolicy::WarnFirst) => (found[0].0, "first"),
+            (_, DuplicatePolicy::WarnLast) => (found[found.len() - 1].0, "last"),
+        };
+
+        let mut warning = format!(
+            "{option} is set {} times, using the {value} value",
+            found.len()
+        );
+        if let Some(provenance) = self.provenance_at(index) {
+            warning += &format!(" from {provenance}");
+        }
+
+        Some(warning)
+    }
+
+    /// Find every line that sets a kernel config option and parse its state.
+    fn find(&self, option: &str) -> KcheckResult<Vec<(usize, KcheckResult<KconfigState>)>> {
         let option_string = format!("{option}\\b");
         let search = Regex::new(&option_string)?;
 
         // Seach the config for the desired option and store the result
-        let mut found_state: Vec<KcheckResult<KconfigState>> =
-            self.lines.iter().filter(|l| search.is_match(l)).fold(
-                Vec::<KcheckResult<KconfigState>>::new(),
-                |mut result, line| {
-                    // The config option has been found, now split up the line
-                    let line_parts: Vec<&str> = line.split_inclusive(option).collect();
-
-                    if Self::is_comment(line_parts[0]) && Self::contains_is_not_set(line_parts[1]) {
-                        result.push(Ok(KconfigState::NotSet));
-                    } else if line_parts.len() > 1
-                        && !Self::is_comment(line_parts[0])
-                        && line_parts[1].contains('=')
-                    {
-                        let value = line_parts[1].split('=').collect::<Vec<&str>>()[1];
-                        match value {
-                            "y" => result.push(Ok(KconfigState::On)),
-                            "m" => result.push(Ok(KconfigState::Module)),
-                            "n" => result.push(Ok(KconfigState::Off)),
-                            v => result
-                                .push(Err(KcheckError::UnknownKernelConfigOption(v.to_string()))),
-                        }
-                    } else {
-                        result.push(Err(KcheckError::KernelConfigParseError))
+        let found_state = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| search.is_match(l))
+            .map(|(index, line)| {
+                // The config option has been found, now split up the line
+                let line_parts: Vec<&str> = line.split_inclusive(option).collect();
+
+                let state = if Self::is_comment(line_parts[0])
+                    && Self::contains_is_not_set(line_parts[1])
+                {
+                    Ok(KconfigState::NotSet)
+                } else if line_parts.len() > 1
+                    && !Self::is_comment(line_parts[0])
+                    && line_parts[1].contains('=')
+                {
+                    let value = line_parts[1].split_once('=').map_or("", |(_, v)| v);
+                    match value {
+                        "y" => Ok(KconfigState::On),
+                        "m" => Ok(KconfigState::Module),
+                        "n" => Ok(KconfigState::Off),
+                        "" => Err(KcheckError::UnknownKernelConfigOption(value.to_string())),
+                        v => Ok(KconfigState::Text(Self::unquote(v))),
                     }
+                } else {
+                    Err(KcheckError::KernelConfigParseError(
+                        self.parse_error(index, option),
+                    ))
+                };
 
-                    result
-                },
-            );
+                (index, state)
+            })
+            .collect();
 
-        // Parse results
-        match found_state.len() {
-            0 => Ok(KconfigState::NotFound),
-            1 => found_state.remove(0),
-            _ => Err(KcheckError::DuplicateConfig(option.to_string())),
+        Ok(found_state)
+    }
+
+    /// Describe a line of the kernel config that could not be parsed.
+    fn parse_error(&self, index: usize, option: &str) -> Diagnostic {
+        let provenance = self.provenance_at(index);
+        let line = provenance.as_ref().map_or(index + 1, Provenance::line);
+        let diagnostic = Diagnostic::new(&format!(
+            "expected `{option}=<value>` or `# {option} is not set`"
+        ))
+        .with_location(Location::whole_line(line, &self.lines[index]));
+
+        match provenance.map(|p| p.source) {
+            Some(source @ (KernelConfigSource::File(_) | KernelConfigSource::Git { .. })) => {
+                diagnostic.with_file(source.to_string())
+            }
+            _ => diagnostic,
+        }
+    }
+
+    /// Remove the quotes and escapes from a string value.
+    fn unquote(value: &str) -> String {
+        match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(v) => v.replace("\\\"", "\"").replace("\\\\", "\\"),
+            None => value.to_string(),
         }
     }
 
@@ -338,6 +777,16 @@ impl KernelConfig {
     }
 }
 
+impl std::fmt::Display for KernelConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -497,15 +946,37 @@ mod test {
         helper_assert_option_state_ok(&kernel_cfg, test_option, expected, AssertMatch::False);
     }
 
+    #[test]
+    fn success_text_option() {
+        let test_option = "CONFIG_TEXT";
+        let test_state = KconfigState::Text("some \"text\"".to_string());
+        let kernel_cfg = KernelConfig::from_lines(
+            KernelConfigSource::default(),
+            vec![
+                "CONFIG_TEXT=\"some \\\"text\\\"\"".to_string(),
+                "CONFIG_NUMBER=0x10".to_string(),
+            ],
+        );
+
+        helper_assert_option_state_ok(&kernel_cfg, test_option, test_state, AssertMatch::True);
+        helper_assert_option_state_ok(
+            &kernel_cfg,
+            "CONFIG_NUMBER",
+            KconfigState::Text("0x10".to_string()),
+            AssertMatch::True,
+        );
+    }
+
     #[test]
     fn fail_unknown_option() {
         let test_option = "CONFIG_INCORRECT";
-        let test_state = KconfigState::Text("incorrect".to_string());
-
-        let test_data = [(test_option, test_state.clone())];
-        let kernel_cfg = helper_create_kernel_cfg(&test_data);
+        let test_state = KconfigState::Text(String::new());
+        let kernel_cfg = KernelConfig::from_lines(
+            KernelConfigSource::default(),
+            vec![format!("{test_option}=")],
+        );
 
-        let expected = KcheckError::UnknownKernelConfigOption("\"incorrect\"".to_string());
+        let expected = KcheckError::UnknownKernelConfigOption(String::new());
         helper_assert_option_state_err(&kernel_cfg, test_option, expected);
 
         // On a failed option lookup via `option`, `check_option` should return false
@@ -526,13 +997,115 @@ mod test {
         helper_assert_option_state_err(&kernel_cfg, test_option, expected);
     }
 
+    #[test]
+    fn success_duplicate_option_policy() {
+        let test_option = "CONFIG_TEST";
+        let test_data = [
+            (test_option, KconfigState::On),
+            ("CONFIG_TEST_TWO", KconfigState::Module),
+            (test_option, KconfigState::Off),
+        ];
+
+        let kernel_cfg = KernelConfigBuilder::default()
+            .options(&test_data)
+            .duplicates(DuplicatePolicy::WarnLast)
+            .build()
+            .expect("Expected to build a kernel config");
+        helper_assert_option_state_ok(
+            &kernel_cfg,
+            test_option,
+            KconfigState::Off,
+            AssertMatch::True,
+        );
+        assert_eq!(
+            kernel_cfg.duplicate_warning(test_option),
+            Ok(Some(
+                "CONFIG_TEST is set 2 times, using the last value from <string>:3".to_string()
+            ))
+        );
+        assert_eq!(kernel_cfg.duplicate_warning("CONFIG_TEST_TWO"), Ok(None));
+
+        let kernel_cfg = KernelConfigBuilder::default()
+            .options(&test_data)
+            .duplicates(DuplicatePolicy::WarnFirst)
+            .build()
+            .expect("Expected to build a kernel config");
+        helper_assert_option_state_ok(
+            &kernel_cfg,
+            test_option,
+            KconfigState::On,
+            AssertMatch::True,
+        );
+
+        let kernel_cfg = helper_create_kernel_cfg(&test_data);
+        assert_eq!(kernel_cfg.duplicate_warning(test_option), Ok(None));
+    }
+
+    #[test]
+    fn success_duplicate_option_policy_entries() {
+        let test_data = [
+            ("CONFIG_TEST", KconfigState::On),
+            ("CONFIG_TEST_TWO", KconfigState::Module),
+            ("CONFIG_TEST", KconfigState::NotSet),
+        ];
+
+        for (policy, value) in [
+            (DuplicatePolicy::WarnLast, "n"),
+            (DuplicatePolicy::WarnFirst, "y"),
+        ] {
+            let kernel_cfg = KernelConfigBuilder::default()
+                .options(&test_data)
+                .duplicates(policy)
+                .build()
+                .expect("Expected to build a kernel config");
+
+            assert_eq!(
+                kernel_cfg.entries(),
+                [
+                    ("CONFIG_TEST".to_string(), value.to_string()),
+                    ("CONFIG_TEST_TWO".to_string(), "m".to_string())
+                ]
+            );
+
+            let (_, _, warning) = kernel_cfg.resolve("CONFIG_TEST").unwrap();
+            assert_eq!(
+                warning,
+                kernel_cfg.duplicate_warning("CONFIG_TEST").unwrap()
+            );
+            assert!(warning.is_some());
+        }
+    }
+
+    #[test]
+    fn success_duplicate_policy_from_str() {
+        assert_eq!(
+            DuplicatePolicy::from_str("error"),
+            Ok(DuplicatePolicy::Error)
+        );
+        assert_eq!(
+            DuplicatePolicy::from_str("last"),
+            Ok(DuplicatePolicy::WarnLast)
+        );
+        assert_eq!(
+            DuplicatePolicy::from_str("first"),
+            Ok(DuplicatePolicy::WarnFirst)
+        );
+        assert_eq!(
+            DuplicatePolicy::from_str("middle"),
+            Err(KcheckError::UnknownDuplicatePolicy("middle".to_string()))
+        );
+    }
+
     #[test]
     fn fail_kernel_config_parse() {
         let test_option = "CONFIG_TEST";
         let mut kernel_cfg = KernelConfig::default();
         kernel_cfg.lines.push(test_option.to_string());
 
-        let expected = KcheckError::KernelConfigParseError;
+        let expected = KcheckError::KernelConfigParseError(
+            Diagnostic::new("expected `CONFIG_TEST=<value>` or `# CONFIG_TEST is not set`")
+                .with_location(Location::whole_line(1, test_option)),
+        );
         helper_assert_option_state_err(&kernel_cfg, test_option, expected)
     }
 
@@ -560,6 +1133,76 @@ mod test {
         );
     }
 
+    #[test]
+    fn success_option_with_provenance() {
+        let cfg = KernelConfig::from_str("CONFIG_TEST_ONE=y\n\n# CONFIG_TEST_TWO is not set")
+            .expect("Expected to create a kernel config from a string");
+
+        let (state, provenance) = cfg
+            .option_with_provenance("CONFIG_TEST_TWO")
+            .expect("Expected to get an option state");
+        assert_eq!(state, KconfigState::NotSet);
+        assert_eq!(
+            provenance,
+            Some(Provenance::new(KernelConfigSource::String, 3))
+        );
+
+        let (state, provenance) = cfg
+            .option_with_provenance("CONFIG_DOES_NOT_EXIST")
+            .expect("Expected to get an option state");
+        assert_eq!(state, KconfigState::NotFound);
+        assert_eq!(provenance, None);
+    }
+
+    #[test]
+    fn success_kernel_config_merge() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        let base = tmpdir.path().join("base.config");
+        let fragment = tmpdir.path().join("fragment.config");
+        std::fs::write(&base, "CONFIG_TEST=y\nCONFIG_TEST_TWO=m\n").unwrap();
+        std::fs::write(
+            &fragment,
+            "# Fragment\nCONFIG_TEST_THREE=y\n# CONFIG_TEST is not set\n",
+        )
+        .unwrap();
+
+        let cfg = KernelConfigBuilder::default()
+            .user(&base)
+            .user(&fragment)
+            .build()
+            .expect("Expected to merge kernel configs");
+
+        assert_eq!(cfg.source(), KernelConfigSource::File(base.clone()));
+        assert_eq!(cfg.option("CONFIG_TEST_TWO").unwrap(), KconfigState::Module);
+        assert_eq!(
+            cfg.to_string(),
+            "# CONFIG_TEST is not set\nCONFIG_TEST_TWO=m\n# Fragment\nCONFIG_TEST_THREE=y\n"
+        );
+
+        // The fragment overrides the base config without creating a duplicate
+        let (state, provenance) = cfg.option_with_provenance("CONFIG_TEST").unwrap();
+        assert_eq!(state, KconfigState::NotSet);
+        assert_eq!(
+            provenance,
+            Some(Provenance::new(
+                KernelConfigSource::File(fragment.clone()),
+                3
+            ))
+        );
+
+        let (_, provenance) = cfg.option_with_provenance("CONFIG_TEST_THREE").unwrap();
+        let provenance = provenance.expect("Expected a provenance");
+        assert_eq!(
+            provenance.source(),
+            KernelConfigSource::File(fragment.clone())
+        );
+        assert_eq!(provenance.line(), 2);
+        assert_eq!(
+            provenance.to_string(),
+            format!("{}:2", fragment.to_string_lossy())
+        );
+    }
+
     #[test]
     fn success_kernel_config_source_from_pathbuf() {
         let path = PathBuf::from("/path/to/config");
@@ -632,6 +1275,44 @@ mod test {
         assert!(cfg.is_err());
     }
 
+    #[derive(Debug)]
+    struct TestProvider;
+
+    impl KernelConfigProvider for TestProvider {
+        fn source(&self) -> KernelConfigSource {
+            KernelConfigSource::Custom("test provider".to_string())
+        }
+
+        fn read(&self) -> KcheckResult<String> {
+            Ok("CONFIG_TEST=y\n# CONFIG_TEST_TWO is not set".to_string())
+        }
+    }
+
+    #[test]
+    fn success_kernel_config_provider() {
+        let cfg = KernelConfigBuilder::default()
+            .provider(TestProvider)
+            .build()
+            .expect("Expected to create a kernel config from a provider");
+
+        assert_eq!(
+            cfg.src,
+            KernelConfigSource::Custom("test provider".to_string())
+        );
+        assert_eq!(cfg.option("CONFIG_TEST").unwrap(), KconfigState::On);
+        assert_eq!(cfg.option("CONFIG_TEST_TWO").unwrap(), KconfigState::NotSet);
+    }
+
+    #[test]
+    fn fail_kernel_config_provider_with_system() {
+        let cfg = KernelConfigBuilder::default()
+            .provider(TestProvider)
+            .system()
+            .build();
+
+        assert!(cfg.is_err());
+    }
+
     #[test]
     fn success_does_not_match_on_similar() {
         let test_file_contents =
@@ -649,4 +1330,23 @@ mod test {
             assert_eq!(result.unwrap(), KconfigState::On);
         });
     }
+
+    #[test]
+    fn success_kernel_config_set_line() {
+        let mut cfg = KernelConfig::from_str(
+            "# Comment\nCONFIG_FOO=y\n# CONFIG_BAR is not set\nCONFIG_BAZ=m\nCONFIG_BAR=y\n",
+        )
+        .expect("Expected a kernel config");
+
+        assert_eq!(
+            cfg.set_line("CONFIG_BAR", "CONFIG_BAR=m"),
+            Some("# CONFIG_BAR is not set".to_string())
+        );
+        assert_eq!(cfg.set_line("CONFIG_QUX", "CONFIG_QUX=y"), None);
+        assert_eq!(
+            cfg.to_string(),
+            "# Comment\nCONFIG_FOO=y\nCONFIG_BAR=m\nCONFIG_BAZ=m\nCONFIG_QUX=y\n"
+        );
+        assert_eq!(cfg.option("CONFIG_BAR").unwrap(), KconfigState::Module);
+    }
 }
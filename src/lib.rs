@@ -7,24 +7,54 @@
 
 #[cfg(feature = "cli-table")]
 use cli_table::{CellStruct, Color, Style, Table};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+pub mod build_script;
+pub mod check;
 pub mod config;
+pub mod diagnostic;
+pub mod embed;
 pub mod error;
+pub mod filter;
+pub mod fix;
+#[cfg(feature = "git")]
+pub mod git;
 pub mod kconfig;
 pub mod kernel;
+pub mod report;
+pub mod schema;
+pub mod target;
+pub mod tree;
 mod util;
 
-use config::{KcheckConfig, KcheckConfigBuilder};
+use check::{Check, CheckContext};
+use config::{
+    Declaration, KcheckConfig, KcheckConfigBuilder, KcheckConfigFragment, KconfigGroup, Quantifier,
+};
 pub use error::{KcheckError, KcheckResult};
-use kconfig::KconfigState;
-use kernel::{KernelConfig, KernelConfigBuilder};
+use kconfig::{KconfigOption, KconfigState, Severity};
+use kernel::{
+    DuplicatePolicy, KernelConfig, KernelConfigBuilder, KernelConfigProvider, KernelConfigSource,
+    Provenance,
+};
+use target::{KernelVersion, Target, When};
+use tree::KconfigTree;
 
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
-enum CheckResult {
+/// The outcome of checking a single requirement.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize)]
+pub enum CheckResult {
     Pass,
     #[default]
     Fail,
+    /// The requirement does not apply to the target architecture or kernel version.
+    Skipped,
+    /// The requirement could not be checked, such as when its line in the kernel
+    /// config cannot be parsed.
+    Error,
 }
 
 impl From<bool> for CheckResult {
@@ -42,22 +72,194 @@ impl std::fmt::Display for CheckResult {
         match self {
             CheckResult::Pass => write!(f, "Pass"),
             CheckResult::Fail => write!(f, "Fail"),
+            CheckResult::Skipped => write!(f, "Skipped"),
+            CheckResult::Error => write!(f, "Error"),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// The desired or found state of a checked requirement.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum ResultState {
+    /// The state could not be determined, such as when a requirement could not be checked.
+    #[default]
+    Unknown,
+    /// The state of a kernel config option.
+    Kconfig(KconfigState),
+    /// A description of the state of a group or application defined check, or the
+    /// reason a requirement was skipped.
+    Custom(String),
+}
+
+impl ResultState {
+    /// The kernel config option state, if this is the state of a kernel config option.
+    pub fn kconfig(&self) -> Option<&KconfigState> {
+        match self {
+            ResultState::Kconfig(state) => Some(state),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ResultState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ResultState::Unknown => write!(f, "Unknown"),
+            ResultState::Kconfig(state) => write!(f, "{state}"),
+            ResultState::Custom(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
 #[cfg_attr(feature = "cli-table", derive(Table))]
 pub struct KcheckConfigResult {
     #[cfg_attr(feature = "cli-table", table(title = "Config Option"))]
     name: String,
     #[cfg_attr(feature = "cli-table", table(title = "Desired State"))]
-    desired_state: KconfigState,
+    desired_state: ResultState,
     #[cfg_attr(feature = "cli-table", table(title = "Kernel State"))]
-    kernel_state: KconfigState,
+    kernel_state: ResultState,
     #[cfg_attr(feature = "cli-table", table(title = "Result"))]
     #[cfg_attr(feature = "cli-table", table(customize_fn = "convert_check_result"))]
     result: CheckResult,
+    #[cfg_attr(feature = "cli-table", table(title = "Remediation"))]
+    #[cfg_attr(feature = "cli-table", table(display_fn = "display_remediation"))]
+    remediation: Option<String>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    url: Option<String>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    fragment: Option<String>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    reason: Option<String>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    severity: Severity,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    members: Vec<KcheckConfigResult>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    provenance: Option<Provenance>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    declaration: Option<Declaration>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    warnings: Vec<String>,
+    #[cfg_attr(feature = "cli-table", table(skip))]
+    error: Option<String>,
+}
+
+impl KcheckConfigResult {
+    /// Name of the checked config option or application defined check.
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The desired state.
+    pub fn desired_state(&self) -> ResultState {
+        self.desired_state.clone()
+    }
+
+    /// The state found in the kernel config or system.
+    pub fn kernel_state(&self) -> ResultState {
+        self.kernel_state.clone()
+    }
+
+    /// The outcome of the check.
+    pub fn result(&self) -> CheckResult {
+        self.result
+    }
+
+    /// The severity of the checked requirement.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// The results of each option in a group requirement.
+    ///
+    /// Empty for single options and application defined checks.
+    pub fn members(&self) -> Vec<KcheckConfigResult> {
+        self.members.clone()
+    }
+
+    /// The line of the kernel config that the checked option was read from.
+    ///
+    /// `None` for options that were not found or skipped, groups, and application
+    /// defined checks.
+    pub fn provenance(&self) -> Option<Provenance> {
+        self.provenance.clone()
+    }
+
+    /// The line of the Kcheck config file that declares the checked requirement.
+    ///
+    /// `None` for requirements that were not read from a file and application
+    /// defined checks.
+    pub fn declaration(&self) -> Option<Declaration> {
+        self.declaration.clone()
+    }
+
+    /// Problems found in the kernel config that did not stop the check, such as
+    /// duplicate options.
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+
+    /// How to fix a failed requirement, such as the `.config` line that sets the
+    /// option to its desired state.
+    ///
+    /// Uses the `fix` of the requirement if it has one. Only set for results with
+    /// [`CheckResult::Fail`].
+    pub fn remediation(&self) -> Option<String> {
+        self.remediation.clone()
+    }
+
+    /// A link to more information about the requirement.
+    pub fn url(&self) -> Option<String> {
+        self.url.clone()
+    }
+
+    /// Name of the fragment that the requirement belongs to.
+    pub fn fragment(&self) -> Option<String> {
+        self.fragment.clone()
+    }
+
+    /// The reason given by the fragment that the requirement belongs to.
+    pub fn reason(&self) -> Option<String> {
+        self.reason.clone()
+    }
+
+    /// The message of the error that prevented the requirement from being checked.
+    ///
+    /// Only set for results with [`CheckResult::Error`].
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    /// Record the fragment that the requirement belongs to.
+    fn with_fragment(mut self, fragment: Option<&KcheckConfigFragment>) -> Self {
+        self.fragment = fragment.and_then(|f| f.name());
+        self.reason = fragment.and_then(|f| f.reason());
+        self
+    }
+
+    /// Record a requirement that could not be checked.
+    fn from_error(
+        name: String,
+        desired_state: ResultState,
+        severity: Severity,
+        e: KcheckError,
+    ) -> Self {
+        Self {
+            name,
+            desired_state,
+            result: CheckResult::Error,
+            severity,
+            error: Some(e.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "cli-table")]
+fn display_remediation(remediation: &Option<String>) -> String {
+    remediation.clone().unwrap_or_default()
 }
 
 #[cfg(feature = "cli-table")]
@@ -65,6 +267,8 @@ fn convert_check_result(cell: CellStruct, result: &CheckResult) -> CellStruct {
     match result {
         CheckResult::Pass => cell.foreground_color(Some(Color::Green)),
         CheckResult::Fail => cell.foreground_color(Some(Color::Red)),
+        CheckResult::Skipped => cell.foreground_color(Some(Color::Yellow)),
+        CheckResult::Error => cell.foreground_color(Some(Color::Magenta)),
     }
 }
 
@@ -73,9 +277,19 @@ fn convert_check_result(cell: CellStruct, result: &CheckResult) -> CellStruct {
 pub struct KcheckBuilder {
     use_system_kernel: bool,
     user_kernel_files: Vec<PathBuf>,
+    kernel_provider: Option<Arc<dyn KernelConfigProvider>>,
+    kconfig_tree: Option<KconfigTree>,
+    duplicates: DuplicatePolicy,
+    strict: bool,
+    arch: Option<String>,
+    kernel_version: Option<KernelVersion>,
 
     use_system_config: bool,
     user_config_files: Vec<PathBuf>,
+    profile: Option<String>,
+
+    #[cfg(feature = "elf")]
+    binaries: Vec<PathBuf>,
 }
 
 impl KcheckBuilder {
@@ -85,12 +299,60 @@ impl KcheckBuilder {
         self
     }
 
-    /// Add new Kconfig parameters using a user-provided kernel config file.
+    /// Add new Kconfig parameters using user-provided kernel config files.
+    ///
+    /// Multiple files are merged in order.
     pub fn kernel_fragments(mut self, files: Vec<PathBuf>) -> Self {
         self.user_kernel_files.extend(files);
         self
     }
 
+    /// Add new Kconfig parameters using a user-provided [`KernelConfigProvider`].
+    pub fn kernel_provider<P: KernelConfigProvider + 'static>(mut self, provider: P) -> Self {
+        self.kernel_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Expand the kernel config using the Kconfig symbols of a kernel source tree.
+    ///
+    /// This allows a defconfig to be checked as the full config produced by
+    /// `make olddefconfig`, including defaults and selected options.
+    pub fn kconfig_tree(mut self, tree: KconfigTree) -> Self {
+        self.kconfig_tree = Some(tree);
+        self
+    }
+
+    /// Set how options that are set more than once in the kernel config are handled.
+    ///
+    /// With a warning policy, the check continues and each affected result
+    /// includes a warning.
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Stop the check at the first requirement that cannot be checked.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Set the architecture used to evaluate conditional requirements.
+    ///
+    /// Defaults to the architecture detected from the kernel config.
+    pub fn arch(mut self, arch: &str) -> Self {
+        self.arch = Some(arch.to_string());
+        self
+    }
+
+    /// Set the kernel version used to evaluate conditional requirements.
+    ///
+    /// Defaults to the version detected from the kernel config header.
+    pub fn kernel_version(mut self, version: KernelVersion) -> Self {
+        self.kernel_version = Some(version);
+        self
+    }
+
     /// Add new config parameters using the system's config files stored in the `/etc/` directory.
     pub fn system_config(mut self) -> Self {
         self.use_system_config = true;
@@ -103,10 +365,24 @@ impl KcheckBuilder {
         self
     }
 
+    /// Add new config parameters using configs embedded in ELF binaries.
+    #[cfg(feature = "elf")]
+    pub fn binaries(mut self, files: Vec<PathBuf>) -> Self {
+        self.binaries.extend(files);
+        self
+    }
+
+    /// Only check the config fragments that belong to a named profile.
+    pub fn profile(mut self, profile: &str) -> Self {
+        self.profile = Some(profile.to_string());
+        self
+    }
+
     /// Build the [`Kcheck`] instance using the provided configuration.
     pub fn build(self) -> KcheckResult<Kcheck> {
         // Gather all the kernel configuration files
-        let mut user_kernel_config_builder = KernelConfigBuilder::default();
+        let mut user_kernel_config_builder =
+            KernelConfigBuilder::default().duplicates(self.duplicates);
         if self.use_system_kernel {
             user_kernel_config_builder = user_kernel_config_builder.system();
         };
@@ -117,7 +393,14 @@ impl KcheckBuilder {
             }
         }
 
-        let user_kernel_config = user_kernel_config_builder.build()?;
+        if let Some(provider) = self.kernel_provider {
+            user_kernel_config_builder = user_kernel_config_builder.provider(provider);
+        }
+
+        let mut user_kernel_config = user_kernel_config_builder.build()?;
+        if let Some(tree) = self.kconfig_tree {
+            user_kernel_config = tree.resolve(&user_kernel_config)?;
+        }
 
         // Gather all the Kcheck configuration files
         let mut kcheck_config_builder = KcheckConfigBuilder::default();
@@ -125,11 +408,32 @@ impl KcheckBuilder {
             kcheck_config_builder = kcheck_config_builder.system();
         };
 
-        let kcheck_config = kcheck_config_builder
+        #[cfg(feature = "elf")]
+        {
+            kcheck_config_builder = kcheck_config_builder.binaries(self.binaries);
+        }
+
+        let mut kcheck_config = kcheck_config_builder
             .config_files(self.user_config_files)
             .build()?;
 
-        Ok(Kcheck::new(kcheck_config, user_kernel_config))
+        if let Some(profile) = self.profile {
+            kcheck_config.select_profile(&profile)?;
+        }
+
+        let mut kcheck = Kcheck::new(kcheck_config, user_kernel_config).with_strict(self.strict);
+        if self.arch.is_some() || self.kernel_version.is_some() {
+            let mut target = Target::from_kernel(&kcheck.kernel);
+            if let Some(arch) = &self.arch {
+                target = target.with_arch(arch);
+            }
+            if let Some(version) = self.kernel_version {
+                target = target.with_version(version);
+            }
+            kcheck = kcheck.with_target(target);
+        }
+
+        Ok(kcheck)
     }
 }
 
@@ -140,30 +444,346 @@ pub struct Kcheck {
 
     /// The kernel configuration to check.
     kernel: KernelConfig,
+
+    /// Application defined checks.
+    checks: Vec<Box<dyn Check>>,
+
+    /// Root of the filesystem made available to application defined checks.
+    root: Option<PathBuf>,
+
+    /// Target used to evaluate conditional requirements.
+    target: Option<Target>,
+
+    /// Stop at the first requirement that cannot be checked.
+    strict: bool,
 }
 
 impl Kcheck {
     /// Create a new [`Kcheck`] instance with previously defined configuration.
     pub fn new(config: KcheckConfig, kernel: KernelConfig) -> Self {
-        Self { config, kernel }
+        Self {
+            config,
+            kernel,
+            ..Default::default()
+        }
+    }
+
+    /// Register an application defined check.
+    pub fn with_check<C: Check + 'static>(mut self, check: C) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// Set the root of the filesystem used by application defined checks.
+    ///
+    /// Defaults to `/`.
+    pub fn with_root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.root = Some(root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the target used to evaluate conditional requirements.
+    ///
+    /// Defaults to the target detected from the kernel config with [`Target::from_kernel`].
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Return an error from [`Kcheck::perform_check`] as soon as a requirement
+    /// cannot be checked, instead of recording a [`CheckResult::Error`] result.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// The source of the kernel config that is checked.
+    pub fn kernel_source(&self) -> KernelConfigSource {
+        self.kernel.source()
+    }
+
+    /// Check a kernel option against the kernel config.
+    ///
+    /// Options with a glob or regex name produce a result for every matching option
+    /// in the kernel config, or a single `NotFound` result if nothing matches.
+    fn check_option(
+        &self,
+        fragment: Option<&KcheckConfigFragment>,
+        option: &KconfigOption,
+        target: &Target,
+    ) -> KcheckResult<Vec<KcheckConfigResult>> {
+        let desired_state = option.state();
+        desired_state.validate()?;
+
+        // Skip requirements that do not apply to the target or kernel config
+        let conditions: Vec<When> = fragment
+            .and_then(|f| f.when())
+            .into_iter()
+            .chain(option.when())
+            .collect();
+        let warnings: Vec<String> = match conditions.is_empty() {
+            true => Vec::new(),
+            false => self.target_warning(target).into_iter().collect(),
+        };
+        let unmet = conditions.into_iter().find(|w| !w.matches(target));
+
+        let unmet = unmet.map(|w| w.to_string()).or_else(|| {
+            option
+                .condition()
+                .filter(|c| !c.matches(&self.kernel))
+                .map(|c| c.to_string())
+        });
+
+        if let Some(requirement) = unmet {
+            return Ok(vec![KcheckConfigResult {
+                name: option.name(),
+                desired_state: ResultState::Kconfig(desired_state),
+                kernel_state: ResultState::Custom(format!("Requires {requirement}")),
+                result: CheckResult::Skipped,
+                url: option.url(),
+                severity: option.severity(),
+                declaration: option.declaration(),
+                warnings,
+                ..Default::default()
+            }]);
+        }
+
+        let mut states = Vec::new();
+        match option.name_pattern()? {
+            Some(pattern) => {
+                // Entries list each option once, with the value chosen by the duplicate policy
+                for (name, _) in self.kernel.entries() {
+                    if pattern.is_match(&name) {
+                        let (kernel_state, provenance, warning) = self.kernel.resolve(&name)?;
+                        states.push((name, kernel_state, provenance, warning));
+                    }
+                }
+
+                if states.is_empty() {
+                    states.push((option.name(), KconfigState::NotFound, None, None));
+                }
+            }
+            None => {
+                let (kernel_state, provenance, warning) = self.kernel.resolve(&option.name())?;
+                states.push((option.name(), kernel_state, provenance, warning));
+            }
+        }
+
+        states
+            .into_iter()
+            .map(|(name, kernel_state, provenance, warning)| {
+                let result = desired_state.check(kernel_state.clone()).into();
+                let remediation = match result {
+                    CheckResult::Fail => option.fix().or_else(|| desired_state.config_line(&name)),
+                    _ => None,
+                };
+
+                Ok(KcheckConfigResult {
+                    warnings: warnings.iter().cloned().chain(warning).collect(),
+                    name,
+                    desired_state: ResultState::Kconfig(desired_state.clone()),
+                    kernel_state: ResultState::Kconfig(kernel_state),
+                    result,
+                    remediation,
+                    url: option.url(),
+                    severity: option.severity(),
+                    provenance,
+                    declaration: option.declaration(),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// A warning for conditional requirements when the target was detected from the
+    /// running system, but the kernel config was read from a file for another system.
+    fn target_warning(&self, target: &Target) -> Option<String> {
+        let source = self.kernel.source();
+        let file = matches!(
+            source,
+            KernelConfigSource::File(_) | KernelConfigSource::Git { .. }
+        );
+
+        (target.from_uname() && file && !source.is_system()).then(|| {
+            format!(
+                "The target architecture or kernel version is not set in {source} and was detected from the running system"
+            )
+        })
+    }
+
+    /// Check a kernel option, recording an error as a [`CheckResult::Error`] result
+    /// unless strict mode is enabled.
+    fn check_option_or_error(
+        &self,
+        fragment: Option<&KcheckConfigFragment>,
+        option: &KconfigOption,
+        target: &Target,
+    ) -> KcheckResult<Vec<KcheckConfigResult>> {
+        let results = match self.check_option(fragment, option, target) {
+            Err(e) if !self.strict => vec![KcheckConfigResult {
+                declaration: option.declaration(),
+                ..KcheckConfigResult::from_error(
+                    option.name(),
+                    ResultState::Kconfig(option.state()),
+                    option.severity(),
+                    e,
+                )
+            }],
+            result => result?,
+        };
+
+        Ok(results
+            .into_iter()
+            .map(|r| r.with_fragment(fragment))
+            .collect())
+    }
+
+    /// Check a group of kernel options against the kernel config.
+    ///
+    /// Skipped members do not count towards the quantifier. The group is skipped
+    /// if all of its members are skipped, and is an error if any member could not
+    /// be checked.
+    fn check_group(
+        &self,
+        fragment: &KcheckConfigFragment,
+        quantifier: Quantifier,
+        group: &KconfigGroup,
+        target: &Target,
+    ) -> KcheckResult<KcheckConfigResult> {
+        let members = group
+            .kernel()
+            .iter()
+            .map(|option| self.check_option_or_error(Some(fragment), option, target))
+            .collect::<KcheckResult<Vec<Vec<KcheckConfigResult>>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<KcheckConfigResult>>();
+
+        let checked: Vec<&KcheckConfigResult> = members
+            .iter()
+            .filter(|m| m.result != CheckResult::Skipped)
+            .collect();
+        let passed = checked
+            .iter()
+            .filter(|m| m.result == CheckResult::Pass)
+            .count();
+
+        let errors: Vec<String> = members
+            .iter()
+            .filter_map(|m| m.error().map(|e| format!("{}: {e}", m.name)))
+            .collect();
+
+        let result = if !errors.is_empty() {
+            CheckResult::Error
+        } else if checked.is_empty() {
+            CheckResult::Skipped
+        } else {
+            quantifier.satisfied(passed, checked.len()).into()
+        };
+
+        let desired_state = members
+            .iter()
+            .map(|m| format!("{}={}", m.name, m.desired_state))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let kernel_state = members
+            .iter()
+            .map(|m| format!("{}={} ({})", m.name, m.kernel_state, m.result))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        // Any failing member of an `AnyOf` group fixes it, while every failing member
+        // of an `AllOf` group has to be fixed. A `NoneOf` group is fixed by disabling
+        // every enabled member that matches, like `Fixer::group`
+        let fixes: Vec<String> = members
+            .iter()
+            .filter(|m| m.result == CheckResult::Fail)
+            .filter_map(|m| m.remediation())
+            .collect();
+        let disables: Vec<String> = members
+            .iter()
+            .filter(|m| m.result == CheckResult::Pass)
+            .filter(|m| {
+                matches!(
+                    m.desired_state.kconfig(),
+                    Some(KconfigState::On | KconfigState::Module | KconfigState::Enabled)
+                )
+            })
+            .map(|m| format!("# {} is not set", m.name))
+            .collect();
+        let remediation = match (result, quantifier) {
+            (CheckResult::Fail, Quantifier::AnyOf) => Some(fixes.join(" or ")),
+            (CheckResult::Fail, Quantifier::AllOf) => Some(fixes.join("\n")),
+            (CheckResult::Fail, Quantifier::NoneOf) => Some(disables.join("\n")),
+            _ => None,
+        }
+        .filter(|r| !r.is_empty());
+
+        Ok(KcheckConfigResult {
+            name: group.name(),
+            desired_state: ResultState::Custom(format!("{quantifier} {desired_state}")),
+            kernel_state: ResultState::Custom(kernel_state),
+            result,
+            remediation,
+            severity: group.severity(),
+            warnings: members.iter().flat_map(|m| m.warnings()).collect(),
+            members,
+            declaration: group.declaration(),
+            error: (!errors.is_empty()).then(|| errors.join("; ")),
+            ..Default::default()
+        }
+        .with_fragment(Some(fragment)))
     }
 
     /// Returns a list of desired configuration options and their current state in a kernel config.
+    ///
+    /// Requirements that cannot be checked are returned as [`CheckResult::Error`]
+    /// results, unless strict mode is enabled with [`Kcheck::with_strict`].
     pub fn perform_check(&self) -> KcheckResult<Vec<KcheckConfigResult>> {
-        let config = self.config.clone().into_iter();
-
         let mut results = Vec::new();
+        let target = match &self.target {
+            Some(t) => t.clone(),
+            None => Target::from_kernel(&self.kernel),
+        };
+
+        for (fragment, option) in self.config.requirements() {
+            results.extend(self.check_option_or_error(fragment, option, &target)?);
+        }
+
+        for fragment in self.config.fragment.iter().flatten() {
+            for (quantifier, group) in fragment.groups() {
+                results.push(self.check_group(fragment, quantifier, group, &target)?);
+            }
+        }
 
-        for (name, desired_state) in config {
-            let kernel_state = self.kernel.option(&name)?;
-            let cfg_result = desired_state.check(kernel_state.clone());
+        let root = self.root.clone().unwrap_or_else(|| PathBuf::from("/"));
+        let ctx = CheckContext::new(&self.kernel, &root);
+        for check in &self.checks {
+            let result = match check.evaluate(&ctx) {
+                Ok(outcome) => KcheckConfigResult {
+                    name: check.name(),
+                    desired_state: ResultState::Custom(check.description()),
+                    kernel_state: ResultState::Custom(outcome.actual()),
+                    result: outcome.result(),
+                    remediation: match outcome.result() {
+                        CheckResult::Fail => check.remediation(),
+                        _ => None,
+                    },
+                    url: check.url(),
+                    severity: check.severity(),
+                    ..Default::default()
+                },
+                Err(e) if !self.strict => KcheckConfigResult::from_error(
+                    check.name(),
+                    ResultState::Custom(check.description()),
+                    check.severity(),
+                    e,
+                ),
+                Err(e) => return Err(e),
+            };
 
-            results.push(KcheckConfigResult {
-                name,
-                desired_state,
-                kernel_state,
-                result: cfg_result.into(),
-            });
+            results.push(result);
         }
 
         Ok(results)
@@ -175,8 +795,10 @@ mod tests {
     use std::sync::LazyLock;
 
     use super::*;
+    use check::CheckOutcome;
     use config::KcheckConfigBuilder;
-    use kconfig::KconfigOption;
+    use kconfig::{KconfigOption, KconfigState};
+    use std::str::FromStr;
 
     const EXPECTED_KERNEL_CONFIG: [&str; 4] = [
         "CONFIG_FOO=y",
@@ -258,4 +880,588 @@ mod tests {
             );
         });
     }
+
+    const TEST_KCHECK_CONDITIONAL_TOML: &str = r#"
+        [[kernel]]
+        name = "CONFIG_FOO"
+        state = "On"
+        when = { kernel = ">=6.0" }
+
+        [[kernel]]
+        name = "CONFIG_OLD_NAME"
+        state = "On"
+        when = { kernel = "<5.15" }
+
+        [[kernel]]
+        name = "CONFIG_BAR"
+        state = "Module"
+        if = "CONFIG_FOO && !CONFIG_BAZ"
+
+        [[kernel]]
+        name = "CONFIG_USB_ACM"
+        state = "Off"
+        requires = "CONFIG_BAZ"
+
+        [[fragment]]
+        name = "pointer-auth"
+        when = { arch = ["arm64"] }
+
+        [[fragment.kernel]]
+        name = "CONFIG_ARM64_PTR_AUTH"
+        state = "On"
+    "#;
+
+    #[test]
+    fn success_kcheck_conditional() {
+        let config =
+            KcheckConfig::from_str(TEST_KCHECK_CONDITIONAL_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let target = Target::new(Some("x86_64"), Some("6.6.0".parse().unwrap()));
+        let results = Kcheck::new(config.clone(), kernel_cfg.clone())
+            .with_target(target)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        let expected = [
+            ("CONFIG_FOO", CheckResult::Pass),
+            ("CONFIG_OLD_NAME", CheckResult::Skipped),
+            ("CONFIG_BAR", CheckResult::Pass),
+            ("CONFIG_USB_ACM", CheckResult::Skipped),
+            ("CONFIG_ARM64_PTR_AUTH", CheckResult::Skipped),
+        ];
+        assert_eq!(results.len(), expected.len());
+        for (result, (name, expected)) in results.iter().zip(expected) {
+            assert_eq!(result.name(), name);
+            assert_eq!(result.result(), expected, "{name}");
+        }
+        assert_eq!(
+            results[3].kernel_state(),
+            ResultState::Custom("Requires CONFIG_BAZ".to_string())
+        );
+        assert_eq!(
+            results[4].kernel_state(),
+            ResultState::Custom("Requires arch arm64".to_string())
+        );
+        assert_eq!(
+            results[4].desired_state().kconfig(),
+            Some(&KconfigState::On)
+        );
+
+        let target = Target::new(Some("aarch64"), Some("5.10.0".parse().unwrap()));
+        let results = Kcheck::new(config, kernel_cfg)
+            .with_target(target)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        assert_eq!(results[0].result(), CheckResult::Skipped);
+        assert_eq!(results[1].result(), CheckResult::Fail);
+        assert_eq!(results[4].result(), CheckResult::Fail);
+    }
+
+    const TEST_KCHECK_GROUP_TOML: &str = r#"
+        [[fragment]]
+        name = "groups"
+
+        [[fragment.any_of]]
+        name = "foo-or-qux"
+        kernel = [
+            { name = "CONFIG_QUX", state = "On" },
+            { name = "CONFIG_FOO", state = "On" },
+        ]
+
+        [[fragment.any_of]]
+        kernel = [
+            { name = "CONFIG_QUX", state = "On" },
+            { name = "CONFIG_BAZ", state = "On" },
+        ]
+
+        [[fragment.all_of]]
+        kernel = [
+            { name = "CONFIG_FOO", state = "On" },
+            { name = "CONFIG_BAR", state = "Enabled" },
+            { name = "CONFIG_OLD_NAME", state = "On", when = { kernel = "<5.15" } },
+        ]
+
+        [[fragment.none_of]]
+        severity = "Warning"
+        kernel = [
+            { name = "CONFIG_BAZ", state = "Enabled" },
+            { name = "CONFIG_USB_ACM", state = "Enabled" },
+        ]
+    "#;
+
+    #[test]
+    fn success_kcheck_groups() {
+        let config = KcheckConfig::from_str(TEST_KCHECK_GROUP_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let target = Target::new(Some("x86_64"), Some("6.6.0".parse().unwrap()));
+        let results = Kcheck::new(config, kernel_cfg)
+            .with_target(target)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        let expected = [
+            ("foo-or-qux", CheckResult::Pass),
+            ("CONFIG_QUX, CONFIG_BAZ", CheckResult::Fail),
+            ("CONFIG_FOO, CONFIG_BAR, CONFIG_OLD_NAME", CheckResult::Pass),
+            ("CONFIG_BAZ, CONFIG_USB_ACM", CheckResult::Fail),
+        ];
+        assert_eq!(results.len(), expected.len());
+        for (result, (name, expected)) in results.iter().zip(expected) {
+            assert_eq!(result.name(), name);
+            assert_eq!(result.result(), expected, "{name}");
+        }
+
+        assert_eq!(
+            results[0].desired_state().to_string(),
+            "Any of CONFIG_QUX=On, CONFIG_FOO=On"
+        );
+        let members = results[0].members();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].result(), CheckResult::Fail);
+        assert_eq!(members[1].result(), CheckResult::Pass);
+
+        assert_eq!(results[2].members()[2].result(), CheckResult::Skipped);
+        assert_eq!(results[3].severity(), Severity::Warning);
+        assert!(results[3]
+            .kernel_state()
+            .to_string()
+            .ends_with("CONFIG_USB_ACM=On (Pass)"));
+    }
+
+    #[test]
+    fn success_kcheck_group_remediation() {
+        let config = KcheckConfig::from_str(TEST_KCHECK_GROUP_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let target = Target::new(Some("x86_64"), Some("6.6.0".parse().unwrap()));
+        let results = Kcheck::new(config, kernel_cfg)
+            .with_target(target)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        // Only the enabled member of the `none_of` group is disabled
+        assert_eq!(results[3].result(), CheckResult::Fail);
+        assert_eq!(
+            results[3].remediation(),
+            Some("# CONFIG_USB_ACM is not set".to_string())
+        );
+        assert_eq!(results[0].remediation(), None);
+    }
+
+    #[test]
+    fn success_kcheck_provenance() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        let base = tmpdir.path().join("base.config");
+        let fragment = tmpdir.path().join("usb.config");
+        std::fs::write(&base, EXPECTED_KERNEL_CONFIG[..3].join("\n")).unwrap();
+        std::fs::write(&fragment, EXPECTED_KERNEL_CONFIG[3]).unwrap();
+        let config = tmpdir.path().join("kcheck.toml");
+        std::fs::write(&config, TEST_KCHECK_CONFIG_TOML).unwrap();
+
+        let results = KcheckBuilder::default()
+            .kernel_fragments(vec![base.clone(), fragment.clone()])
+            .config_fragments(vec![config])
+            .build()
+            .expect("Expected to build a Kcheck system")
+            .perform_check()
+            .expect("Expected to perform check");
+
+        let provenance: Vec<(PathBuf, usize)> = results
+            .iter()
+            .filter_map(|r| r.provenance())
+            .map(|p| match p.source() {
+                kernel::KernelConfigSource::File(path) => (path, p.line()),
+                source => panic!("Expected a file source, found {source:?}"),
+            })
+            .collect();
+
+        assert_eq!(
+            provenance,
+            [
+                (base.clone(), 1),
+                (base.clone(), 2),
+                (base, 3),
+                (fragment, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn success_kcheck_duplicates_warning() {
+        let config = KcheckConfig::from_str(TEST_KCHECK_CONFIG_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfigBuilder::default()
+            .options(&[
+                ("CONFIG_FOO", KconfigState::Off),
+                ("CONFIG_BAR", KconfigState::Module),
+                ("CONFIG_BAZ", KconfigState::Off),
+                ("CONFIG_USB_ACM", KconfigState::On),
+                ("CONFIG_FOO", KconfigState::On),
+            ])
+            .duplicates(DuplicatePolicy::WarnLast)
+            .build()
+            .expect("Expected to build a kernel config");
+
+        let results = Kcheck::new(config, kernel_cfg)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        assert!(results.iter().all(|r| r.result() == CheckResult::Pass));
+        assert_eq!(
+            results[0].warnings(),
+            ["CONFIG_FOO is set 2 times, using the last value from <string>:5"]
+        );
+        assert!(results[1..].iter().all(|r| r.warnings().is_empty()));
+    }
+
+    #[test]
+    fn success_kcheck_target_warning() {
+        let tmpdir = tempfile::tempdir().expect("Failed to create temp dir");
+        let kernel = tmpdir.path().join("board.config");
+        let config = tmpdir.path().join("kcheck.toml");
+        std::fs::write(&kernel, EXPECTED_KERNEL_CONFIG.join("\n")).unwrap();
+        std::fs::write(
+            &config,
+            r#"
+            [[kernel]]
+            name = "CONFIG_FOO"
+            state = "On"
+            when = { arch = ["arm64"] }
+
+            [[kernel]]
+            name = "CONFIG_BAR"
+            state = "Module"
+            "#,
+        )
+        .unwrap();
+
+        let builder = KcheckBuilder::default()
+            .kernel_fragments(vec![kernel.clone()])
+            .config_fragments(vec![config]);
+
+        // The kernel config does not set its target, so it is taken from `uname`
+        let results = builder
+            .clone()
+            .build()
+            .expect("Expected to build a Kcheck system")
+            .perform_check()
+            .expect("Expected to perform check");
+        assert_eq!(
+            results[0].warnings(),
+            [format!(
+                "The target architecture or kernel version is not set in {} and was detected from the running system",
+                kernel.to_string_lossy()
+            )]
+        );
+        assert!(results[1].warnings().is_empty());
+
+        let results = builder
+            .arch("aarch64")
+            .kernel_version(KernelVersion::new(6, 6, 0))
+            .build()
+            .expect("Expected to build a Kcheck system")
+            .perform_check()
+            .expect("Expected to perform check");
+        assert_eq!(results[0].result(), CheckResult::Pass);
+        assert!(results.iter().all(|r| r.warnings().is_empty()));
+    }
+
+    #[test]
+    fn success_kcheck_remediation() {
+        let config = KcheckConfigBuilder::default()
+            .kernel(vec![
+                KconfigOption::new("CONFIG_FOO", KconfigState::On),
+                KconfigOption::new("CONFIG_BAR", KconfigState::Off),
+                KconfigOption::new("CONFIG_BAZ", KconfigState::Module)
+                    .with_fix("Enable BAZ in the board fragment")
+                    .with_url("https://example.com/baz"),
+            ])
+            .fragment(vec![KcheckConfigFragment::new(
+                "groups".to_string(),
+                "Test groups".to_string(),
+                Vec::new(),
+            )
+            .with_group(
+                Quantifier::AnyOf,
+                KconfigGroup::new(vec![
+                    KconfigOption::new("CONFIG_QUX", KconfigState::On),
+                    KconfigOption::new("CONFIG_BAZ", KconfigState::On),
+                ]),
+            )
+            .with_group(
+                Quantifier::AllOf,
+                KconfigGroup::new(vec![
+                    KconfigOption::new("CONFIG_QUX", KconfigState::Module),
+                    KconfigOption::new("CONFIG_FOO", KconfigState::Module),
+                ]),
+            )])
+            .build()
+            .expect("Expected to build a Kcheck config");
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let results = Kcheck::new(config, kernel_cfg)
+            .with_check(TestPathCheck("/does/not/exist"))
+            .perform_check()
+            .expect("Expected to perform check");
+
+        let remediations: Vec<Option<String>> = results.iter().map(|r| r.remediation()).collect();
+        assert_eq!(
+            remediations,
+            [
+                None,
+                Some("# CONFIG_BAR is not set".to_string()),
+                Some("Enable BAZ in the board fragment".to_string()),
+                Some("CONFIG_QUX=y or CONFIG_BAZ=y".to_string()),
+                Some("CONFIG_QUX=m\nCONFIG_FOO=m".to_string()),
+                Some("mkdir -p /does/not/exist".to_string()),
+            ]
+        );
+        assert_eq!(
+            results[2].url(),
+            Some("https://example.com/baz".to_string())
+        );
+    }
+
+    const TEST_KCHECK_PATTERN_TOML: &str = r#"
+        [[kernel]]
+        name = "CONFIG_*_DEBUG*"
+        state = "Disabled"
+
+        [[kernel]]
+        name = "/CONFIG_(KASAN|UBSAN)/"
+        state = "Disabled"
+
+        [[kernel]]
+        name = "CONFIG_LOCALVERSION"
+        state = { Regex = '-ourco-\d+' }
+    "#;
+
+    #[test]
+    fn success_kcheck_patterns() {
+        let config = KcheckConfig::from_str(TEST_KCHECK_PATTERN_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfigBuilder::default()
+            .options(&[
+                ("CONFIG_SLUB_DEBUG", KconfigState::On),
+                ("CONFIG_DEBUG_INFO", KconfigState::On),
+                ("CONFIG_PAGE_DEBUG_FLAGS", KconfigState::NotSet),
+                (
+                    "CONFIG_LOCALVERSION",
+                    KconfigState::Text("-ourco-7".to_string()),
+                ),
+            ])
+            .build()
+            .expect("Expected to build a kernel config");
+
+        let results = Kcheck::new(config, kernel_cfg)
+            .with_target(Target::new(Some("x86_64"), None))
+            .perform_check()
+            .expect("Expected to perform check");
+
+        let expected = [
+            ("CONFIG_SLUB_DEBUG", "On", CheckResult::Fail),
+            ("CONFIG_PAGE_DEBUG_FLAGS", "NotSet", CheckResult::Pass),
+            ("/CONFIG_(KASAN|UBSAN)/", "NotFound", CheckResult::Pass),
+            ("CONFIG_LOCALVERSION", "-ourco-7", CheckResult::Pass),
+        ];
+        assert_eq!(results.len(), expected.len());
+        for (result, (name, state, expected)) in results.iter().zip(expected) {
+            assert_eq!(result.name(), name);
+            assert_eq!(result.kernel_state().to_string(), state);
+            assert_eq!(result.result(), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn fail_kcheck_invalid_regex() {
+        let config = KcheckConfigBuilder::default()
+            .kernel(vec![KconfigOption::new(
+                "CONFIG_LOCALVERSION",
+                KconfigState::Regex("(".to_string()),
+            )])
+            .build()
+            .expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let result = Kcheck::new(config, kernel_cfg)
+            .with_strict(true)
+            .perform_check();
+        assert!(matches!(result, Err(KcheckError::Regex(_))));
+    }
+
+    #[test]
+    fn success_kcheck_records_errors() {
+        let config = KcheckConfig::from_str(TEST_KCHECK_CONFIG_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str(
+            "CONFIG_FOO=y\nCONFIG_BAR\n# CONFIG_BAZ is not set\nCONFIG_USB_ACM=y",
+        )
+        .expect("Expected to build a kernel config");
+
+        let results = Kcheck::new(config, kernel_cfg)
+            .with_check(TestFailingCheck)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        let expected = [
+            CheckResult::Pass,
+            CheckResult::Error,
+            CheckResult::Pass,
+            CheckResult::Pass,
+            CheckResult::Error,
+        ];
+        assert_eq!(
+            results.iter().map(|r| r.result()).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            results[1].error(),
+            Some(
+                "Kernel config parse error: 2:1: expected `CONFIG_BAR=<value>` or \
+                 `# CONFIG_BAR is not set`"
+                    .to_string()
+            )
+        );
+        assert_eq!(results[1].kernel_state(), ResultState::Unknown);
+        assert_eq!(results[0].error(), None);
+        assert_eq!(results[4].error(), Some("IO Error: unreadable".to_string()));
+    }
+
+    #[test]
+    fn success_kcheck_records_group_errors() {
+        let config = KcheckConfig::from_str(
+            r#"
+            [[fragment]]
+            name = "group"
+
+            [[fragment.any_of]]
+            kernel = [
+                { name = "CONFIG_FOO", state = "On" },
+                { name = "CONFIG_BAR", state = "On" },
+            ]
+            "#,
+        )
+        .expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str("CONFIG_FOO=y\nCONFIG_BAR")
+            .expect("Expected to build a kernel config");
+
+        let results = Kcheck::new(config, kernel_cfg)
+            .perform_check()
+            .expect("Expected to perform check");
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].result(), CheckResult::Error);
+        assert_eq!(
+            results[0].error(),
+            Some(
+                "CONFIG_BAR: Kernel config parse error: 2:1: expected `CONFIG_BAR=<value>` or \
+                 `# CONFIG_BAR is not set`"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn fail_kcheck_strict() {
+        let config = KcheckConfig::from_str(TEST_KCHECK_CONFIG_TOML).expect("Expected a config");
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let result = Kcheck::new(config, kernel_cfg)
+            .with_check(TestFailingCheck)
+            .with_strict(true)
+            .perform_check();
+        assert_eq!(
+            result.map(|_| ()),
+            Err(KcheckError::IoError("unreadable".to_string()))
+        );
+    }
+
+    struct TestFailingCheck;
+
+    impl Check for TestFailingCheck {
+        fn name(&self) -> String {
+            "failing".to_string()
+        }
+
+        fn description(&self) -> String {
+            "Readable".to_string()
+        }
+
+        fn evaluate(&self, _ctx: &CheckContext) -> KcheckResult<CheckOutcome> {
+            Err(KcheckError::IoError("unreadable".to_string()))
+        }
+    }
+
+    struct TestPathCheck(&'static str);
+
+    impl Check for TestPathCheck {
+        fn name(&self) -> String {
+            format!("path:{}", self.0)
+        }
+
+        fn description(&self) -> String {
+            "Exists".to_string()
+        }
+
+        fn severity(&self) -> Severity {
+            Severity::Warning
+        }
+
+        fn remediation(&self) -> Option<String> {
+            Some(format!("mkdir -p {}", self.0))
+        }
+
+        fn evaluate(&self, ctx: &CheckContext) -> KcheckResult<CheckOutcome> {
+            if ctx.exists(self.0) {
+                Ok(CheckOutcome::pass("Exists"))
+            } else {
+                Ok(CheckOutcome::fail("Missing"))
+            }
+        }
+    }
+
+    #[test]
+    fn success_kcheck_custom_check() {
+        let config = KcheckConfigBuilder::default()
+            .kernel(TEST_KCHECK_CONFIG.to_owned())
+            .build()
+            .expect("Expected to build a Kcheck config");
+
+        let kernel_cfg = KernelConfig::from_str(&EXPECTED_KERNEL_CONFIG.join("\n"))
+            .expect("Expected to build a kernel config");
+
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
+        std::fs::create_dir_all(root.path().join("dev")).expect("Failed to create dev dir");
+        std::fs::write(root.path().join("dev/kvm"), "").expect("Failed to create dev node");
+
+        let kcheck = Kcheck::new(config, kernel_cfg)
+            .with_root(root.path())
+            .with_check(TestPathCheck("/dev/kvm"))
+            .with_check(TestPathCheck("/dev/missing"));
+        let results = kcheck.perform_check().expect("Expected to perform check");
+
+        assert_eq!(results.len(), TEST_KCHECK_CONFIG.len() + 2);
+
+        let kvm = &results[TEST_KCHECK_CONFIG.len()];
+        assert_eq!(kvm.name(), "path:/dev/kvm");
+        assert_eq!(
+            kvm.desired_state(),
+            ResultState::Custom("Exists".to_string())
+        );
+        assert_eq!(kvm.result(), CheckResult::Pass);
+        assert_eq!(kvm.severity(), Severity::Warning);
+
+        let missing = &results[TEST_KCHECK_CONFIG.len() + 1];
+        assert_eq!(
+            missing.kernel_state(),
+            ResultState::Custom("Missing".to_string())
+        );
+        assert_eq!(missing.result(), CheckResult::Fail);
+    }
 }
@@ -0,0 +1,189 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{is_web_url, Report};
+use crate::{CheckResult, KcheckConfigResult};
+use std::fmt::Write;
+
+/// Styles embedded in the page so that it does not load any other files.
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 72em; color: #1f2328; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { border: 1px solid #d0d7de; padding: 0.4em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f6f8fa; }
+code { font-family: monospace; }
+.badge { border-radius: 1em; color: #fff; display: inline-block; font-size: 0.85em; padding: 0.1em 0.7em; }
+.pass { background: #1a7f37; }
+.fail { background: #cf222e; }
+.skipped { background: #9a6700; }
+.error { background: #8250df; }
+";
+
+/// Render a report as a self-contained HTML page, with a table of results for each fragment.
+pub(super) fn render(report: &Report) -> String {
+    let mut out = String::new();
+    let title = escape(&report.title());
+
+    let _ = writeln!(out, "<!DOCTYPE html>");
+    let _ = writeln!(out, "<html lang=\"en\">");
+    let _ = writeln!(out, "<head>");
+    let _ = writeln!(out, "<meta charset=\"utf-8\">");
+    let _ = writeln!(out, "<title>{title}</title>");
+    let _ = writeln!(out, "<style>\n{STYLE}</style>");
+    let _ = writeln!(out, "</head>");
+    let _ = writeln!(out, "<body>");
+    let _ = writeln!(out, "<h1>{title}</h1>");
+
+    let status = match report.passed() {
+        true => "<span class=\"badge pass\">Passed</span>",
+        false => "<span class=\"badge fail\">Failed</span>",
+    };
+
+    let _ = writeln!(out, "<ul>");
+    let _ = writeln!(out, "<li><strong>Status:</strong> {status}</li>");
+    if let Some(source) = &report.source {
+        let source = escape(&source.to_string());
+        let _ = writeln!(
+            out,
+            "<li><strong>Kernel config:</strong> <code>{source}</code></li>"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "<li><strong>Results:</strong> {}</li>",
+        report.summary()
+    );
+    let _ = writeln!(out, "</ul>");
+
+    for section in report.sections() {
+        let _ = writeln!(out, "<h2>{}</h2>", escape(&section.name));
+        if let Some(reason) = &section.reason {
+            let _ = writeln!(out, "<p>{}</p>", escape(reason));
+        }
+
+        let _ = writeln!(out, "<table>");
+        let _ = writeln!(
+            out,
+            "<tr><th>Result</th><th>Option</th><th>Severity</th><th>Desired State</th>\
+             <th>Kernel State</th><th>Remediation</th></tr>"
+        );
+        for result in section.results {
+            let _ = writeln!(out, "{}", row(result));
+        }
+        let _ = writeln!(out, "</table>");
+    }
+
+    let _ = writeln!(out, "</body>");
+    let _ = writeln!(out, "</html>");
+
+    out
+}
+
+/// A table row for a single result.
+fn row(result: &KcheckConfigResult) -> String {
+    let name = escape(&result.name());
+    let name = match result.url() {
+        Some(url) if is_web_url(&url) => {
+            format!("<a href=\"{}\"><code>{name}</code></a>", escape(&url))
+        }
+        Some(url) => format!("<code>{name}</code> ({})", escape(&url)),
+        None => format!("<code>{name}</code>"),
+    };
+
+    let remediation = result
+        .remediation()
+        .map(|r| {
+            r.lines()
+                .map(|l| format!("<code>{}</code>", escape(l)))
+                .collect::<Vec<String>>()
+                .join("<br>")
+        })
+        .unwrap_or_default();
+
+    format!(
+        "<tr><td>{}</td><td>{name}</td><td>{}</td><td>{}</td><td>{}</td><td>{remediation}</td></tr>",
+        badge(result.result()),
+        result.severity(),
+        escape(&result.desired_state().to_string()),
+        escape(&result.kernel_state().to_string()),
+    )
+}
+
+/// A badge for the outcome of a check.
+fn badge(result: CheckResult) -> String {
+    let class = match result {
+        CheckResult::Pass => "pass",
+        CheckResult::Fail => "fail",
+        CheckResult::Skipped => "skipped",
+        CheckResult::Error => "error",
+    };
+
+    format!("<span class=\"badge {class}\">{result}</span>")
+}
+
+/// Escape text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::KcheckConfig,
+        kernel::KernelConfig,
+        report::{test::helper_report, Report},
+        Kcheck,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn success_html() {
+        let html = helper_report().html();
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.ends_with("</html>\n"));
+        assert!(html.contains("<title>Kernel Config Report</title>"));
+        assert!(html.contains("<strong>Status:</strong> <span class=\"badge fail\">Failed</span>"));
+        assert!(html.contains("<strong>Kernel config:</strong> <code>board.config</code>"));
+        assert!(html.contains("<h2>usb</h2>\n<p>Serial &lt;console&gt; over USB</p>"));
+        assert!(html.contains(
+            "<tr><td><span class=\"badge fail\">Fail</span></td>\
+             <td><a href=\"https://example.com/acm\"><code>CONFIG_USB_ACM</code></a></td>\
+             <td>Error</td><td>Enabled (On or Module)</td><td>NotSet</td>\
+             <td><code>CONFIG_USB_ACM=y</code></td></tr>"
+        ));
+
+        // Nothing is loaded from other files
+        assert!(!html.contains("<link"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn success_html_url_scheme() {
+        let config = KcheckConfig::from_str(
+            r#"
+            [[kernel]]
+            name = "CONFIG_USB"
+            state = "On"
+            url = "javascript:alert(1)"
+            "#,
+        )
+        .expect("Expected a config");
+        let kernel = KernelConfig::from_str("").expect("Expected a kernel config");
+        let results = Kcheck::new(config, kernel)
+            .perform_check()
+            .expect("Expected to check the kernel config");
+
+        let html = Report::new(results).html();
+        assert!(!html.contains("<a href"));
+        assert!(html.contains("<td><code>CONFIG_USB</code> (javascript:alert(1))</td>"));
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{is_web_url, Report};
+use crate::{CheckResult, KcheckConfigResult};
+use std::fmt::Write;
+
+/// Render a report as Markdown, with a table of results for each fragment.
+pub(super) fn render(report: &Report) -> String {
+    let mut out = String::new();
+
+    let status = match report.passed() {
+        true => "✅ Passed",
+        false => "❌ Failed",
+    };
+
+    let _ = writeln!(out, "# {}\n", escape(&report.title()));
+    let _ = writeln!(out, "- **Status:** {status}");
+    if let Some(source) = &report.source {
+        let _ = writeln!(out, "- **Kernel config:** `{source}`");
+    }
+    let _ = writeln!(out, "- **Results:** {}", report.summary());
+
+    for section in report.sections() {
+        let _ = writeln!(out, "\n## {}\n", escape(&section.name));
+        if let Some(reason) = &section.reason {
+            let _ = writeln!(out, "{}\n", escape(reason));
+        }
+
+        let _ = writeln!(
+            out,
+            "| Result | Option | Severity | Desired State | Kernel State | Remediation |"
+        );
+        let _ = writeln!(out, "| --- | --- | --- | --- | --- | --- |");
+        for result in section.results {
+            let _ = writeln!(out, "{}", row(result));
+        }
+    }
+
+    out
+}
+
+/// A table row for a single result.
+fn row(result: &KcheckConfigResult) -> String {
+    let name = match result.url() {
+        Some(url) if is_web_url(&url) => format!("[`{}`](<{}>)", result.name(), encode(&url)),
+        Some(url) => format!("`{}` ({})", result.name(), escape(&url)),
+        None => format!("`{}`", result.name()),
+    };
+
+    let remediation = result
+        .remediation()
+        .map(|r| {
+            r.lines()
+                .map(|l| format!("`{l}`"))
+                .collect::<Vec<String>>()
+                .join("<br>")
+        })
+        .unwrap_or_default();
+
+    let cells = [
+        badge(result.result()).to_string(),
+        name,
+        result.severity().to_string(),
+        escape(&result.desired_state().to_string()),
+        escape(&result.kernel_state().to_string()),
+        remediation,
+    ];
+
+    let cells: Vec<String> = cells.iter().map(|c| c.replace('|', "\\|")).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+/// A badge for the outcome of a check.
+fn badge(result: CheckResult) -> &'static str {
+    match result {
+        CheckResult::Pass => "✅ Pass",
+        CheckResult::Fail => "❌ Fail",
+        CheckResult::Skipped => "⏭️ Skipped",
+        CheckResult::Error => "⚠️ Error",
+    }
+}
+
+/// Percent-encode the characters that end a link destination enclosed in `<` and `>`.
+fn encode(url: &str) -> String {
+    url.chars()
+        .map(|c| match c {
+            '<' | '>' | ' ' | '\t' | '\n' | '\r' => format!("%{:02X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Escape text so that it is not rendered as HTML or spread over several lines.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod test {
+    use super::encode;
+    use crate::report::test::helper_report;
+
+    #[test]
+    fn success_markdown() {
+        let markdown = helper_report().with_title("Board A").markdown();
+
+        assert!(markdown.starts_with(
+            "# Board A\n\n\
+             - **Status:** ❌ Failed\n\
+             - **Kernel config:** `board.config`\n\
+             - **Results:** 2 passed, 3 failed, 0 skipped, 0 errors\n"
+        ));
+        assert!(markdown.contains("\n## usb\n\nSerial &lt;console&gt; over USB\n\n| Result |"));
+        assert!(markdown.contains(
+            "| ❌ Fail | [`CONFIG_USB_ACM`](<https://example.com/acm>) | Error | Enabled (On or Module) | NotSet | `CONFIG_USB_ACM=y` |"
+        ));
+        assert!(markdown.contains(
+            "| ❌ Fail | `CONFIG_LOCALVERSION` | Error | -board\\|a | NotFound | `CONFIG_LOCALVERSION=\"-board\\|a\"` |"
+        ));
+        assert!(markdown.contains("\n## Other Requirements\n"));
+    }
+
+    #[test]
+    fn success_markdown_url() {
+        assert_eq!(
+            encode("https://example.com/a b(c)<d>"),
+            "https://example.com/a%20b(c)%3Cd%3E"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Render check results as reports that can be shared outside of the terminal.
+//!
+//! A [`Report`] groups the results of [`Kcheck::perform_check`](crate::Kcheck::perform_check)
+//! by the fragment they belong to and renders them as Markdown or a self-contained
+//! HTML page, or renders them as a SARIF log for code scanning tools or as TAP for
+//! test harnesses.
+
+mod html;
+mod markdown;
+mod sarif;
+mod tap;
+
+use crate::{kconfig::Severity, kernel::KernelConfigSource, CheckResult, KcheckConfigResult};
+
+/// Title of a report that does not set one.
+const DEFAULT_TITLE: &str = "Kernel Config Report";
+
+/// A report of check results.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Title of the report.
+    title: Option<String>,
+
+    /// The checked kernel config.
+    source: Option<KernelConfigSource>,
+
+    /// The results to report, in the order they were checked.
+    results: Vec<KcheckConfigResult>,
+}
+
+impl Report {
+    /// Create a new [`Report`] from check results.
+    pub fn new(results: Vec<KcheckConfigResult>) -> Self {
+        Self {
+            results,
+            ..Default::default()
+        }
+    }
+
+    /// Set the title of the report.
+    ///
+    /// Defaults to "Kernel Config Report".
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Set the kernel config that was checked.
+    pub fn with_source(mut self, source: KernelConfigSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Render the report as Markdown.
+    pub fn markdown(&self) -> String {
+        markdown::render(self)
+    }
+
+    /// Render the report as a self-contained HTML page.
+    pub fn html(&self) -> String {
+        html::render(self)
+    }
+
+    /// Render the report as a SARIF 2.1.0 log for code scanning tools.
+    ///
+    /// Failed requirements point at the line of the Kcheck config file that
+    /// declares them, and at the line of the kernel config that sets the option.
+    pub fn sarif(&self) -> String {
+        sarif::render(self)
+    }
+
+    /// Render the report in the Test Anything Protocol, for test harnesses that run
+    /// the check on the target.
+    ///
+    /// Failed requirements with a `Warning` or `Info` severity are marked `TODO`,
+    /// so only requirements with an `Error` severity fail the run.
+    pub fn tap(&self) -> String {
+        tap::render(self)
+    }
+
+    /// Title of the report.
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or(DEFAULT_TITLE.to_string())
+    }
+
+    /// Returns `true` if no requirement with an `Error` severity failed or could not
+    /// be checked.
+    fn passed(&self) -> bool {
+        !self.results.iter().any(|r| {
+            r.severity() == Severity::Error
+                && matches!(r.result(), CheckResult::Fail | CheckResult::Error)
+        })
+    }
+
+    /// A summary of the number of results with each outcome.
+    fn summary(&self) -> String {
+        let count =
+            |result: CheckResult| self.results.iter().filter(|r| r.result() == result).count();
+
+        format!(
+            "{} passed, {} failed, {} skipped, {} errors",
+            count(CheckResult::Pass),
+            count(CheckResult::Fail),
+            count(CheckResult::Skipped),
+            count(CheckResult::Error)
+        )
+    }
+
+    /// The results grouped by fragment, in the order each fragment is first checked.
+    ///
+    /// Results that do not belong to a fragment are grouped into a final section.
+    fn sections(&self) -> Vec<Section<'_>> {
+        let mut sections: Vec<Section> = Vec::new();
+        let mut other: Vec<&KcheckConfigResult> = Vec::new();
+
+        for result in &self.results {
+            let Some(name) = result.fragment() else {
+                other.push(result);
+                continue;
+            };
+
+            match sections.iter_mut().find(|s| s.name == name) {
+                Some(section) => section.results.push(result),
+                None => sections.push(Section {
+                    name,
+                    reason: result.reason(),
+                    results: vec![result],
+                }),
+            }
+        }
+
+        if !other.is_empty() {
+            let name = match sections.is_empty() {
+                true => "Requirements",
+                false => "Other Requirements",
+            };
+
+            sections.push(Section {
+                name: name.to_string(),
+                reason: None,
+                results: other,
+            });
+        }
+
+        sections
+    }
+}
+
+/// Returns `true` if a URL can be linked to from a report.
+///
+/// Only `http` and `https` URLs are linked, so that a URL such as `javascript:...`
+/// does not become a clickable script in a published report.
+fn is_web_url(url: &str) -> bool {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    matches!(scheme.as_deref(), Some("http" | "https"))
+}
+
+/// The results of a single fragment.
+struct Section<'a> {
+    name: String,
+    reason: Option<String>,
+    results: Vec<&'a KcheckConfigResult>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::KcheckConfig, kernel::KernelConfig, Kcheck};
+    use std::str::FromStr;
+
+    const TEST_KCHECK_CONFIG: &str = r#"
+        [[kernel]]
+        name = "CONFIG_LOCALVERSION"
+        state = { Text = "-board|a" }
+
+        [[fragment]]
+        name = "usb"
+        reason = "Serial <console> over USB"
+        kernel = [
+            { name = "CONFIG_USB", state = "On" },
+            { name = "CONFIG_USB_ACM", state = "Enabled", url = "https://example.com/acm" },
+        ]
+
+        [[fragment.any_of]]
+        kernel = [
+            { name = "CONFIG_USB_EHCI", state = "On" },
+            { name = "CONFIG_USB_XHCI", state = "On" },
+        ]
+
+        [[fragment]]
+        name = "debug"
+        kernel = [{ name = "CONFIG_DEBUG", state = "Off", severity = "Warning" }]
+    "#;
+
+    const TEST_KERNEL_CONFIG: &str = "\
+CONFIG_USB=y
+# CONFIG_USB_ACM is not set
+CONFIG_USB_XHCI=y
+CONFIG_DEBUG=y
+";
+
+    pub(super) fn helper_report() -> Report {
+        let config = KcheckConfig::from_str(TEST_KCHECK_CONFIG).expect("Expected a config");
+        let kernel = KernelConfig::from_str(TEST_KERNEL_CONFIG).expect("Expected a kernel config");
+        let results = Kcheck::new(config, kernel)
+            .perform_check()
+            .expect("Expected to check the kernel config");
+
+        Report::new(results).with_source(KernelConfigSource::Custom("board.config".to_string()))
+    }
+
+    #[test]
+    fn success_report_sections() {
+        let report = helper_report();
+        let sections = report.sections();
+
+        let names: Vec<&str> = sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["usb", "debug", "Other Requirements"]);
+        assert_eq!(
+            sections[0].reason.as_deref(),
+            Some("Serial <console> over USB")
+        );
+        assert_eq!(sections[0].results.len(), 3);
+        assert_eq!(sections[2].results[0].name(), "CONFIG_LOCALVERSION");
+    }
+
+    #[test]
+    fn success_report_web_url() {
+        assert!(is_web_url("https://example.com/acm"));
+        assert!(is_web_url("HTTP://example.com"));
+        assert!(!is_web_url("javascript:alert(1)"));
+        assert!(!is_web_url("file:///etc/passwd"));
+        assert!(!is_web_url("example.com"));
+    }
+
+    #[test]
+    fn success_report_summary() {
+        let report = helper_report();
+        assert_eq!(report.summary(), "2 passed, 3 failed, 0 skipped, 0 errors");
+        assert!(!report.passed());
+
+        let report = Report::new(Vec::new());
+        assert_eq!(report.title(), DEFAULT_TITLE);
+        assert!(report.passed());
+        assert!(report.sections().is_empty());
+    }
+}
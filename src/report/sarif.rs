@@ -0,0 +1,455 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::Report;
+use crate::{
+    config::Declaration, kconfig::Severity, kernel::KernelConfigSource, CheckResult,
+    KcheckConfigResult,
+};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Version of the SARIF format.
+const SARIF_VERSION: &str = "2.1.0";
+
+/// Location of the JSON Schema of the SARIF format.
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The base id of URIs that are relative to the working directory.
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Render a report as a SARIF log.
+///
+/// Every requirement is a rule, and every failed requirement or requirement that
+/// could not be checked is a result. Results point at the line of the Kcheck config
+/// that declares the requirement, and at the kernel config line of the option.
+pub(super) fn render(report: &Report) -> String {
+    let root = std::env::current_dir().unwrap_or_default();
+
+    // Requirements are identified by their fragment, name, and declaration
+    let mut requirements: Vec<(Option<String>, String, Option<Declaration>)> = Vec::new();
+    let mut declared: Vec<&KcheckConfigResult> = Vec::new();
+    for result in &report.results {
+        let requirement = (result.fragment(), result.name(), result.declaration());
+        if !requirements.contains(&requirement) {
+            requirements.push(requirement);
+            declared.push(result);
+        }
+    }
+
+    let ids = rule_ids(&requirements);
+    let rules: Vec<Value> = declared
+        .into_iter()
+        .zip(&ids)
+        .map(|(result, id)| rule(result, id))
+        .collect();
+
+    let results: Vec<Value> = report
+        .results
+        .iter()
+        .filter(|r| matches!(r.result(), CheckResult::Fail | CheckResult::Error))
+        .map(|r| {
+            let requirement = (r.fragment(), r.name(), r.declaration());
+            let index = requirements
+                .iter()
+                .position(|p| *p == requirement)
+                .unwrap_or_default();
+            result(r, &ids[index], index, report.source.as_ref(), &root)
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "kcheck",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "originalUriBaseIds": {
+                SRCROOT: { "uri": format!("{}/", file_uri(&root).trim_end_matches('/')) },
+            },
+            "results": results,
+        }]
+    });
+
+    serde_json::to_string_pretty(&log).expect("Expected the SARIF log to serialize")
+}
+
+/// The rule id of each requirement.
+///
+/// Ids are the fragment and name of the requirement, followed by a number when
+/// the same option is required more than once in a fragment.
+fn rule_ids(requirements: &[(Option<String>, String, Option<Declaration>)]) -> Vec<String> {
+    let bases: Vec<String> = requirements
+        .iter()
+        .map(|(fragment, name, _)| match fragment {
+            Some(fragment) => format!("{fragment}/{name}"),
+            None => name.clone(),
+        })
+        .collect();
+
+    bases
+        .iter()
+        .enumerate()
+        .map(
+            |(i, base)| match bases.iter().filter(|b| *b == base).count() {
+                1 => base.clone(),
+                _ => {
+                    let n = bases[..i].iter().filter(|b| *b == base).count() + 1;
+                    format!("{base}/{n}")
+                }
+            },
+        )
+        .collect()
+}
+
+/// A rule describing a requirement.
+fn rule(result: &KcheckConfigResult, id: &str) -> Value {
+    let mut rule = json!({
+        "id": id,
+        "shortDescription": { "text": format!("{} should be {}", result.name(), result.desired_state()) },
+        "defaultConfiguration": { "level": level(result.severity()) },
+    });
+
+    if let Some(url) = result.url() {
+        rule["helpUri"] = json!(url);
+    }
+
+    rule
+}
+
+/// A result for a failed requirement.
+fn result(
+    result: &KcheckConfigResult,
+    rule_id: &str,
+    rule_index: usize,
+    source: Option<&KernelConfigSource>,
+    root: &Path,
+) -> Value {
+    let mut text = match result.error() {
+        Some(error) => format!("{} could not be checked: {error}", result.name()),
+        None => format!(
+            "{} is {}, expected {}",
+            result.name(),
+            result.kernel_state(),
+            result.desired_state()
+        ),
+    };
+
+    if let Some(remediation) = result.remediation() {
+        text.push_str(&format!("\nFix: {remediation}"));
+    }
+
+    let declaration = result
+        .declaration()
+        .map(|d| location(&d.file(), d.line(), "Declared here", root));
+
+    let kernel = result.provenance().and_then(|p| {
+        kernel_path(&p.source()).map(|path| location(&path, p.line(), "Kernel config", root))
+    });
+
+    let mut value = json!({
+        "ruleId": rule_id,
+        "ruleIndex": rule_index,
+        "level": level(result.severity()),
+        "message": { "text": text },
+    });
+
+    // Results without a declaration, such as embedded requirements, point at the
+    // kernel config line instead, or at the checked kernel config as a whole
+    match (declaration, kernel) {
+        (Some(declaration), kernel) => {
+            value["locations"] = json!([declaration]);
+            if let Some(mut kernel) = kernel {
+                kernel["id"] = json!(0);
+                value["relatedLocations"] = json!([kernel]);
+            }
+        }
+        (None, Some(kernel)) => value["locations"] = json!([kernel]),
+        (None, None) => {
+            if let Some(path) = source.and_then(kernel_path) {
+                value["locations"] = json!([{
+                    "physicalLocation": { "artifactLocation": artifact_location(&path, root) },
+                    "message": { "text": "Kernel config" },
+                }]);
+            }
+        }
+    }
+
+    value
+}
+
+/// The path of a kernel config read from a file or from a git repository.
+fn kernel_path(source: &KernelConfigSource) -> Option<PathBuf> {
+    match source {
+        KernelConfigSource::File(path) | KernelConfigSource::Git { path, .. } => Some(path.clone()),
+        _ => None,
+    }
+}
+
+/// A location pointing at a line of a file.
+fn location(path: &Path, line: usize, message: &str, root: &Path) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": artifact_location(path, root),
+            "region": { "startLine": line },
+        },
+        "message": { "text": message },
+    })
+}
+
+/// The artifact location of a path.
+///
+/// Relative paths and paths inside `root`, the working directory, are relative
+/// to the `%SRCROOT%` base id. Other paths are absolute `file` URIs.
+fn artifact_location(path: &Path, root: &Path) -> Value {
+    let relative = match path.is_absolute() {
+        true => path.strip_prefix(root).ok(),
+        false => Some(path),
+    };
+
+    match relative {
+        Some(relative) => json!({ "uri": encode(relative), "uriBaseId": SRCROOT }),
+        None => json!({ "uri": file_uri(path) }),
+    }
+}
+
+/// A `file` URI for an absolute path.
+fn file_uri(path: &Path) -> String {
+    let path = encode(path);
+    match path.starts_with('/') {
+        true => format!("file://{path}"),
+        false => format!("file:///{path}"),
+    }
+}
+
+/// Percent-encode a path for use in a URI.
+fn encode(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            b => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+/// The SARIF level of a requirement severity.
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::KcheckConfig, kconfig::KconfigState, kernel::KernelConfigBuilder, Kcheck};
+
+    const TEST_KCHECK_CONFIG: &str = r#"[[kernel]]
+name = "CONFIG_USB"
+state = "On"
+
+[[kernel]]
+name = "CONFIG_USB_ACM"
+state = "Enabled"
+url = "https://example.com/acm"
+
+[[kernel]]
+name = "CONFIG_DEBUG"
+state = "Off"
+severity = "Warning"
+"#;
+
+    const TEST_KERNEL_CONFIG: &str = "CONFIG_USB=y\n# CONFIG_USB_ACM is not set\nCONFIG_DEBUG=y\n";
+
+    #[test]
+    fn success_sarif() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let config_file = dir.path().join("kcheck.toml");
+        let kernel_file = dir.path().join("board.config");
+        std::fs::write(&config_file, TEST_KCHECK_CONFIG).unwrap();
+        std::fs::write(&kernel_file, TEST_KERNEL_CONFIG).unwrap();
+
+        let config = KcheckConfig::try_from_file(&config_file).expect("Expected a config");
+        let kernel = KernelConfigBuilder::default()
+            .user(&kernel_file)
+            .build()
+            .expect("Expected a kernel config");
+        let results = Kcheck::new(config, kernel)
+            .perform_check()
+            .expect("Expected to check the kernel config");
+
+        let sarif = Report::new(results).sarif();
+        let log: Value = serde_json::from_str(&sarif).expect("Expected valid JSON");
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "kcheck");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            run["tool"]["driver"]["rules"][1]["helpUri"],
+            "https://example.com/acm"
+        );
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+
+        let acm = &results[0];
+        assert_eq!(acm["ruleId"], "CONFIG_USB_ACM");
+        assert_eq!(acm["ruleIndex"], 1);
+        assert_eq!(acm["level"], "error");
+        assert_eq!(
+            acm["message"]["text"],
+            "CONFIG_USB_ACM is NotSet, expected Enabled (On or Module)\nFix: CONFIG_USB_ACM=y"
+        );
+
+        let root = std::env::current_dir().unwrap();
+        let declared = &acm["locations"][0]["physicalLocation"];
+        assert_eq!(
+            declared["artifactLocation"],
+            artifact_location(&config_file, &root)
+        );
+        assert_eq!(declared["region"]["startLine"], 5);
+
+        let kernel = &acm["relatedLocations"][0]["physicalLocation"];
+        assert_eq!(
+            kernel["artifactLocation"],
+            artifact_location(&kernel_file, &root)
+        );
+        assert_eq!(kernel["region"]["startLine"], 2);
+
+        assert_eq!(results[1]["ruleId"], "CONFIG_DEBUG");
+        assert_eq!(results[1]["level"], "warning");
+    }
+
+    #[test]
+    fn success_sarif_rule_ids() {
+        let contents = r#"
+[[fragment]]
+name = "usb"
+kernel = [
+    { name = "CONFIG_USB", state = "On" },
+    { name = "CONFIG_USB", state = "Enabled" },
+]
+
+[[fragment]]
+name = "serial"
+kernel = [{ name = "CONFIG_USB", state = "On" }]
+"#;
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let config_file = dir.path().join("kcheck.toml");
+        let kernel_file = dir.path().join("board.config");
+        std::fs::write(&config_file, contents).unwrap();
+        std::fs::write(&kernel_file, "# CONFIG_USB is not set\n").unwrap();
+
+        let config = KcheckConfig::try_from_file(&config_file).expect("Expected a config");
+        let kernel = KernelConfigBuilder::default()
+            .user(&kernel_file)
+            .build()
+            .expect("Expected a kernel config");
+        let results = Kcheck::new(config, kernel)
+            .perform_check()
+            .expect("Expected to check the kernel config");
+
+        let log: Value =
+            serde_json::from_str(&Report::new(results).sarif()).expect("Expected valid JSON");
+        let run = &log["runs"][0];
+
+        let ids: Vec<&str> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            ids,
+            ["usb/CONFIG_USB/1", "usb/CONFIG_USB/2", "serial/CONFIG_USB"]
+        );
+
+        let results: Vec<(&str, u64)> = run["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["ruleId"].as_str().unwrap(),
+                    r["ruleIndex"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            results,
+            [
+                ("usb/CONFIG_USB/1", 0),
+                ("usb/CONFIG_USB/2", 1),
+                ("serial/CONFIG_USB", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn success_sarif_without_declaration() {
+        let config: KcheckConfig = toml::from_str(TEST_KCHECK_CONFIG).expect("Expected a config");
+        let kernel = KernelConfigBuilder::default()
+            .options(&[
+                ("CONFIG_USB", KconfigState::On),
+                ("CONFIG_USB_ACM", KconfigState::NotSet),
+                ("CONFIG_DEBUG", KconfigState::On),
+            ])
+            .build()
+            .expect("Expected a kernel config");
+        let results = Kcheck::new(config, kernel)
+            .perform_check()
+            .expect("Expected to check the kernel config");
+
+        let source = KernelConfigSource::Git {
+            revision: "HEAD".to_string(),
+            path: PathBuf::from("configs/board.config"),
+        };
+        let log: Value = serde_json::from_str(&Report::new(results).with_source(source).sarif())
+            .expect("Expected valid JSON");
+
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        for result in results {
+            assert_eq!(
+                result["locations"][0]["physicalLocation"]["artifactLocation"],
+                json!({ "uri": "configs/board.config", "uriBaseId": "%SRCROOT%" })
+            );
+            assert!(result.get("relatedLocations").is_none());
+        }
+    }
+
+    #[test]
+    fn success_sarif_artifact_location() {
+        let root = Path::new("/src");
+        assert_eq!(
+            artifact_location(Path::new("/src/configs/kcheck.toml"), root),
+            json!({ "uri": "configs/kcheck.toml", "uriBaseId": "%SRCROOT%" })
+        );
+        assert_eq!(
+            artifact_location(Path::new("configs/my kcheck #1.toml"), root),
+            json!({ "uri": "configs/my%20kcheck%20%231.toml", "uriBaseId": "%SRCROOT%" })
+        );
+        assert_eq!(
+            artifact_location(Path::new("/etc/kcheck/ä.toml"), root),
+            json!({ "uri": "file:///etc/kcheck/%C3%A4.toml" })
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::Report;
+use crate::{kconfig::Severity, CheckResult, KcheckConfigResult};
+use std::fmt::Write;
+
+/// Render a report in the Test Anything Protocol.
+///
+/// Every result is a test point. Failed requirements are followed by a YAML
+/// diagnostic block, requirements with a `Warning` or `Info` severity are marked
+/// `TODO` so that they do not fail the run, and skipped requirements are marked `SKIP`.
+/// The output only uses TAP version 13 features, so it declares version 13 for
+/// strict TAP 13 parsers. TAP 14 consumers read it as well.
+pub(super) fn render(report: &Report) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "TAP version 13");
+    let _ = writeln!(out, "# {}", report.title());
+    if let Some(source) = &report.source {
+        let _ = writeln!(out, "# Kernel config: {source}");
+    }
+    let _ = writeln!(out, "1..{}", report.results.len());
+
+    for (i, result) in report.results.iter().enumerate() {
+        let number = i + 1;
+        let description = escape(&result.name());
+
+        match result.result() {
+            CheckResult::Pass => {
+                let _ = writeln!(out, "ok {number} - {description}");
+            }
+            CheckResult::Skipped => {
+                let reason = escape(&result.kernel_state().to_string());
+                let _ = writeln!(out, "ok {number} - {description} # SKIP {reason}");
+            }
+            CheckResult::Fail | CheckResult::Error => {
+                let directive = match result.severity() {
+                    Severity::Error => String::new(),
+                    severity => format!(" # TODO {severity} severity"),
+                };
+
+                let _ = writeln!(out, "not ok {number} - {description}{directive}");
+                out.push_str(&diagnostics(result));
+            }
+        }
+    }
+
+    let _ = writeln!(out, "# {}", report.summary());
+    out
+}
+
+/// A YAML diagnostic block for a failed result.
+fn diagnostics(result: &KcheckConfigResult) -> String {
+    let message = match result.error() {
+        Some(error) => format!("{} could not be checked: {error}", result.name()),
+        None => format!("{} is not in the desired state", result.name()),
+    };
+
+    let fields = [
+        ("message", Some(message)),
+        ("severity", Some(result.severity().to_string())),
+        ("desired", Some(result.desired_state().to_string())),
+        ("actual", Some(result.kernel_state().to_string())),
+        ("fragment", result.fragment()),
+        ("reason", result.reason()),
+        ("remediation", result.remediation()),
+        ("url", result.url()),
+        ("at", result.declaration().map(|d| d.to_string())),
+        ("kernel", result.provenance().map(|p| p.to_string())),
+    ];
+
+    let mut out = String::from("  ---\n");
+    for (key, value) in fields {
+        if let Some(value) = value {
+            let _ = writeln!(out, "  {key}: {}", quote(&value));
+        }
+    }
+    out.push_str("  ...\n");
+
+    out
+}
+
+/// Escape a test point description so that `#` does not start a directive.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace('\n', " ")
+}
+
+/// Quote a YAML scalar.
+///
+/// JSON strings are valid double quoted YAML scalars.
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).expect("Expected a string to serialize")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::KcheckConfig,
+        kernel::{KernelConfig, KernelConfigSource},
+        report::{test::helper_report, Report},
+        target::{KernelVersion, Target},
+        Kcheck,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn success_tap() {
+        let tap = helper_report().tap();
+
+        assert!(tap.starts_with(
+            "TAP version 13\n\
+             # Kernel Config Report\n\
+             # Kernel config: board.config\n\
+             1..5\n"
+        ));
+        assert!(tap.contains("\nok 2 - CONFIG_USB\n"));
+        assert!(tap.contains(
+            "\nnot ok 3 - CONFIG_USB_ACM\n  ---\n\
+             \x20 message: \"CONFIG_USB_ACM is not in the desired state\"\n\
+             \x20 severity: \"Error\"\n\
+             \x20 desired: \"Enabled (On or Module)\"\n\
+             \x20 actual: \"NotSet\"\n\
+             \x20 fragment: \"usb\"\n\
+             \x20 reason: \"Serial <console> over USB\"\n\
+             \x20 remediation: \"CONFIG_USB_ACM=y\"\n\
+             \x20 url: \"https://example.com/acm\"\n\
+             \x20 kernel: \"<string>:2\"\n\
+             \x20 ...\n"
+        ));
+        assert!(tap.contains("\nnot ok 4 - CONFIG_DEBUG # TODO Warning severity\n"));
+        assert!(tap.ends_with("# 2 passed, 3 failed, 0 skipped, 0 errors\n"));
+    }
+
+    #[test]
+    fn success_tap_skip() {
+        let config = KcheckConfig::from_str(
+            r#"
+            [[kernel]]
+            name = "CONFIG_ARM64_#1"
+            state = "On"
+            when = { arch = ["arm64"] }
+            "#,
+        )
+        .expect("Expected a config");
+        let kernel = KernelConfig::from_str("").expect("Expected a kernel config");
+        let results = Kcheck::new(config, kernel)
+            .with_target(Target::new(
+                Some("x86_64"),
+                Some(KernelVersion::new(6, 1, 0)),
+            ))
+            .perform_check()
+            .expect("Expected to check the kernel config");
+
+        let tap = Report::new(results)
+            .with_source(KernelConfigSource::default())
+            .tap();
+        assert!(tap.contains("\nok 1 - CONFIG_ARM64_\\#1 # SKIP Requires arch arm64\n"));
+    }
+}
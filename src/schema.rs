@@ -0,0 +1,408 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A JSON Schema for the `kcheck` config format.
+//!
+//! The schema is derived from [`KcheckConfig`] and can be used by editors to
+//! validate and complete config files. Config files are validated against the
+//! schema before they are deserialized, so that errors point at the location
+//! of the offending value.
+
+use crate::{
+    config::KcheckConfig,
+    diagnostic::Diagnostic,
+    error::{KcheckError, KcheckResult},
+};
+use serde_json::{Map, Value};
+
+/// The JSON Schema of the `kcheck` config format.
+pub fn schema() -> Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(KcheckConfig))
+        .expect("Expected the schema to serialize");
+
+    // Aliases are not a part of the generated schema
+    let option = &mut schema["$defs"]["KconfigOption"]["properties"];
+    option["requires"] = option["if"].clone();
+
+    schema
+}
+
+/// Validate a config against the schema.
+///
+/// Each [`Diagnostic`] holds the path to the value that does not match.
+pub fn validate(config: &Value) -> KcheckResult<()> {
+    let schema = schema();
+    let mut validator = Validator {
+        root: &schema,
+        errors: Vec::new(),
+    };
+    validator.validate(&schema, config, "");
+
+    match validator.errors.is_empty() {
+        true => Ok(()),
+        false => Err(KcheckError::SchemaValidation(validator.errors)),
+    }
+}
+
+/// Keywords that the validator checks.
+const KEYWORDS: &[&str] = &[
+    "$ref",
+    "type",
+    "const",
+    "oneOf",
+    "anyOf",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+];
+
+/// Keywords that only describe a schema and do not affect validation.
+const ANNOTATIONS: &[&str] = &["$schema", "$defs", "title", "description", "default"];
+
+/// Validates values against the subset of JSON Schema used by the generated schema.
+///
+/// Schemas that use any other keyword fail validation, so that a change to the
+/// generated schema cannot silently accept invalid configs.
+struct Validator<'a> {
+    root: &'a Value,
+    errors: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, path: &str, message: String) {
+        let diagnostic = Diagnostic::new(&message);
+        self.errors.push(match path.is_empty() {
+            true => diagnostic,
+            false => diagnostic.with_path(path),
+        });
+    }
+
+    /// Returns `true` if the value is valid without recording any errors.
+    fn is_valid(&self, schema: &Value, value: &Value) -> bool {
+        let mut validator = Validator {
+            root: self.root,
+            errors: Vec::new(),
+        };
+        validator.validate(schema, value, "");
+        validator.errors.is_empty()
+    }
+
+    fn validate(&mut self, schema: &Value, value: &Value, path: &str) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.error(path, "no value is allowed".to_string()),
+            Value::Object(object) => {
+                let unsupported = object.keys().find(|k| {
+                    !KEYWORDS.contains(&k.as_str()) && !ANNOTATIONS.contains(&k.as_str())
+                });
+                if let Some(keyword) = unsupported {
+                    return self.error(path, format!("unsupported schema keyword `{keyword}`"));
+                }
+                schema
+            }
+            _ => return self.error(path, "invalid schema".to_string()),
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/$defs/");
+            let root = self.root;
+            self.validate(&root["$defs"][name], value, path);
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let types: Vec<&str> = match expected {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                t => t.as_str().into_iter().collect(),
+            };
+
+            if !types.iter().any(|t| type_matches(t, value)) {
+                let found = type_name(value);
+                self.error(
+                    path,
+                    format!("expected {}, found {found}", types.join(" or ")),
+                );
+                return;
+            }
+        }
+
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                self.error(path, format!("expected `{}`", display(expected)));
+            }
+        }
+
+        if let Some(variants) = schema.get("oneOf").or_else(|| schema.get("anyOf")) {
+            self.validate_variants(variants.as_array().into_iter().flatten(), value, path);
+        }
+
+        if let Value::Object(object) = value {
+            self.validate_object(schema, object, path);
+        }
+
+        if let (Some(items), Value::Array(array)) = (schema.get("items"), value) {
+            for (i, item) in array.iter().enumerate() {
+                self.validate(items, item, &format!("{path}[{i}]"));
+            }
+        }
+    }
+
+    fn validate_variants<'v>(
+        &mut self,
+        variants: impl Iterator<Item = &'v Value>,
+        value: &Value,
+        path: &str,
+    ) {
+        let root = self.root;
+        let variants: Vec<&Value> = variants.map(|v| resolve(root, v)).collect();
+        if variants.iter().any(|v| self.is_valid(v, value)) {
+            return;
+        }
+
+        // Report the errors of the only variant with a matching type, such as
+        // `{ Text = 1 }` for a state, since it is most likely the intended one.
+        let candidates: Vec<&&Value> = variants
+            .iter()
+            .filter(|v| v.get("const").is_none())
+            .filter(|v| match v.get("type").and_then(Value::as_str) {
+                Some(t) => type_matches(t, value),
+                None => true,
+            })
+            .filter(|v| {
+                let required = v.get("required").and_then(Value::as_array);
+                required.into_iter().flatten().all(|key| {
+                    let key = key.as_str().unwrap_or_default();
+                    value.get(key).is_some()
+                })
+            })
+            .collect();
+
+        if let [variant] = candidates[..] {
+            self.validate(variant, value, path);
+            return;
+        }
+
+        // Variants are either constants or objects with a single key, such as `{ Text = "a" }`
+        let names: Vec<String> = variants
+            .iter()
+            .filter_map(|v| {
+                let key = || v.get("required")?.as_array()?.first().cloned();
+                v.get("const").cloned().or_else(key)
+            })
+            .map(|name| format!("`{}`", display(&name)))
+            .collect();
+
+        let variant = match value {
+            Value::Object(object) if object.len() == 1 => object.keys().next().cloned(),
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        };
+
+        match (variant, names.is_empty()) {
+            (Some(variant), false) => self.error(
+                path,
+                format!(
+                    "unknown variant `{variant}`, expected one of {}",
+                    names.join(", ")
+                ),
+            ),
+            _ => self.error(path, "does not match any of the allowed values".to_string()),
+        }
+    }
+
+    fn validate_object(&mut self, schema: &Value, object: &Map<String, Value>, path: &str) {
+        let empty = Map::new();
+        let properties = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+
+        for required in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(required) {
+                self.error(path, format!("missing field `{required}`"));
+            }
+        }
+
+        for (key, item) in object {
+            let item_path = match path.is_empty() {
+                true => key.clone(),
+                false => format!("{path}.{key}"),
+            };
+
+            match (properties.get(key), schema.get("additionalProperties")) {
+                (Some(property), _) => self.validate(property, item, &item_path),
+                (None, Some(Value::Bool(false))) => {
+                    let expected: Vec<String> =
+                        properties.keys().map(|k| format!("`{k}`")).collect();
+                    self.error(
+                        &item_path,
+                        format!(
+                            "unknown field `{key}`, expected one of {}",
+                            expected.join(", ")
+                        ),
+                    );
+                }
+                (None, Some(additional)) => self.validate(additional, item, &item_path),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+/// Follow a `$ref` to the schema definition it points to.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => &root["$defs"][reference.trim_start_matches("#/$defs/")],
+        None => schema,
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => value.is_i64() || value.is_u64(),
+        t => type_name(value) == t,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Display strings without quotes.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn success_schema_is_published() {
+        let published: Value = serde_json::from_str(include_str!("../kcheck.schema.json"))
+            .expect("Expected the published schema to be valid JSON");
+        assert_eq!(
+            published,
+            schema(),
+            "Update the schema with `kcheck-cli schema > kcheck.schema.json`"
+        );
+    }
+
+    #[test]
+    fn success_validate() {
+        let config = json!({
+            "$schema": "./kcheck.schema.json",
+            "name": "test",
+            "kernel": [
+                { "name": "CONFIG_A", "state": "On", "severity": "Warning" },
+                { "name": "CONFIG_B", "state": { "Text": "b" }, "requires": "CONFIG_A" },
+            ],
+            "fragment": [{
+                "name": "lsm",
+                "profiles": ["production"],
+                "when": { "arch": ["arm64"], "kernel": ">=6.1" },
+                "any_of": [{ "kernel": [{ "name": "CONFIG_C", "state": "Enabled" }] }],
+            }],
+        });
+
+        assert_eq!(validate(&config), Ok(()));
+    }
+
+    #[test]
+    fn success_schema_keywords_supported() {
+        fn keywords(schema: &Value, found: &mut Vec<String>) {
+            if let Value::Object(object) = schema {
+                for (key, value) in object {
+                    found.push(key.clone());
+                    match key.as_str() {
+                        "properties" | "$defs" => {
+                            value.as_object().into_iter().flatten().for_each(|(_, v)| {
+                                keywords(v, found);
+                            })
+                        }
+                        _ => keywords(value, found),
+                    }
+                }
+            } else if let Value::Array(array) = schema {
+                array.iter().for_each(|v| keywords(v, found));
+            }
+        }
+
+        let mut found = Vec::new();
+        keywords(&schema(), &mut found);
+        for keyword in found {
+            assert!(
+                KEYWORDS.contains(&keyword.as_str()) || ANNOTATIONS.contains(&keyword.as_str()),
+                "The validator does not support the `{keyword}` keyword"
+            );
+        }
+    }
+
+    #[test]
+    fn fail_validate_unsupported_keyword() {
+        let schema = json!({ "type": "integer", "minimum": 1 });
+        let mut validator = Validator {
+            root: &schema,
+            errors: Vec::new(),
+        };
+        validator.validate(&schema, &json!(0), "count");
+
+        assert_eq!(
+            validator.errors,
+            [Diagnostic::new("unsupported schema keyword `minimum`").with_path("count")]
+        );
+    }
+
+    #[test]
+    fn fail_validate() {
+        let config = json!({
+            "kernel": [
+                { "name": "CONFIG_A", "state": "Yes" },
+                { "name": "CONFIG_B", "state": { "Text": 1 } },
+                { "name": "CONFIG_C", "stat": "On" },
+            ],
+            "fragment": [{ "name": "test", "any_of": [{ "kernel": {} }] }],
+        });
+
+        let errors: Vec<String> = match validate(&config) {
+            Err(KcheckError::SchemaValidation(errors)) => {
+                errors.iter().map(|e| e.to_string()).collect()
+            }
+            result => panic!("Expected schema errors, found {result:?}"),
+        };
+
+        assert_eq!(
+            errors,
+            [
+                "fragment[0].any_of[0].kernel: expected array, found object",
+                "kernel[0].state: unknown variant `Yes`, expected one of `NotFound`, `NotSet`, \
+                 `Off`, `Disabled`, `On`, `Module`, `Enabled`, `Text`, `Regex`",
+                "kernel[1].state.Text: expected string, found number",
+                "kernel[2]: missing field `state`",
+                "kernel[2].stat: unknown field `stat`, expected one of `fix`, `if`, `name`, \
+                 `requires`, `severity`, `state`, `url`, `when`",
+            ]
+        );
+    }
+}
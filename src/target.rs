@@ -0,0 +1,600 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Conditions that limit requirements to some architectures, kernel versions,
+//! or kernel configs.
+
+use crate::{
+    error::{KcheckError, KcheckResult},
+    kconfig::KconfigState,
+    kernel::KernelConfig,
+    tree::{Expr, SymbolLookup, Tristate, CONFIG_PREFIX},
+};
+use nix::sys::utsname::uname;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, str::FromStr};
+
+/// Kernel config options that identify the architecture a config is built for.
+const ARCH_OPTIONS: [(&str, &str); 9] = [
+    ("CONFIG_X86_64", "x86_64"),
+    ("CONFIG_X86_32", "x86"),
+    ("CONFIG_ARM64", "arm64"),
+    ("CONFIG_ARM", "arm"),
+    ("CONFIG_RISCV", "riscv"),
+    ("CONFIG_PPC64", "powerpc64"),
+    ("CONFIG_PPC32", "powerpc"),
+    ("CONFIG_S390", "s390"),
+    ("CONFIG_LOONGARCH", "loongarch"),
+];
+
+/// Map the different names of an architecture to the name used by `kcheck`.
+///
+/// For example, `uname -m` reports `aarch64` for the `arm64` kernel architecture.
+pub fn normalize_arch(arch: &str) -> String {
+    let arch = arch.trim().to_lowercase();
+    let normalized = match arch.as_str() {
+        "amd64" | "x86-64" => "x86_64",
+        "i386" | "i486" | "i586" | "i686" | "x86_32" => "x86",
+        "aarch64" => "arm64",
+        a if a.starts_with("armv") => "arm",
+        "riscv64" | "riscv32" => "riscv",
+        "ppc64" | "ppc64le" => "powerpc64",
+        "ppc" => "powerpc",
+        "s390x" => "s390",
+        "loongarch64" => "loongarch",
+        a => a,
+    };
+
+    normalized.to_string()
+}
+
+/// A kernel release version, such as `6.6.12`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct KernelVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl KernelVersion {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for KernelVersion {
+    type Err = KcheckError;
+
+    /// Parse a version, ignoring suffixes such as `-rc1` or `-generic`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || KcheckError::InvalidCondition(format!("Invalid kernel version `{s}`"));
+
+        let numeric = s
+            .trim()
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .next()
+            .unwrap_or_default();
+
+        let mut parts = numeric.split('.').filter(|p| !p.is_empty());
+        let mut next = |required: bool| match parts.next() {
+            Some(p) => p.parse::<u32>().map_err(|_| invalid()),
+            None if required => Err(invalid()),
+            None => Ok(0),
+        };
+
+        Ok(Self {
+            major: next(true)?,
+            minor: next(false)?,
+            patch: next(false)?,
+        })
+    }
+}
+
+impl std::fmt::Display for KernelVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A comparison in a [`VersionReq`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum VersionOp {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// All components that were written must match, so `6.1` matches `6.1.80`.
+    Equal(usize),
+}
+
+/// A kernel version range, such as `>=5.10, <6.2`.
+///
+/// All comparisons must be met. A version without an operator matches any
+/// release with the same leading components, so `6.1` matches `6.1.80`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct VersionReq {
+    text: String,
+    comparisons: Vec<(VersionOp, KernelVersion)>,
+}
+
+impl VersionReq {
+    /// Returns `true` if the version is in the range.
+    pub fn matches(&self, version: &KernelVersion) -> bool {
+        self.comparisons.iter().all(|(op, v)| match op {
+            VersionOp::Less => version < v,
+            VersionOp::LessEqual => version <= v,
+            VersionOp::Greater => version > v,
+            VersionOp::GreaterEqual => version >= v,
+            VersionOp::Equal(parts) => {
+                let expected = [v.major, v.minor, v.patch];
+                let actual = [version.major, version.minor, version.patch];
+                expected[..*parts] == actual[..*parts]
+            }
+        })
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparisons = Vec::new();
+
+        for part in s.split(',').map(str::trim) {
+            let (op, version) = if let Some(v) = part.strip_prefix(">=") {
+                (VersionOp::GreaterEqual, v)
+            } else if let Some(v) = part.strip_prefix("<=") {
+                (VersionOp::LessEqual, v)
+            } else if let Some(v) = part.strip_prefix('>') {
+                (VersionOp::Greater, v)
+            } else if let Some(v) = part.strip_prefix('<') {
+                (VersionOp::Less, v)
+            } else {
+                let v = part.trim_start_matches('=').trim();
+                (VersionOp::Equal(v.split('.').count().min(3)), v)
+            };
+
+            // Unlike release strings, versions in a range cannot have a suffix
+            let version = version.trim();
+            if version.is_empty() || !version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                return Err(KcheckError::InvalidCondition(format!(
+                    "Invalid kernel version range `{s}`"
+                )));
+            }
+
+            comparisons.push((op, KernelVersion::from_str(version)?));
+        }
+
+        Ok(Self {
+            text: s.trim().to_string(),
+            comparisons,
+        })
+    }
+}
+
+impl TryFrom<String> for VersionReq {
+    type Error = KcheckError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_str(&s)
+    }
+}
+
+impl From<VersionReq> for String {
+    fn from(req: VersionReq) -> Self {
+        req.text
+    }
+}
+
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// The architecture and kernel version a kernel config is built for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Target {
+    arch: Option<String>,
+    version: Option<KernelVersion>,
+    /// The architecture was detected with `uname -m`.
+    arch_from_uname: bool,
+    /// The version was detected with `uname -r`.
+    version_from_uname: bool,
+}
+
+impl Target {
+    pub fn new(arch: Option<&str>, version: Option<KernelVersion>) -> Self {
+        Self {
+            arch: arch.map(normalize_arch),
+            version,
+            ..Default::default()
+        }
+    }
+
+    /// Detect the target of a kernel config.
+    ///
+    /// The architecture is detected from options such as `CONFIG_X86_64` or
+    /// `CONFIG_ARM64` and the version from the config header. When the config
+    /// does not contain them, `uname -m` and `uname -r` are used.
+    pub fn from_kernel(kernel: &KernelConfig) -> Self {
+        let arch = ARCH_OPTIONS
+            .iter()
+            .find(|(option, _)| kernel.check_option(option, KconfigState::On))
+            .map(|(_, arch)| arch.to_string());
+        let arch_from_uname = arch.is_none();
+        let arch = arch.or_else(|| {
+            uname()
+                .ok()
+                .map(|u| normalize_arch(&u.machine().to_string_lossy()))
+        });
+
+        let version = kernel.header_version();
+        let version_from_uname = version.is_none();
+        let version = version.or_else(|| {
+            uname()
+                .ok()
+                .and_then(|u| KernelVersion::from_str(&u.release().to_string_lossy()).ok())
+        });
+
+        Self {
+            arch_from_uname: arch_from_uname && arch.is_some(),
+            version_from_uname: version_from_uname && version.is_some(),
+            arch,
+            version,
+        }
+    }
+
+    /// Replace the architecture, such as when the kernel config does not set it.
+    pub fn with_arch(mut self, arch: &str) -> Self {
+        self.arch = Some(normalize_arch(arch));
+        self.arch_from_uname = false;
+        self
+    }
+
+    /// Replace the kernel version, such as when the kernel config has no header.
+    pub fn with_version(mut self, version: KernelVersion) -> Self {
+        self.version = Some(version);
+        self.version_from_uname = false;
+        self
+    }
+
+    /// Returns `true` if the architecture or version was detected from the
+    /// running system with `uname`.
+    pub fn from_uname(&self) -> bool {
+        self.arch_from_uname || self.version_from_uname
+    }
+
+    /// The normalized architecture name.
+    pub fn arch(&self) -> Option<String> {
+        self.arch.clone()
+    }
+
+    /// The kernel version.
+    pub fn version(&self) -> Option<KernelVersion> {
+        self.version
+    }
+}
+
+/// A condition that limits a requirement to some targets.
+///
+/// Requirements whose conditions are not met are reported as
+/// [`CheckResult::Skipped`](crate::CheckResult::Skipped).
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[schemars(deny_unknown_fields)]
+pub struct When {
+    /// Architectures the requirement applies to, such as `x86_64` or `arm64`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    arch: Vec<String>,
+    /// Kernel versions the requirement applies to, such as `>=5.10, <6.2`.
+    kernel: Option<VersionReq>,
+}
+
+impl When {
+    /// Limit the requirement to one of the architectures.
+    pub fn arch(mut self, arch: &[&str]) -> Self {
+        self.arch = arch.iter().map(|a| a.to_string()).collect();
+        self
+    }
+
+    /// Limit the requirement to a range of kernel versions.
+    pub fn kernel(mut self, range: &str) -> KcheckResult<Self> {
+        self.kernel = Some(VersionReq::from_str(range)?);
+        Ok(self)
+    }
+
+    /// Returns `true` if the target meets the condition.
+    ///
+    /// A condition on an unknown architecture or version is not met.
+    pub fn matches(&self, target: &Target) -> bool {
+        let arch = self.arch.is_empty()
+            || target
+                .arch
+                .as_ref()
+                .is_some_and(|t| self.arch.iter().any(|a| normalize_arch(a) == *t));
+
+        let kernel = match (&self.kernel, &target.version) {
+            (None, _) => true,
+            (Some(req), Some(version)) => req.matches(version),
+            (Some(_), None) => false,
+        };
+
+        arch && kernel
+    }
+}
+
+impl std::fmt::Display for When {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if !self.arch.is_empty() {
+            parts.push(format!("arch {}", self.arch.join(" or ")));
+        }
+        if let Some(kernel) = &self.kernel {
+            parts.push(format!("kernel {kernel}"));
+        }
+
+        write!(f, "{}", parts.join(" and "))
+    }
+}
+
+/// An expression on the options of a kernel config, such as
+/// `CONFIG_BPF_SYSCALL && !CONFIG_PREEMPT_RT`.
+///
+/// Uses the Kconfig expression syntax with `&&`, `||`, `!`, parentheses, and
+/// comparisons such as `CONFIG_HZ >= 250`. The `CONFIG_` prefix is optional.
+/// An option that is set to `y` or `m` is true, and `!` negates it, so that
+/// `!CONFIG_NET` is false when `CONFIG_NET=m`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    text: String,
+    expr: Expr,
+}
+
+impl Condition {
+    /// Returns `true` if the kernel config meets the condition.
+    pub fn matches(&self, kernel: &KernelConfig) -> bool {
+        Self::eval(&self.expr, &mut KernelLookup::new(kernel))
+    }
+
+    /// Evaluate an expression as a boolean.
+    ///
+    /// Kconfig evaluates `!m` to `m`, so operands are turned into booleans before
+    /// they are negated or combined.
+    fn eval(expr: &Expr, lookup: &mut KernelLookup) -> bool {
+        match expr {
+            Expr::Not(e) => !Self::eval(e, lookup),
+            Expr::And(a, b) => Self::eval(a, lookup) && Self::eval(b, lookup),
+            Expr::Or(a, b) => Self::eval(a, lookup) || Self::eval(b, lookup),
+            e => e.eval(lookup) != Tristate::No,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = KcheckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expr = Expr::parse_condition(s)
+            .map_err(|e| KcheckError::InvalidCondition(format!("`{s}`: {e}")))?;
+
+        Ok(Self {
+            text: s.trim().to_string(),
+            expr,
+        })
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = KcheckError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_str(&s)
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.text
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Evaluates [`Condition`] symbols using the values in a kernel config.
+struct KernelLookup {
+    /// Raw values of the options in the kernel config.
+    values: HashMap<String, String>,
+}
+
+impl KernelLookup {
+    /// Create a lookup of the options in a kernel config.
+    fn new(kernel: &KernelConfig) -> Self {
+        let mut values = HashMap::new();
+        for (name, value) in kernel.entries() {
+            values.entry(name).or_insert(value);
+        }
+
+        Self { values }
+    }
+
+    /// The raw value of an option, with or without the `CONFIG_` prefix.
+    fn value(&self, name: &str) -> Option<String> {
+        self.values
+            .get(name)
+            .or_else(|| self.values.get(&format!("{CONFIG_PREFIX}{name}")))
+            .cloned()
+    }
+}
+
+impl SymbolLookup for KernelLookup {
+    fn tristate(&mut self, name: &str) -> Tristate {
+        self.value(name)
+            .and_then(|v| Tristate::from_value(&v))
+            .unwrap_or_default()
+    }
+
+    fn string(&mut self, name: &str) -> String {
+        self.value(name).unwrap_or_default()
+    }
+
+    fn modules(&mut self) -> Tristate {
+        self.tristate("MODULES")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kernel::{DuplicatePolicy, KernelConfigBuilder};
+
+    #[test]
+    fn success_kernel_version_parse() {
+        let test_array = [
+            ("6.6.12", KernelVersion::new(6, 6, 12)),
+            ("5.15.0-91-generic", KernelVersion::new(5, 15, 0)),
+            ("6.8-rc1", KernelVersion::new(6, 8, 0)),
+            ("6", KernelVersion::new(6, 0, 0)),
+        ];
+
+        for (text, expected) in test_array {
+            assert_eq!(KernelVersion::from_str(text).unwrap(), expected, "{text}");
+        }
+
+        assert!(KernelVersion::from_str("linux").is_err());
+    }
+
+    #[test]
+    fn success_version_req_matches() {
+        let req = VersionReq::from_str(">=5.10, <6.2").unwrap();
+        assert!(req.matches(&KernelVersion::new(5, 10, 0)));
+        assert!(req.matches(&KernelVersion::new(6, 1, 80)));
+        assert!(!req.matches(&KernelVersion::new(6, 2, 0)));
+        assert!(!req.matches(&KernelVersion::new(5, 4, 200)));
+
+        let req = VersionReq::from_str("6.1").unwrap();
+        assert!(req.matches(&KernelVersion::new(6, 1, 80)));
+        assert!(!req.matches(&KernelVersion::new(6, 10, 0)));
+
+        assert!(VersionReq::from_str(">=five").is_err());
+    }
+
+    #[test]
+    fn success_when_matches() {
+        let arm64_6_6 = Target::new(Some("aarch64"), Some(KernelVersion::new(6, 6, 0)));
+        let x86_5_10 = Target::new(Some("x86_64"), Some(KernelVersion::new(5, 10, 0)));
+
+        let when = When::default().arch(&["arm64"]);
+        assert!(when.matches(&arm64_6_6));
+        assert!(!when.matches(&x86_5_10));
+
+        let when = When::default().kernel(">=6.0").unwrap();
+        assert!(when.matches(&arm64_6_6));
+        assert!(!when.matches(&x86_5_10));
+        assert!(!when.matches(&Target::default()));
+
+        let when = When::default()
+            .arch(&["x86_64", "arm64"])
+            .kernel("<6.0")
+            .unwrap();
+        assert!(!when.matches(&arm64_6_6));
+        assert!(when.matches(&x86_5_10));
+        assert_eq!(when.to_string(), "arch x86_64 or arm64 and kernel <6.0");
+
+        assert!(When::default().matches(&Target::default()));
+    }
+
+    #[test]
+    fn success_target_from_kernel() {
+        let kernel = KernelConfig::from_str(
+            "#\n# Linux/arm64 6.6.0 Kernel Configuration\n#\nCONFIG_ARM64=y\n# CONFIG_X86_64 is not set",
+        )
+        .unwrap();
+
+        let target = Target::from_kernel(&kernel);
+        assert_eq!(target.arch(), Some("arm64".to_string()));
+        assert_eq!(target.version(), Some(KernelVersion::new(6, 6, 0)));
+        assert!(!target.from_uname());
+    }
+
+    #[test]
+    fn success_target_with_overrides() {
+        let kernel = KernelConfig::from_str("CONFIG_USB=y").unwrap();
+
+        // Without an architecture option or header, the running system is used
+        let target = Target::from_kernel(&kernel);
+        assert!(target.from_uname());
+
+        let target = target
+            .with_arch("aarch64")
+            .with_version(KernelVersion::new(5, 10, 0));
+        assert_eq!(target.arch(), Some("arm64".to_string()));
+        assert_eq!(target.version(), Some(KernelVersion::new(5, 10, 0)));
+        assert!(!target.from_uname());
+    }
+
+    #[test]
+    fn success_condition_matches() {
+        let kernel = KernelConfig::from_str(
+            "CONFIG_BPF_SYSCALL=y\nCONFIG_NET=m\n# CONFIG_PREEMPT_RT is not set\nCONFIG_HZ=250",
+        )
+        .unwrap();
+
+        let test_array = [
+            ("CONFIG_BPF_SYSCALL", true),
+            ("CONFIG_NET", true),
+            ("BPF_SYSCALL && !PREEMPT_RT", true),
+            ("CONFIG_PREEMPT_RT || CONFIG_MISSING", false),
+            ("!(CONFIG_BPF_SYSCALL && CONFIG_NET)", false),
+            ("!CONFIG_NET", false),
+            ("!CONFIG_NET || CONFIG_PREEMPT_RT", false),
+            ("!CONFIG_PREEMPT_RT && !CONFIG_MISSING", true),
+            ("CONFIG_NET = m", true),
+            ("CONFIG_HZ >= 250", true),
+            ("CONFIG_HZ > 250", false),
+        ];
+
+        for (text, expected) in test_array {
+            let condition = Condition::from_str(text).unwrap();
+            assert_eq!(condition.matches(&kernel), expected, "{text}");
+        }
+
+        assert!(Condition::from_str("CONFIG_A &&").is_err());
+    }
+
+    #[test]
+    fn success_condition_duplicate_policy() {
+        let kernel = KernelConfigBuilder::default()
+            .options(&[
+                ("CONFIG_NET", KconfigState::On),
+                ("CONFIG_NET", KconfigState::NotSet),
+            ])
+            .duplicates(DuplicatePolicy::WarnLast)
+            .build()
+            .unwrap();
+
+        let condition = Condition::from_str("CONFIG_NET").unwrap();
+        assert_eq!(kernel.option("CONFIG_NET").unwrap(), KconfigState::NotSet);
+        assert!(!condition.matches(&kernel));
+    }
+
+    #[test]
+    fn fail_when_parse() {
+        let result: Result<When, _> = toml::from_str("kernel = \">=6.x\"");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,447 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::error::{KcheckError, KcheckResult};
+
+/// A Kconfig tristate value.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Tristate {
+    /// `n`
+    #[default]
+    No,
+    /// `m`
+    Module,
+    /// `y`
+    Yes,
+}
+
+impl Tristate {
+    /// Tristate `&&`, the minimum of both values.
+    pub fn and(self, other: Tristate) -> Tristate {
+        self.min(other)
+    }
+
+    /// Tristate `||`, the maximum of both values.
+    pub fn or(self, other: Tristate) -> Tristate {
+        self.max(other)
+    }
+
+    /// Parse a `y`, `m`, or `n` value.
+    pub fn from_value(value: &str) -> Option<Tristate> {
+        match value {
+            "y" => Some(Tristate::Yes),
+            "m" => Some(Tristate::Module),
+            "n" => Some(Tristate::No),
+            _ => None,
+        }
+    }
+}
+
+impl std::ops::Not for Tristate {
+    type Output = Tristate;
+
+    /// Tristate `!`, which leaves `m` unchanged.
+    fn not(self) -> Tristate {
+        match self {
+            Tristate::No => Tristate::Yes,
+            Tristate::Module => Tristate::Module,
+            Tristate::Yes => Tristate::No,
+        }
+    }
+}
+
+impl From<bool> for Tristate {
+    fn from(b: bool) -> Self {
+        match b {
+            true => Tristate::Yes,
+            false => Tristate::No,
+        }
+    }
+}
+
+impl std::fmt::Display for Tristate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Tristate::No => "n",
+            Tristate::Module => "m",
+            Tristate::Yes => "y",
+        };
+
+        write!(f, "{text}")
+    }
+}
+
+/// Comparison operators allowed in Kconfig expressions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CompareOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/// A Kconfig expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Expr {
+    /// A constant such as `y`, `0x10` or `"text"`.
+    Const(String),
+    /// A reference to a symbol.
+    Symbol(String),
+    /// The value of the symbol that enables loadable module support.
+    Modules,
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+}
+
+impl Default for Expr {
+    fn default() -> Self {
+        Expr::yes()
+    }
+}
+
+/// Provides symbol values while evaluating an [`Expr`].
+pub(crate) trait SymbolLookup {
+    /// The tristate value of a symbol.
+    fn tristate(&mut self, name: &str) -> Tristate;
+
+    /// The string value of a symbol.
+    fn string(&mut self, name: &str) -> String;
+
+    /// The value of the symbol that enables loadable module support.
+    fn modules(&mut self) -> Tristate;
+}
+
+impl Expr {
+    /// An expression that is always `y`.
+    pub(crate) fn yes() -> Expr {
+        Expr::Const("y".to_string())
+    }
+
+    /// Combine two expressions with `&&`, simplifying constant `y` operands.
+    pub(crate) fn and(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(c), e) | (e, Expr::Const(c)) if c == "y" => e,
+            (a, b) => Expr::And(Box::new(a), Box::new(b)),
+        }
+    }
+
+    /// Combine two expressions with `||`.
+    pub(crate) fn or(self, other: Expr) -> Expr {
+        Expr::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Parse an expression.
+    ///
+    /// An empty expression, which can be the result of expanding a macro that
+    /// cannot be evaluated, is equivalent to `n`.
+    pub(crate) fn parse(text: &str) -> KcheckResult<Expr> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Ok(Expr::Const("n".to_string()));
+        }
+
+        let mut parser = ExprParser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(t) => Err(KcheckError::KconfigParseError(format!(
+                "Unexpected `{t}` in expression `{text}`"
+            ))),
+        }
+    }
+
+    /// Parse an expression used as a dependency or condition.
+    ///
+    /// A constant `m` in a dependency is only satisfied if modules are enabled.
+    pub(crate) fn parse_condition(text: &str) -> KcheckResult<Expr> {
+        Ok(Self::parse(text)?.rewrite_m())
+    }
+
+    /// Replace constant `m` with `m && MODULES`.
+    fn rewrite_m(self) -> Expr {
+        match self {
+            Expr::Const(c) if c == "m" => {
+                Expr::And(Box::new(Expr::Const(c)), Box::new(Expr::Modules))
+            }
+            Expr::Not(e) => Expr::Not(Box::new(e.rewrite_m())),
+            Expr::And(a, b) => Expr::And(Box::new(a.rewrite_m()), Box::new(b.rewrite_m())),
+            Expr::Or(a, b) => Expr::Or(Box::new(a.rewrite_m()), Box::new(b.rewrite_m())),
+            e => e,
+        }
+    }
+
+    /// Evaluate the expression to a tristate value.
+    pub(crate) fn eval<L: SymbolLookup>(&self, lookup: &mut L) -> Tristate {
+        match self {
+            Expr::Const(c) => Tristate::from_value(c).unwrap_or_default(),
+            Expr::Symbol(s) => lookup.tristate(s),
+            Expr::Modules => lookup.modules(),
+            Expr::Not(e) => !e.eval(lookup),
+            Expr::And(a, b) => a.eval(lookup).and(b.eval(lookup)),
+            Expr::Or(a, b) => a.eval(lookup).or(b.eval(lookup)),
+            Expr::Compare(op, a, b) => {
+                let left = a.string(lookup);
+                let right = b.string(lookup);
+
+                let ordering = match (parse_number(&left), parse_number(&right)) {
+                    (Some(l), Some(r)) => l.cmp(&r),
+                    _ => left.cmp(&right),
+                };
+
+                let result = match op {
+                    CompareOp::Equal => ordering.is_eq(),
+                    CompareOp::NotEqual => ordering.is_ne(),
+                    CompareOp::Less => ordering.is_lt(),
+                    CompareOp::LessEqual => ordering.is_le(),
+                    CompareOp::Greater => ordering.is_gt(),
+                    CompareOp::GreaterEqual => ordering.is_ge(),
+                };
+
+                match result {
+                    true => Tristate::Yes,
+                    false => Tristate::No,
+                }
+            }
+        }
+    }
+
+    /// Evaluate the expression to a string value.
+    pub(crate) fn string<L: SymbolLookup>(&self, lookup: &mut L) -> String {
+        match self {
+            Expr::Const(c) => c.clone(),
+            Expr::Symbol(s) => lookup.string(s),
+            e => e.eval(lookup).to_string(),
+        }
+    }
+}
+
+/// Parse a decimal or hexadecimal number.
+pub(crate) fn parse_number(value: &str) -> Option<i64> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => value.parse::<i64>().ok(),
+    }
+}
+
+/// Returns `true` if the word is a constant rather than a symbol reference.
+fn is_constant(word: &str) -> bool {
+    Tristate::from_value(word).is_some() || parse_number(word).is_some()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "{w}"),
+            Token::Str(s) => write!(f, "\"{s}\""),
+            Token::Op(o) => write!(f, "{o}"),
+        }
+    }
+}
+
+const OPERATORS: [&str; 11] = ["&&", "||", "!=", "<=", ">=", "!", "(", ")", "=", "<", ">"];
+
+fn tokenize(text: &str) -> KcheckResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if rest.starts_with('"') || rest.starts_with('\'') {
+            let (s, remaining) = super::parser::take_string(rest)?;
+            tokens.push(Token::Str(s));
+            rest = remaining;
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'))
+                .unwrap_or(rest.len());
+
+            if end == 0 {
+                return Err(KcheckError::KconfigParseError(format!(
+                    "Unexpected character in expression `{text}`"
+                )));
+            }
+
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek_op(&self, op: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Op(o)) if *o == op)
+    }
+
+    fn parse_or(&mut self) -> KcheckResult<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek_op("||") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> KcheckResult<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.peek_op("&&") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> KcheckResult<Expr> {
+        if self.peek_op("!") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+
+        self.parse_compare()
+    }
+
+    fn parse_compare(&mut self) -> KcheckResult<Expr> {
+        let left = self.parse_primary()?;
+
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op("=")) => CompareOp::Equal,
+            Some(Token::Op("!=")) => CompareOp::NotEqual,
+            Some(Token::Op("<")) => CompareOp::Less,
+            Some(Token::Op("<=")) => CompareOp::LessEqual,
+            Some(Token::Op(">")) => CompareOp::Greater,
+            Some(Token::Op(">=")) => CompareOp::GreaterEqual,
+            _ => return Ok(left),
+        };
+
+        self.pos += 1;
+        let right = self.parse_primary()?;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_primary(&mut self) -> KcheckResult<Expr> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+
+        match token {
+            Some(Token::Op("(")) => {
+                let expr = self.parse_or()?;
+                if !self.peek_op(")") {
+                    return Err(KcheckError::KconfigParseError(
+                        "Missing `)` in expression".to_string(),
+                    ));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Word(w)) if is_constant(&w) => Ok(Expr::Const(w)),
+            Some(Token::Word(w)) => Ok(Expr::Symbol(w)),
+            Some(Token::Str(s)) => Ok(Expr::Const(s)),
+            Some(t) => Err(KcheckError::KconfigParseError(format!(
+                "Unexpected `{t}` in expression"
+            ))),
+            None => Err(KcheckError::KconfigParseError(
+                "Unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct TestLookup(HashMap<&'static str, &'static str>);
+
+    impl SymbolLookup for TestLookup {
+        fn tristate(&mut self, name: &str) -> Tristate {
+            self.0
+                .get(name)
+                .and_then(|v| Tristate::from_value(v))
+                .unwrap_or_default()
+        }
+
+        fn string(&mut self, name: &str) -> String {
+            self.0.get(name).unwrap_or(&"").to_string()
+        }
+
+        fn modules(&mut self) -> Tristate {
+            self.tristate("MODULES")
+        }
+    }
+
+    fn helper_eval(expr: &str) -> Tristate {
+        let mut lookup = TestLookup(HashMap::from([
+            ("FOO", "y"),
+            ("BAR", "m"),
+            ("BAZ", "n"),
+            ("NUM", "0x20"),
+            ("TEXT", "hello"),
+        ]));
+
+        Expr::parse_condition(expr)
+            .expect("Expected to parse an expression")
+            .eval(&mut lookup)
+    }
+
+    #[test]
+    fn success_expr_eval() {
+        let test_array = [
+            ("FOO", Tristate::Yes),
+            ("BAR", Tristate::Module),
+            ("FOO && BAR", Tristate::Module),
+            ("BAZ || BAR", Tristate::Module),
+            ("!BAR", Tristate::Module),
+            ("!(FOO && !BAZ)", Tristate::No),
+            ("FOO && (BAZ || !BAZ)", Tristate::Yes),
+            ("UNDEFINED", Tristate::No),
+            ("BAR = m", Tristate::Yes),
+            ("FOO != y", Tristate::No),
+            ("NUM > 16", Tristate::Yes),
+            ("NUM <= 0x1f", Tristate::No),
+            ("TEXT = \"hello\"", Tristate::Yes),
+            ("m", Tristate::No),
+            ("", Tristate::No),
+        ];
+
+        for (expr, expected) in test_array {
+            assert_eq!(helper_eval(expr), expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn fail_expr_parse() {
+        for expr in ["FOO &&", "(FOO", "FOO BAR", "FOO = "] {
+            assert!(Expr::parse(expr).is_err(), "{expr}");
+        }
+    }
+}
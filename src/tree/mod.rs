@@ -0,0 +1,640 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Read the Kconfig symbol database of a kernel source tree.
+//!
+//! A [`KconfigTree`] knows the type, defaults, dependencies, `select`,
+//! `imply`, and `choice` relationships of every symbol. It can expand a
+//! minimal config, such as a defconfig, into the full config that the kernel
+//! build would produce with `make olddefconfig`.
+//!
+//! Macros that run shell commands, such as the toolchain checks in
+//! `scripts/Kconfig.include`, are not executed. Symbols that depend on them
+//! evaluate to `n`.
+
+mod expr;
+mod parser;
+mod resolve;
+
+use crate::{
+    error::{KcheckError, KcheckResult},
+    kernel::KernelConfig,
+};
+pub use expr::Tristate;
+pub(crate) use expr::{Expr, SymbolLookup};
+use nix::sys::utsname::uname;
+use parser::Parser;
+use resolve::Resolver;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Prefix added to symbol names in a kernel config.
+pub const CONFIG_PREFIX: &str = "CONFIG_";
+
+/// The type of a Kconfig symbol.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SymbolType {
+    /// Referenced but never given a type.
+    #[default]
+    Unknown,
+    Bool,
+    Tristate,
+    String,
+    Int,
+    Hex,
+}
+
+/// A prompt and the condition under which it is shown.
+#[derive(Clone, Debug)]
+pub(crate) struct Prompt {
+    pub(crate) text: String,
+    pub(crate) visible: Expr,
+}
+
+/// A value, such as a `default` or `select`, and the condition under which it applies.
+#[derive(Clone, Debug)]
+pub(crate) struct Property {
+    pub(crate) expr: Expr,
+    pub(crate) cond: Expr,
+}
+
+/// A Kconfig symbol.
+#[derive(Clone, Debug, Default)]
+pub struct Symbol {
+    pub(crate) name: String,
+    pub(crate) kind: SymbolType,
+    pub(crate) defined: bool,
+    pub(crate) prompts: Vec<Prompt>,
+    pub(crate) defaults: Vec<Property>,
+    /// Dependencies of the symbol, combined across all of its definitions.
+    pub(crate) dir_dep: Option<Expr>,
+    /// Symbols that `select` this symbol.
+    pub(crate) rev_dep: Vec<Property>,
+    /// Symbols that `imply` this symbol.
+    pub(crate) implied: Vec<Property>,
+    pub(crate) ranges: Vec<(Expr, Expr, Expr)>,
+    /// Index of the choice the symbol belongs to.
+    pub(crate) choice: Option<usize>,
+    pub(crate) help: Option<String>,
+}
+
+impl Symbol {
+    /// Name of the symbol without the `CONFIG_` prefix.
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Name of the symbol as it appears in a kernel config.
+    pub fn config_name(&self) -> String {
+        format!("{CONFIG_PREFIX}{}", self.name)
+    }
+
+    /// The type of the symbol.
+    pub fn kind(&self) -> SymbolType {
+        self.kind
+    }
+
+    /// The first prompt of the symbol, if it can be set by a user.
+    pub fn prompt(&self) -> Option<String> {
+        self.prompts.first().map(|p| p.text.clone())
+    }
+
+    /// The help text of the symbol.
+    pub fn help(&self) -> Option<String> {
+        self.help.clone()
+    }
+}
+
+/// A `choice` block, of which at most one member is selected.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Choice {
+    pub(crate) prompts: Vec<Prompt>,
+    pub(crate) defaults: Vec<Property>,
+    pub(crate) members: Vec<String>,
+    pub(crate) optional: bool,
+}
+
+/// The Kconfig symbol database of a kernel source tree.
+#[derive(Clone, Debug, Default)]
+pub struct KconfigTree {
+    pub(crate) symbols: Vec<Symbol>,
+    pub(crate) index: HashMap<String, usize>,
+    /// Symbols in the order they are first defined.
+    pub(crate) order: Vec<usize>,
+    pub(crate) choices: Vec<Choice>,
+    /// Name of the symbol that enables loadable module support.
+    pub(crate) modules: Option<String>,
+}
+
+impl KconfigTree {
+    /// Get the index of a symbol, adding it if it has not been seen yet.
+    pub(crate) fn symbol_index(&mut self, name: &str) -> usize {
+        if let Some(index) = self.index.get(name) {
+            return *index;
+        }
+
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            ..Default::default()
+        });
+        self.index.insert(name.to_string(), self.symbols.len() - 1);
+        self.symbols.len() - 1
+    }
+
+    /// Look up a defined symbol, with or without the `CONFIG_` prefix.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        let name = name.strip_prefix(CONFIG_PREFIX).unwrap_or(name);
+        self.index
+            .get(name)
+            .map(|i| &self.symbols[*i])
+            .filter(|s| s.defined)
+    }
+
+    /// All defined symbols, in the order they are defined.
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.order.iter().map(|i| &self.symbols[*i])
+    }
+
+    /// Symbols that have to be enabled for a symbol to be enabled.
+    ///
+    /// These are the symbols that the `depends on` of the symbol requires directly,
+    /// with the `CONFIG_` prefix. Symbols that are one of several alternatives,
+    /// such as `A || B`, are not included.
+    pub fn dependencies(&self, name: &str) -> Vec<String> {
+        let mut dependencies = Vec::new();
+        if let Some(dep) = self.symbol(name).and_then(|s| s.dir_dep.as_ref()) {
+            self.required_symbols(dep, &mut dependencies);
+        }
+
+        dependencies
+    }
+
+    /// Collect the symbols that an expression requires to be enabled.
+    fn required_symbols(&self, expr: &Expr, symbols: &mut Vec<String>) {
+        let name = match expr {
+            Expr::And(a, b) => {
+                self.required_symbols(a, symbols);
+                self.required_symbols(b, symbols);
+                return;
+            }
+            Expr::Symbol(s) => s,
+            Expr::Modules => match &self.modules {
+                Some(modules) => modules,
+                None => return,
+            },
+            _ => return,
+        };
+
+        let name = format!("{CONFIG_PREFIX}{name}");
+        if !symbols.contains(&name) {
+            symbols.push(name);
+        }
+    }
+
+    /// Expand a config into a fully resolved config, like `make olddefconfig`.
+    ///
+    /// Values in `config` are used where they are allowed by the dependencies
+    /// of each symbol. Every other symbol takes its default value, and
+    /// `select`, `imply`, and `choice` relationships are applied. Options that
+    /// are not defined in the tree are dropped.
+    pub fn resolve(&self, config: &KernelConfig) -> KcheckResult<KernelConfig> {
+        let lines = Resolver::new(self, Self::user_values(config)).config_lines();
+        Ok(KernelConfig::from_lines(config.source(), lines).with_provenance_from(config))
+    }
+
+    /// Reduce a config to the smallest config that reproduces it, like `make savedefconfig`.
+    ///
+    /// Options that are at their default value, that are forced by `select`,
+    /// or that cannot be set because of their dependencies are removed.
+    /// Resolving the result with [`KconfigTree::resolve`] produces the same
+    /// values as resolving `config`.
+    pub fn savedefconfig(&self, config: &KernelConfig) -> KcheckResult<KernelConfig> {
+        let lines = Resolver::new(self, Self::user_values(config)).minimal_lines();
+        Ok(KernelConfig::from_lines(config.source(), lines).with_provenance_from(config))
+    }
+
+    /// Values set in a kernel config, keyed by symbol name without the `CONFIG_` prefix.
+    fn user_values(config: &KernelConfig) -> HashMap<String, String> {
+        config
+            .entries()
+            .into_iter()
+            .filter_map(|(name, value)| {
+                name.strip_prefix(CONFIG_PREFIX)
+                    .map(|n| (n.to_string(), value))
+            })
+            .collect()
+    }
+}
+
+/// Build a [`KconfigTree`] from a kernel source tree.
+#[derive(Clone, Debug, Default)]
+pub struct KconfigTreeBuilder {
+    source_tree: Option<PathBuf>,
+    arch: Option<String>,
+    kconfig: Option<PathBuf>,
+    variables: HashMap<String, String>,
+}
+
+impl KconfigTreeBuilder {
+    /// Path to the root of the kernel source tree.
+    pub fn source_tree<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.source_tree = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// The target architecture, such as `arm64` or `x86_64`.
+    ///
+    /// Defaults to the architecture of the running system.
+    pub fn arch(mut self, arch: &str) -> Self {
+        self.arch = Some(arch.to_string());
+        self
+    }
+
+    /// Path to the top level Kconfig file, relative to the source tree.
+    ///
+    /// Defaults to `Kconfig`.
+    pub fn kconfig<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.kconfig = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set a variable used when expanding macros in Kconfig files.
+    pub fn variable(mut self, name: &str, value: &str) -> Self {
+        self.variables.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Map an architecture name to the `ARCH` and `SRCARCH` used by the kernel build.
+    fn arch_variables(arch: &str) -> (String, String) {
+        let arch = match arch {
+            "aarch64" => "arm64",
+            "i686" | "i586" | "i486" | "i386" => "i386",
+            a if a.starts_with("arm") && a != "arm64" => "arm",
+            "riscv64" | "riscv32" => "riscv",
+            "ppc" | "ppc64" | "ppc64le" => "powerpc",
+            "mips64" | "mips64el" | "mipsel" => "mips",
+            "s390x" => "s390",
+            "loongarch64" => "loongarch",
+            a => a,
+        };
+
+        let srcarch = match arch {
+            "x86_64" | "i386" => "x86",
+            "sparc32" | "sparc64" => "sparc",
+            "parisc64" => "parisc",
+            "sh64" => "sh",
+            a => a,
+        };
+
+        (arch.to_string(), srcarch.to_string())
+    }
+
+    /// Parse the Kconfig files of the source tree.
+    pub fn build(self) -> KcheckResult<KconfigTree> {
+        let source_tree = self.source_tree.ok_or_else(|| {
+            KcheckError::KconfigParseError("No kernel source tree set".to_string())
+        })?;
+
+        let arch = match self.arch {
+            Some(a) => a,
+            None => uname()
+                .map(|u| u.machine().to_string_lossy().to_string())
+                .map_err(|e| KcheckError::KconfigParseError(e.to_string()))?,
+        };
+
+        let kconfig = self.kconfig.unwrap_or_else(|| PathBuf::from("Kconfig"));
+        if !source_tree.join(&kconfig).exists() {
+            return Err(KcheckError::FileDoesNotExist(
+                source_tree.join(&kconfig).to_string_lossy().to_string(),
+            ));
+        }
+
+        let (arch, srcarch) = Self::arch_variables(&arch);
+        let mut variables = HashMap::from([
+            ("ARCH".to_string(), arch),
+            ("SRCARCH".to_string(), srcarch),
+            (
+                "srctree".to_string(),
+                source_tree.to_string_lossy().to_string(),
+            ),
+        ]);
+        variables.extend(self.variables);
+
+        Parser::new(&source_tree, &variables).parse(&kconfig)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kconfig::KconfigState;
+    use std::str::FromStr;
+
+    const TEST_KCONFIG: &str = r#"
+mainmenu "Test $(ARCH) Kernel Configuration"
+
+config MODULES
+	bool "Enable loadable module support"
+	modules
+
+config NET
+	bool "Networking support"
+	default y
+
+config INET
+	bool "TCP/IP networking"
+	depends on NET
+	default y
+
+config NETFILTER
+	tristate "Netfilter"
+	depends on INET
+	select NETFILTER_CORE
+	imply NETFILTER_LOG
+
+config NETFILTER_CORE
+	tristate
+
+config NETFILTER_LOG
+	tristate "Netfilter logging"
+
+config CC_HAS_FEATURE
+	def_bool $(success,$(CC) -mfeature)
+
+config HZ
+	int "Timer frequency"
+	range 100 1000
+	default 250
+
+config HOSTNAME
+	string "Default hostname"
+	default "(none)"
+
+choice
+	prompt "Preemption model"
+	default PREEMPT_VOLUNTARY
+
+config PREEMPT_NONE
+	bool "No forced preemption"
+
+config PREEMPT_VOLUNTARY
+	bool "Voluntary preemption"
+
+config PREEMPT
+	bool "Preemptible kernel"
+
+endchoice
+
+source "arch/$(SRCARCH)/Kconfig"
+"#;
+
+    const TEST_ARCH_KCONFIG: &str = r#"
+config ARM64
+	def_bool y
+	select NET
+
+menu "Platform"
+	depends on ARM64
+
+config ARCH_TEST
+	bool "Test platform"
+	help
+	  Support for the test platform.
+
+	  Say Y here.
+
+endmenu
+"#;
+
+    /// Create a minimal kernel source tree with an arm64 architecture.
+    fn helper_create_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        std::fs::write(dir.path().join("Kconfig"), TEST_KCONFIG).unwrap();
+        std::fs::create_dir_all(dir.path().join("arch/arm64")).unwrap();
+        std::fs::write(dir.path().join("arch/arm64/Kconfig"), TEST_ARCH_KCONFIG).unwrap();
+        dir
+    }
+
+    fn helper_resolve(tree: &KconfigTree, config: &str) -> KernelConfig {
+        let config = KernelConfig::from_str(config).expect("Expected to parse a kernel config");
+        tree.resolve(&config)
+            .expect("Expected to resolve the kernel config")
+    }
+
+    #[test]
+    fn success_tree_symbols() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("aarch64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let symbol = tree.symbol("CONFIG_ARCH_TEST").expect("Expected a symbol");
+        assert_eq!(symbol.kind(), SymbolType::Bool);
+        assert_eq!(symbol.prompt(), Some("Test platform".to_string()));
+        assert_eq!(
+            symbol.help(),
+            Some("Support for the test platform.\n\nSay Y here.".to_string())
+        );
+
+        assert_eq!(tree.symbol("HZ").unwrap().kind(), SymbolType::Int);
+        assert!(tree.symbol("DOES_NOT_EXIST").is_none());
+        assert_eq!(tree.symbols().next().unwrap().name(), "MODULES");
+    }
+
+    #[test]
+    fn success_tree_resolve_defaults() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let cfg = helper_resolve(&tree, "CONFIG_NETFILTER=y\nCONFIG_ARCH_TEST=y\n");
+
+        let test_array = [
+            ("CONFIG_ARM64", KconfigState::On),
+            ("CONFIG_NET", KconfigState::On),
+            ("CONFIG_INET", KconfigState::On),
+            ("CONFIG_NETFILTER", KconfigState::On),
+            ("CONFIG_NETFILTER_CORE", KconfigState::On),
+            ("CONFIG_NETFILTER_LOG", KconfigState::On),
+            ("CONFIG_MODULES", KconfigState::NotSet),
+            ("CONFIG_CC_HAS_FEATURE", KconfigState::NotSet),
+            ("CONFIG_PREEMPT_VOLUNTARY", KconfigState::On),
+            ("CONFIG_PREEMPT", KconfigState::NotSet),
+            ("CONFIG_ARCH_TEST", KconfigState::On),
+        ];
+
+        for (option, state) in test_array {
+            assert_eq!(cfg.option(option).unwrap(), state, "{option}");
+        }
+
+        let entries = cfg.entries();
+        assert!(entries.contains(&("CONFIG_HZ".to_string(), "250".to_string())));
+        assert!(entries.contains(&("CONFIG_HOSTNAME".to_string(), "(none)".to_string())));
+    }
+
+    #[test]
+    fn success_tree_resolve_provenance() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let cfg = helper_resolve(&tree, "CONFIG_ARCH_TEST=y\nCONFIG_NETFILTER=y\n");
+
+        let (_, provenance) = cfg.option_with_provenance("CONFIG_NETFILTER").unwrap();
+        assert_eq!(provenance.map(|p| p.line()), Some(2));
+
+        let (_, provenance) = cfg.option_with_provenance("CONFIG_NET").unwrap();
+        assert_eq!(provenance, None);
+    }
+    #[test]
+    fn success_tree_resolve_user_values() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let cfg = helper_resolve(
+            &tree,
+            "CONFIG_MODULES=y\nCONFIG_NETFILTER=m\n# CONFIG_INET is not set\nCONFIG_PREEMPT=y\nCONFIG_HZ=5000\n",
+        );
+
+        let test_array = [
+            ("CONFIG_MODULES", KconfigState::On),
+            // Dependencies are not met, so the user value is dropped
+            ("CONFIG_NETFILTER", KconfigState::NotFound),
+            ("CONFIG_INET", KconfigState::NotSet),
+            ("CONFIG_PREEMPT", KconfigState::On),
+            ("CONFIG_PREEMPT_VOLUNTARY", KconfigState::NotSet),
+        ];
+
+        for (option, state) in test_array {
+            assert_eq!(cfg.option(option).unwrap(), state, "{option}");
+        }
+
+        // Values outside of the allowed range are replaced by the default
+        assert!(cfg
+            .entries()
+            .contains(&("CONFIG_HZ".to_string(), "250".to_string())));
+    }
+
+    #[test]
+    fn success_tree_resolve_modules() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let cfg = helper_resolve(&tree, "CONFIG_MODULES=y\nCONFIG_NETFILTER=m\n");
+        assert_eq!(
+            cfg.option("CONFIG_NETFILTER").unwrap(),
+            KconfigState::Module
+        );
+        assert_eq!(
+            cfg.option("CONFIG_NETFILTER_CORE").unwrap(),
+            KconfigState::Module
+        );
+
+        // Without module support, `m` is promoted to `y`
+        let cfg = helper_resolve(&tree, "CONFIG_NETFILTER=m\n");
+        assert_eq!(cfg.option("CONFIG_NETFILTER").unwrap(), KconfigState::On);
+    }
+
+    #[test]
+    fn success_tree_dependencies() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        assert_eq!(tree.dependencies("CONFIG_NETFILTER"), ["CONFIG_INET"]);
+        assert_eq!(tree.dependencies("INET"), ["CONFIG_NET"]);
+        assert!(tree.dependencies("CONFIG_NET").is_empty());
+        assert!(tree.dependencies("CONFIG_MISSING").is_empty());
+    }
+
+    #[test]
+    fn success_tree_savedefconfig() {
+        let dir = helper_create_tree();
+        let tree = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build()
+            .expect("Expected to parse the Kconfig tree");
+
+        let full = helper_resolve(
+            &tree,
+            "CONFIG_NET=y\nCONFIG_NETFILTER=y\nCONFIG_HZ=300\nCONFIG_PREEMPT=y\nCONFIG_ARCH_TEST=y\n",
+        );
+        let minimal = tree
+            .savedefconfig(&full)
+            .expect("Expected to minimize the kernel config");
+
+        assert_eq!(
+            minimal.to_string(),
+            "CONFIG_NETFILTER=y\nCONFIG_HZ=300\nCONFIG_PREEMPT=y\nCONFIG_ARCH_TEST=y\n"
+        );
+
+        // The minimal config expands back to the full config
+        let expanded = tree.resolve(&minimal).unwrap();
+        assert_eq!(expanded.entries(), full.entries());
+
+        // A config that only contains defaults is reduced to nothing
+        let defaults = helper_resolve(&tree, "");
+        assert_eq!(tree.savedefconfig(&defaults).unwrap().to_string(), "");
+    }
+
+    #[test]
+    fn fail_tree_missing_source() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let result = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build();
+        assert!(matches!(result, Err(KcheckError::FileDoesNotExist(_))));
+
+        std::fs::write(dir.path().join("Kconfig"), "source \"missing/Kconfig\"\n").unwrap();
+        let result = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build();
+        assert!(matches!(result, Err(KcheckError::KconfigParseError(_))));
+
+        assert!(KconfigTreeBuilder::default().build().is_err());
+    }
+
+    #[test]
+    fn fail_tree_unclosed_block() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        std::fs::write(
+            dir.path().join("Kconfig"),
+            "menu \"Test\"\nconfig A\n\tbool\n",
+        )
+        .unwrap();
+
+        let result = KconfigTreeBuilder::default()
+            .source_tree(dir.path())
+            .arch("arm64")
+            .build();
+        assert!(matches!(result, Err(KcheckError::KconfigParseError(_))));
+    }
+}
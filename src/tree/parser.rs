@@ -0,0 +1,866 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{expr::Expr, Choice, KconfigTree, Prompt, Property, Symbol, SymbolType};
+use crate::{
+    error::{KcheckError, KcheckResult},
+    util,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Take a quoted string from the start of `text`.
+///
+/// Returns the unescaped string and the remaining text.
+pub(crate) fn take_string(text: &str) -> KcheckResult<(String, &str)> {
+    let mut chars = text.char_indices();
+    let quote = match chars.next() {
+        Some((_, q)) if q == '"' || q == '\'' => q,
+        _ => {
+            return Err(KcheckError::KconfigParseError(format!(
+                "Expected a quoted string: {text}"
+            )))
+        }
+    };
+
+    let mut value = String::new();
+    let mut escaped = false;
+    for (i, c) in chars {
+        if escaped {
+            value.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return Ok((value, &text[i + 1..]));
+        } else {
+            value.push(c);
+        }
+    }
+
+    Err(KcheckError::KconfigParseError(format!(
+        "Unterminated string: {text}"
+    )))
+}
+
+/// Find the byte offset of a standalone word outside of quoted strings.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) => {
+                if b == b'\\' {
+                    i += 1;
+                } else if b == q {
+                    quote = None;
+                }
+            }
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None => {
+                let before = i == 0 || bytes[i - 1].is_ascii_whitespace();
+                let end = i + word.len();
+                let after = end >= bytes.len() || bytes[end].is_ascii_whitespace();
+                if before && after && text[i..].starts_with(word) {
+                    return Some(i);
+                }
+            }
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Split a trailing `if <expr>` condition from an attribute.
+fn split_condition(text: &str) -> KcheckResult<(&str, Option<Expr>)> {
+    match find_word(text, "if") {
+        Some(pos) => Ok((
+            text[..pos].trim(),
+            Some(Expr::parse_condition(&text[pos + 2..])?),
+        )),
+        None => Ok((text.trim(), None)),
+    }
+}
+
+/// Remove a `#` comment that is not inside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+
+    line
+}
+
+/// Width of the leading whitespace of a line, with tabs expanded to 8 columns.
+fn indentation(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width = (width / 8 + 1) * 8,
+            _ => break,
+        }
+    }
+    width
+}
+
+#[derive(Clone, Debug)]
+enum EntryKind {
+    Config(String),
+    Choice,
+    Menu,
+    Comment,
+}
+
+#[derive(Clone, Debug)]
+enum Attr {
+    Type(SymbolType),
+    Prompt(String, Option<Expr>),
+    Default(Expr, Option<Expr>),
+    DependsOn(Expr),
+    Select(String, Option<Expr>),
+    Imply(String, Option<Expr>),
+    Range(Expr, Expr, Option<Expr>),
+    VisibleIf(Expr),
+    Optional,
+    Modules,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    kind: EntryKind,
+    attrs: Vec<Attr>,
+    help: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FrameKind {
+    If,
+    Menu,
+    Choice,
+}
+
+/// An open `if`, `menu`, or `choice` block.
+#[derive(Clone, Debug)]
+struct Frame {
+    kind: FrameKind,
+    /// Dependencies of the block's children.
+    dep: Expr,
+    /// Visibility of the block's children, from `visible if`.
+    visible: Expr,
+}
+
+/// A Kconfig preprocessor variable.
+#[derive(Clone, Debug)]
+struct Variable {
+    value: String,
+    recursive: bool,
+}
+
+/// Help text being collected.
+#[derive(Clone, Debug, Default)]
+struct HelpState {
+    indent: Option<usize>,
+    text: String,
+}
+
+pub(crate) struct Parser {
+    srctree: PathBuf,
+    variables: HashMap<String, Variable>,
+    tree: KconfigTree,
+    frames: Vec<Frame>,
+    pending: Option<Entry>,
+    help: Option<HelpState>,
+    /// Current file and line, used for error messages and `$(filename)`.
+    location: (PathBuf, usize),
+}
+
+impl Parser {
+    pub(crate) fn new(srctree: &Path, variables: &HashMap<String, String>) -> Self {
+        let variables = variables
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.clone(),
+                    Variable {
+                        value: v.clone(),
+                        recursive: false,
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            srctree: srctree.to_path_buf(),
+            variables,
+            tree: KconfigTree::default(),
+            frames: Vec::new(),
+            pending: None,
+            help: None,
+            location: (PathBuf::new(), 0),
+        }
+    }
+
+    /// Parse the top level Kconfig file and everything it sources.
+    pub(crate) fn parse(mut self, kconfig: &Path) -> KcheckResult<KconfigTree> {
+        self.parse_file(&self.srctree.join(kconfig))?;
+        self.finalize_entry()?;
+
+        if !self.frames.is_empty() {
+            return Err(KcheckError::KconfigParseError(format!(
+                "{} block is not closed",
+                match self.frames[self.frames.len() - 1].kind {
+                    FrameKind::If => "if",
+                    FrameKind::Menu => "menu",
+                    FrameKind::Choice => "choice",
+                }
+            )));
+        }
+
+        Ok(self.tree)
+    }
+
+    fn error(&self, message: &str) -> KcheckError {
+        KcheckError::KconfigParseError(format!(
+            "{}:{}: {message}",
+            self.location.0.display(),
+            self.location.1
+        ))
+    }
+
+    fn parse_file(&mut self, path: &Path) -> KcheckResult<()> {
+        let contents = util::file_contents_as_string(path)?;
+        let saved_location = self.location.clone();
+
+        let mut lines = contents.lines().enumerate();
+        while let Some((index, raw)) = lines.next() {
+            self.location = (path.to_path_buf(), index + 1);
+
+            if self.collect_help(raw) {
+                continue;
+            }
+
+            // Join continued lines
+            let mut line = raw.to_string();
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some((_, next)) => line.push_str(next),
+                    None => break,
+                }
+            }
+
+            self.parse_line(&line)?;
+        }
+
+        self.location = saved_location;
+        Ok(())
+    }
+
+    /// Add a line to the current help text.
+    ///
+    /// Returns `false` if the line is not a part of the help text.
+    fn collect_help(&mut self, raw: &str) -> bool {
+        let Some(help) = self.help.as_mut() else {
+            return false;
+        };
+
+        if raw.trim().is_empty() {
+            if help.indent.is_some() {
+                help.text.push('\n');
+            }
+            return true;
+        }
+
+        let indent = indentation(raw);
+        let in_help = match help.indent {
+            Some(i) => indent >= i,
+            None if indent > 0 => {
+                help.indent = Some(indent);
+                true
+            }
+            None => false,
+        };
+
+        if in_help {
+            help.text.push_str(raw.trim());
+            help.text.push('\n');
+            return true;
+        }
+
+        let text = self.help.take().map(|h| h.text.trim_end().to_string());
+        if let Some(entry) = self.pending.as_mut() {
+            entry.help = text;
+        }
+
+        false
+    }
+
+    fn parse_line(&mut self, line: &str) -> KcheckResult<()> {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        if self.parse_assignment(line)? {
+            return Ok(());
+        }
+
+        let line = self.expand(line, &[])?;
+        let line = line.trim();
+        let (keyword, rest) = match line.split_once(char::is_whitespace) {
+            Some((k, r)) => (k, r.trim()),
+            None => (line, ""),
+        };
+
+        match keyword {
+            "mainmenu" => Ok(()),
+            "config" | "menuconfig" => self.begin_entry(EntryKind::Config(rest.to_string())),
+            "choice" => self.begin_entry(EntryKind::Choice),
+            "menu" => self.begin_entry(EntryKind::Menu),
+            "comment" => self.begin_entry(EntryKind::Comment),
+            "endchoice" => self.end_block(FrameKind::Choice),
+            "endmenu" => self.end_block(FrameKind::Menu),
+            "endif" => self.end_block(FrameKind::If),
+            "if" => {
+                self.finalize_entry()?;
+                let dep = self.parent_dep().and(Expr::parse_condition(rest)?);
+                let visible = self.parent_visible();
+                self.frames.push(Frame {
+                    kind: FrameKind::If,
+                    dep,
+                    visible,
+                });
+                Ok(())
+            }
+            "source" | "rsource" | "osource" | "orsource" => {
+                self.finalize_entry()?;
+                self.source(keyword, rest)
+            }
+            _ => self.parse_attribute(keyword, rest),
+        }
+    }
+
+    /// Handle a preprocessor variable assignment.
+    ///
+    /// Returns `false` if the line is not an assignment.
+    fn parse_assignment(&mut self, line: &str) -> KcheckResult<bool> {
+        let name_end = line
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(line.len());
+        if name_end == 0 {
+            return Ok(false);
+        }
+
+        let name = &line[..name_end];
+        let rest = line[name_end..].trim_start();
+
+        let (op, value) = if let Some(v) = rest.strip_prefix(":=") {
+            (":=", v)
+        } else if let Some(v) = rest.strip_prefix("+=") {
+            ("+=", v)
+        } else if let Some(v) = rest.strip_prefix('=') {
+            ("=", v)
+        } else {
+            return Ok(false);
+        };
+
+        let value = value.trim();
+        let variable = match op {
+            ":=" => Variable {
+                value: self.expand(value, &[])?,
+                recursive: false,
+            },
+            "=" => Variable {
+                value: value.to_string(),
+                recursive: true,
+            },
+            _ => {
+                let mut variable = self.variables.get(name).cloned().unwrap_or(Variable {
+                    value: String::new(),
+                    recursive: true,
+                });
+                let appended = match variable.recursive {
+                    true => value.to_string(),
+                    false => self.expand(value, &[])?,
+                };
+                if !variable.value.is_empty() {
+                    variable.value.push(' ');
+                }
+                variable.value.push_str(&appended);
+                variable
+            }
+        };
+
+        self.variables.insert(name.to_string(), variable);
+        Ok(true)
+    }
+
+    /// Expand `$(...)` macro references.
+    ///
+    /// Commands run by `$(shell,...)` are not executed and expand to an empty
+    /// string, so toolchain dependent symbols evaluate to `n`.
+    fn expand(&self, text: &str, args: &[String]) -> KcheckResult<String> {
+        let mut result = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+
+            if let Some(inner_start) = after.strip_prefix('(') {
+                let end = Self::matching_paren(inner_start)
+                    .ok_or_else(|| self.error("Unterminated macro reference"))?;
+                result.push_str(&self.call(&inner_start[..end], args)?);
+                rest = &inner_start[end + 1..];
+            } else {
+                // Legacy `$VARIABLE` references
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                if end == 0 {
+                    result.push('$');
+                } else {
+                    result.push_str(&self.call(&after[..end], args)?);
+                }
+                rest = &after[end..];
+            }
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    /// Find the `)` that closes a macro reference.
+    fn matching_paren(text: &str) -> Option<usize> {
+        let mut depth = 0;
+        for (i, c) in text.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(i),
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Split macro arguments on commas that are not nested in another reference.
+    fn split_args(text: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+
+        for (i, c) in text.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(&text[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+
+        parts.push(&text[start..]);
+        parts
+    }
+
+    /// Evaluate a single macro reference.
+    fn call(&self, reference: &str, args: &[String]) -> KcheckResult<String> {
+        let parts = Self::split_args(reference);
+        let name = self.expand(parts[0], args)?;
+        let name = name.trim();
+
+        let call_args = parts[1..]
+            .iter()
+            .map(|a| self.expand(a, args))
+            .collect::<KcheckResult<Vec<String>>>()?;
+
+        if call_args.is_empty() {
+            if let Ok(index) = name.parse::<usize>() {
+                return Ok(args.get(index.wrapping_sub(1)).cloned().unwrap_or_default());
+            }
+        }
+
+        match name {
+            "shell" | "info" | "warning-if" | "error-if" => return Ok(String::new()),
+            "filename" => return Ok(self.location.0.display().to_string()),
+            "lineno" => return Ok(self.location.1.to_string()),
+            _ => {}
+        }
+
+        match self.variables.get(name) {
+            Some(v) if v.recursive => self.expand(&v.value, &call_args),
+            Some(v) => Ok(v.value.clone()),
+            None => Ok(String::new()),
+        }
+    }
+
+    fn source(&mut self, keyword: &str, rest: &str) -> KcheckResult<()> {
+        let (path, _) = take_string(rest).map_err(|_| self.error("Expected a quoted path"))?;
+
+        let path = match keyword {
+            "rsource" | "orsource" => self.location.0.parent().unwrap_or(Path::new("")).join(path),
+            _ => self.srctree.join(path),
+        };
+
+        if !path.exists() {
+            if keyword.starts_with('o') {
+                return Ok(());
+            }
+            return Err(self.error(&format!("Sourced file not found: {}", path.display())));
+        }
+
+        self.parse_file(&path)
+    }
+
+    fn begin_entry(&mut self, kind: EntryKind) -> KcheckResult<()> {
+        self.finalize_entry()?;
+
+        if let EntryKind::Config(name) = &kind {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(self.error(&format!("Invalid symbol name `{name}`")));
+            }
+        }
+
+        self.pending = Some(Entry {
+            kind,
+            attrs: Vec::new(),
+            help: None,
+        });
+        Ok(())
+    }
+
+    fn end_block(&mut self, kind: FrameKind) -> KcheckResult<()> {
+        self.finalize_entry()?;
+
+        match self.frames.pop() {
+            Some(frame) if frame.kind == kind => Ok(()),
+            _ => Err(self.error("Unexpected end of block")),
+        }
+    }
+
+    fn parse_attribute(&mut self, keyword: &str, rest: &str) -> KcheckResult<()> {
+        let attr = match keyword {
+            "bool" | "boolean" | "tristate" | "string" | "int" | "hex" | "def_bool"
+            | "def_tristate" => {
+                let kind = match keyword {
+                    "bool" | "boolean" | "def_bool" => SymbolType::Bool,
+                    "tristate" | "def_tristate" => SymbolType::Tristate,
+                    "string" => SymbolType::String,
+                    "int" => SymbolType::Int,
+                    _ => SymbolType::Hex,
+                };
+
+                let (value, cond) = split_condition(rest)?;
+                if keyword.starts_with("def_") {
+                    self.push_attr(Attr::Type(kind))?;
+                    Attr::Default(Expr::parse(value)?, cond)
+                } else if value.is_empty() {
+                    Attr::Type(kind)
+                } else {
+                    self.push_attr(Attr::Type(kind))?;
+                    Attr::Prompt(take_string(value)?.0, cond)
+                }
+            }
+            "prompt" => {
+                let (value, cond) = split_condition(rest)?;
+                Attr::Prompt(take_string(value)?.0, cond)
+            }
+            "default" => {
+                let (value, cond) = split_condition(rest)?;
+                Attr::Default(Expr::parse(value)?, cond)
+            }
+            "depends" => {
+                let expr = rest
+                    .strip_prefix("on")
+                    .ok_or_else(|| self.error("Expected `depends on`"))?;
+                Attr::DependsOn(Expr::parse_condition(expr)?)
+            }
+            "select" | "imply" => {
+                let (value, cond) = split_condition(rest)?;
+                match keyword {
+                    "select" => Attr::Select(value.to_string(), cond),
+                    _ => Attr::Imply(value.to_string(), cond),
+                }
+            }
+            "range" => {
+                let (value, cond) = split_condition(rest)?;
+                let bounds: Vec<&str> = value.split_whitespace().collect();
+                if bounds.len() != 2 {
+                    return Err(self.error("Expected two values for `range`"));
+                }
+                Attr::Range(Expr::parse(bounds[0])?, Expr::parse(bounds[1])?, cond)
+            }
+            "visible" => {
+                let expr = rest
+                    .strip_prefix("if")
+                    .ok_or_else(|| self.error("Expected `visible if`"))?;
+                Attr::VisibleIf(Expr::parse_condition(expr)?)
+            }
+            "help" | "---help---" => {
+                self.help = Some(HelpState::default());
+                return Ok(());
+            }
+            "optional" => Attr::Optional,
+            "modules" => Attr::Modules,
+            "option" if rest.trim() == "modules" => Attr::Modules,
+            "option" | "transitional" | "allnoconfig_y" => return Ok(()),
+            _ => return Err(self.error(&format!("Unknown keyword `{keyword}`"))),
+        };
+
+        self.push_attr(attr)
+    }
+
+    fn push_attr(&mut self, attr: Attr) -> KcheckResult<()> {
+        match self.pending.as_mut() {
+            Some(entry) => {
+                entry.attrs.push(attr);
+                Ok(())
+            }
+            None => Err(self.error("Attribute outside of an entry")),
+        }
+    }
+
+    /// Combined dependencies of all open blocks.
+    fn parent_dep(&self) -> Expr {
+        self.frames
+            .last()
+            .map(|f| f.dep.clone())
+            .unwrap_or_else(Expr::yes)
+    }
+
+    /// Combined `visible if` conditions of all open blocks.
+    fn parent_visible(&self) -> Expr {
+        self.frames
+            .last()
+            .map(|f| f.visible.clone())
+            .unwrap_or_else(Expr::yes)
+    }
+
+    /// Index of the innermost open choice, if any.
+    fn current_choice(&self) -> Option<usize> {
+        match self.frames.iter().rev().find(|f| f.kind != FrameKind::If) {
+            Some(f) if f.kind == FrameKind::Choice => Some(self.tree.choices.len() - 1),
+            _ => None,
+        }
+    }
+
+    /// Add the pending entry to the tree.
+    fn finalize_entry(&mut self) -> KcheckResult<()> {
+        if let Some(help) = self.help.take() {
+            if let Some(entry) = self.pending.as_mut() {
+                entry.help = Some(help.text.trim_end().to_string());
+            }
+        }
+
+        let Some(entry) = self.pending.take() else {
+            return Ok(());
+        };
+
+        let dep = entry
+            .attrs
+            .iter()
+            .filter_map(|a| match a {
+                Attr::DependsOn(e) => Some(e.clone()),
+                _ => None,
+            })
+            .fold(self.parent_dep(), Expr::and);
+        let visible = self.parent_visible();
+        let cond = |c: &Option<Expr>| match c {
+            Some(c) => c.clone().and(dep.clone()),
+            None => dep.clone(),
+        };
+
+        match entry.kind {
+            EntryKind::Config(name) => {
+                let choice = self.current_choice();
+                let index = self.tree.symbol_index(&name);
+
+                for attr in &entry.attrs {
+                    match attr {
+                        Attr::Type(kind) => {
+                            let symbol = &mut self.tree.symbols[index];
+                            if symbol.kind == SymbolType::Unknown {
+                                symbol.kind = *kind;
+                            }
+                        }
+                        Attr::Prompt(text, c) => self.tree.symbols[index].prompts.push(Prompt {
+                            text: text.clone(),
+                            visible: cond(c).and(visible.clone()),
+                        }),
+                        Attr::Default(expr, c) => {
+                            self.tree.symbols[index].defaults.push(Property {
+                                expr: expr.clone(),
+                                cond: cond(c),
+                            })
+                        }
+                        Attr::Select(target, c) | Attr::Imply(target, c) => {
+                            let target_index = self.tree.symbol_index(target);
+                            let property = Property {
+                                expr: Expr::Symbol(name.clone()),
+                                cond: cond(c),
+                            };
+                            let target = &mut self.tree.symbols[target_index];
+                            match attr {
+                                Attr::Select(..) => target.rev_dep.push(property),
+                                _ => target.implied.push(property),
+                            }
+                        }
+                        Attr::Range(min, max, c) => self.tree.symbols[index].ranges.push((
+                            min.clone(),
+                            max.clone(),
+                            cond(c),
+                        )),
+                        Attr::Modules => self.tree.modules = Some(name.clone()),
+                        Attr::DependsOn(_) | Attr::VisibleIf(_) | Attr::Optional => {}
+                    }
+                }
+
+                if !self.tree.symbols[index].defined {
+                    self.tree.order.push(index);
+                }
+
+                let symbol: &mut Symbol = &mut self.tree.symbols[index];
+                symbol.defined = true;
+                symbol.dir_dep = Some(match symbol.dir_dep.take() {
+                    Some(existing) => existing.or(dep.clone()),
+                    None => dep.clone(),
+                });
+                if entry.help.is_some() {
+                    symbol.help = entry.help;
+                }
+
+                if let Some(choice) = choice {
+                    if symbol.choice.is_none() {
+                        symbol.choice = Some(choice);
+                        self.tree.choices[choice].members.push(name);
+                    }
+                }
+            }
+            EntryKind::Choice => {
+                let mut choice = Choice::default();
+
+                for attr in &entry.attrs {
+                    match attr {
+                        Attr::Prompt(text, c) => choice.prompts.push(Prompt {
+                            text: text.clone(),
+                            visible: cond(c).and(visible.clone()),
+                        }),
+                        Attr::Default(expr, c) => choice.defaults.push(Property {
+                            expr: expr.clone(),
+                            cond: cond(c),
+                        }),
+                        Attr::Optional => choice.optional = true,
+                        _ => {}
+                    }
+                }
+
+                self.tree.choices.push(choice);
+                self.frames.push(Frame {
+                    kind: FrameKind::Choice,
+                    dep,
+                    visible,
+                });
+            }
+            EntryKind::Menu => {
+                let visible = entry
+                    .attrs
+                    .iter()
+                    .filter_map(|a| match a {
+                        Attr::VisibleIf(e) => Some(e.clone()),
+                        _ => None,
+                    })
+                    .fold(visible, Expr::and);
+
+                self.frames.push(Frame {
+                    kind: FrameKind::Menu,
+                    dep,
+                    visible,
+                });
+            }
+            EntryKind::Comment => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn success_take_string() {
+        let (value, rest) = take_string(r#""a \"quoted\" string" if FOO"#).unwrap();
+        assert_eq!(value, r#"a "quoted" string"#);
+        assert_eq!(rest, " if FOO");
+        assert!(take_string("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn success_split_condition() {
+        let (value, cond) = split_condition(r#""prompt if this" if FOO && BAR"#).unwrap();
+        assert_eq!(value, r#""prompt if this""#);
+        assert_eq!(cond, Some(Expr::parse("FOO && BAR").unwrap()));
+
+        let (value, cond) = split_condition("y").unwrap();
+        assert_eq!(value, "y");
+        assert_eq!(cond, None);
+    }
+
+    #[test]
+    fn success_strip_comment() {
+        assert_eq!(strip_comment("bool \"a # b\" # comment"), "bool \"a # b\" ");
+        assert_eq!(strip_comment("# comment"), "");
+    }
+
+    #[test]
+    fn success_expand_macros() {
+        let variables = HashMap::from([("SRCARCH".to_string(), "arm64".to_string())]);
+        let mut parser = Parser::new(Path::new("/"), &variables);
+
+        parser.parse_assignment("success = $(shell,true)").unwrap();
+        parser
+            .parse_assignment("cc-option = $(success,$(CC) $(1))")
+            .unwrap();
+        parser.parse_assignment("greet = hello $(1)").unwrap();
+
+        assert_eq!(
+            parser.expand("arch/$(SRCARCH)/Kconfig", &[]).unwrap(),
+            "arch/arm64/Kconfig"
+        );
+        assert_eq!(
+            parser.expand("arch/$SRCARCH/Kconfig", &[]).unwrap(),
+            "arch/arm64/Kconfig"
+        );
+        assert_eq!(parser.expand("$(greet,world)", &[]).unwrap(), "hello world");
+        assert_eq!(
+            parser.expand("def_bool $(cc-option,-mfoo)", &[]).unwrap(),
+            "def_bool "
+        );
+    }
+}
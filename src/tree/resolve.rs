@@ -0,0 +1,442 @@
+// Copyright (c) 2023 Jake Swensen
+// SPDX-License-Identifier: MPL-2.0
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{
+    expr::{parse_number, Expr, SymbolLookup, Tristate},
+    KconfigTree, Property, Symbol, SymbolType, CONFIG_PREFIX,
+};
+use std::collections::{HashMap, HashSet};
+
+/// The calculated value of a symbol.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SymbolValue {
+    pub(crate) tristate: Tristate,
+    pub(crate) string: String,
+    /// The symbol is written to the resolved config.
+    pub(crate) write: bool,
+}
+
+/// Calculates symbol values the same way as the kernel's `conf` tool.
+///
+/// Values are calculated lazily and cached, so symbols can be resolved in any
+/// order. Dependency cycles resolve to `n`.
+pub(crate) struct Resolver<'a> {
+    tree: &'a KconfigTree,
+    /// Values from the user's config, without the `CONFIG_` prefix.
+    user: HashMap<String, String>,
+    values: HashMap<usize, SymbolValue>,
+    in_progress: HashSet<usize>,
+    /// The selected member of each choice.
+    choices: HashMap<usize, Option<usize>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub(crate) fn new(tree: &'a KconfigTree, user: HashMap<String, String>) -> Self {
+        Self {
+            tree,
+            user,
+            values: HashMap::new(),
+            in_progress: HashSet::new(),
+            choices: HashMap::new(),
+        }
+    }
+
+    /// The resolved config, in the order symbols are defined.
+    pub(crate) fn config_lines(&mut self) -> Vec<String> {
+        let tree = self.tree;
+        let mut lines = Vec::new();
+
+        for index in &tree.order {
+            let value = self.value(*index);
+            if value.write {
+                lines.extend(Self::config_line(&tree.symbols[*index], &value));
+            }
+        }
+
+        lines
+    }
+
+    /// The smallest config that resolves to the same values, like `make savedefconfig`.
+    ///
+    /// Symbols are left out if they cannot be changed by the user, are already
+    /// at their default value, or are the default member of a choice.
+    pub(crate) fn minimal_lines(&mut self) -> Vec<String> {
+        let tree = self.tree;
+        let mut lines = Vec::new();
+
+        for index in &tree.order {
+            let symbol = &tree.symbols[*index];
+            let value = self.value(*index);
+            if !value.write {
+                continue;
+            }
+
+            let visible = self.visibility(symbol);
+            let rev_dep = self.reverse(&symbol.rev_dep, symbol.kind);
+            if visible <= rev_dep {
+                continue;
+            }
+
+            let default = match symbol.kind {
+                SymbolType::Bool | SymbolType::Tristate => {
+                    let default = self.default_tristate(symbol).unwrap_or_default();
+                    let default = default.or(rev_dep);
+                    self.promote_module(default, symbol.kind).to_string()
+                }
+                _ => self.default_string(symbol).unwrap_or_default(),
+            };
+            if value.string == default {
+                continue;
+            }
+
+            if let Some(choice) = symbol.choice {
+                if !tree.choices[choice].optional
+                    && self.choice_default(choice) == Some(*index)
+                    && value.tristate == Tristate::Yes
+                {
+                    continue;
+                }
+            }
+
+            lines.extend(Self::config_line(symbol, &value));
+        }
+
+        lines
+    }
+
+    /// Format a symbol value as a kernel config line.
+    fn config_line(symbol: &Symbol, value: &SymbolValue) -> Option<String> {
+        let name = format!("{CONFIG_PREFIX}{}", symbol.name);
+        let line = match symbol.kind {
+            SymbolType::Bool | SymbolType::Tristate => match value.tristate {
+                Tristate::No => format!("# {name} is not set"),
+                t => format!("{name}={t}"),
+            },
+            SymbolType::String => format!(
+                "{name}=\"{}\"",
+                value.string.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+            SymbolType::Int | SymbolType::Hex => format!("{name}={}", value.string),
+            SymbolType::Unknown => return None,
+        };
+
+        Some(line)
+    }
+
+    /// Calculate the value of a symbol.
+    pub(crate) fn value(&mut self, index: usize) -> SymbolValue {
+        if let Some(value) = self.values.get(&index) {
+            return value.clone();
+        }
+
+        if !self.in_progress.insert(index) {
+            return SymbolValue::default();
+        }
+
+        let tree = self.tree;
+        let symbol = &tree.symbols[index];
+        let value = match symbol.kind {
+            SymbolType::Bool | SymbolType::Tristate => self.calc_tristate(index, symbol),
+            SymbolType::String | SymbolType::Int | SymbolType::Hex => self.calc_string(symbol),
+            SymbolType::Unknown => SymbolValue::default(),
+        };
+
+        self.in_progress.remove(&index);
+        self.values.insert(index, value.clone());
+        value
+    }
+
+    /// Convert `m` to `y` for symbols that cannot be modules.
+    fn promote_module(&mut self, value: Tristate, kind: SymbolType) -> Tristate {
+        if value == Tristate::Module
+            && (kind != SymbolType::Tristate || self.modules() == Tristate::No)
+        {
+            Tristate::Yes
+        } else {
+            value
+        }
+    }
+
+    /// The highest value a user can set the symbol to.
+    pub(crate) fn visibility(&mut self, symbol: &Symbol) -> Tristate {
+        let visible = symbol
+            .prompts
+            .iter()
+            .fold(Tristate::No, |acc, p| acc.or(p.visible.eval(self)));
+        self.promote_module(visible, symbol.kind)
+    }
+
+    /// Combine properties that apply to the symbol, such as `select` or `imply`.
+    fn reverse(&mut self, properties: &[Property], kind: SymbolType) -> Tristate {
+        let value = properties.iter().fold(Tristate::No, |acc, p| {
+            acc.or(p.expr.eval(self).and(p.cond.eval(self)))
+        });
+        self.promote_module(value, kind)
+    }
+
+    /// The first default whose condition is met.
+    fn default<'p>(&mut self, defaults: &'p [Property]) -> Option<&'p Property> {
+        defaults.iter().find(|p| p.cond.eval(self) != Tristate::No)
+    }
+
+    fn dir_dep(&mut self, symbol: &Symbol) -> Tristate {
+        match &symbol.dir_dep {
+            Some(dep) => dep.eval(self),
+            None => Tristate::Yes,
+        }
+    }
+
+    /// The user value of a symbol, if one was set.
+    fn user_tristate(&self, symbol: &Symbol) -> Option<Tristate> {
+        self.user
+            .get(&symbol.name)
+            .and_then(|v| Tristate::from_value(v))
+    }
+
+    /// The value of a `bool` or `tristate` symbol from its defaults and `imply`.
+    ///
+    /// Returns `None` if no default applies.
+    fn default_tristate(&mut self, symbol: &Symbol) -> Option<Tristate> {
+        let mut value = None;
+
+        if let Some(default) = self.default(&symbol.defaults) {
+            value = Some(default.expr.eval(self).and(default.cond.eval(self)));
+        }
+
+        let implied = self.reverse(&symbol.implied, symbol.kind);
+        if implied != Tristate::No {
+            let implied = value.unwrap_or_default().or(implied);
+            value = Some(implied.and(self.dir_dep(symbol)));
+        }
+
+        value
+    }
+
+    fn calc_tristate(&mut self, index: usize, symbol: &Symbol) -> SymbolValue {
+        let visible = self.visibility(symbol);
+        let rev_dep = self.reverse(&symbol.rev_dep, symbol.kind);
+
+        if let Some(choice) = symbol.choice {
+            if self.choice_visibility(choice) != Tristate::No {
+                let selected = self.choice_selection(choice) == Some(index);
+                let tristate = Tristate::from(selected).or(rev_dep);
+                return SymbolValue {
+                    tristate,
+                    string: tristate.to_string(),
+                    write: true,
+                };
+            }
+        }
+
+        let mut write = visible != Tristate::No;
+        let user = self.user_tristate(symbol).filter(|_| write);
+
+        let mut value = match user {
+            Some(u) => u.and(visible),
+            None => {
+                if rev_dep != Tristate::No {
+                    write = true;
+                }
+
+                match self.default_tristate(symbol) {
+                    Some(value) => {
+                        write = true;
+                        value
+                    }
+                    None => Tristate::No,
+                }
+            }
+        };
+
+        value = value.or(rev_dep);
+        if self.dir_dep(symbol) == Tristate::No && rev_dep == Tristate::No {
+            write = false;
+        }
+
+        let tristate = self.promote_module(value, symbol.kind);
+        SymbolValue {
+            tristate,
+            string: tristate.to_string(),
+            write,
+        }
+    }
+
+    /// The default value of a `string`, `int`, or `hex` symbol.
+    fn default_string(&mut self, symbol: &Symbol) -> Option<String> {
+        let default = self.default(&symbol.defaults)?;
+        let value = default.expr.string(self);
+        Some(self.clamp(symbol, value))
+    }
+
+    fn calc_string(&mut self, symbol: &Symbol) -> SymbolValue {
+        let visible = self.visibility(symbol);
+        let mut write = visible != Tristate::No;
+
+        let user = self
+            .user
+            .get(&symbol.name)
+            .cloned()
+            .filter(|_| write)
+            .filter(|v| self.in_range(symbol, v));
+
+        let string = match user {
+            Some(u) => u,
+            None => match self.default_string(symbol) {
+                Some(value) => {
+                    write = true;
+                    value
+                }
+                None => String::new(),
+            },
+        };
+
+        if self.dir_dep(symbol) == Tristate::No {
+            write = false;
+        }
+
+        SymbolValue {
+            tristate: Tristate::No,
+            string,
+            write,
+        }
+    }
+
+    /// The first range whose condition is met, for `int` and `hex` symbols.
+    fn range(&mut self, symbol: &Symbol) -> Option<(i64, i64)> {
+        if !matches!(symbol.kind, SymbolType::Int | SymbolType::Hex) {
+            return None;
+        }
+
+        for (min, max, cond) in &symbol.ranges {
+            if cond.eval(self) != Tristate::No {
+                let min = parse_number(&min.string(self))?;
+                let max = parse_number(&max.string(self))?;
+                return Some((min, max));
+            }
+        }
+
+        None
+    }
+
+    fn in_range(&mut self, symbol: &Symbol, value: &str) -> bool {
+        if !matches!(symbol.kind, SymbolType::Int | SymbolType::Hex) {
+            return true;
+        }
+
+        match (parse_number(value), self.range(symbol)) {
+            (Some(v), Some((min, max))) => v >= min && v <= max,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Limit a default value to the allowed range.
+    fn clamp(&mut self, symbol: &Symbol, value: String) -> String {
+        let (Some(v), Some((min, max))) = (parse_number(&value), self.range(symbol)) else {
+            return value;
+        };
+
+        let clamped = v.clamp(min, max);
+        match (clamped == v, symbol.kind) {
+            (true, _) => value,
+            (false, SymbolType::Hex) => format!("{clamped:#x}"),
+            (false, _) => clamped.to_string(),
+        }
+    }
+
+    fn choice_visibility(&mut self, choice: usize) -> Tristate {
+        let tree = self.tree;
+        tree.choices[choice]
+            .prompts
+            .iter()
+            .fold(Tristate::No, |acc, p| acc.or(p.visible.eval(self)))
+    }
+
+    /// Members of a choice that are visible.
+    fn choice_members(&mut self, choice: usize) -> Vec<usize> {
+        let tree = self.tree;
+        tree.choices[choice]
+            .members
+            .iter()
+            .filter_map(|m| tree.index.get(m).copied())
+            .filter(|i| self.visibility(&tree.symbols[*i]) != Tristate::No)
+            .collect()
+    }
+
+    /// The member of a choice that is selected when the user has not picked one.
+    ///
+    /// This is the first default whose condition is met, or the first visible member.
+    fn choice_default(&mut self, choice: usize) -> Option<usize> {
+        let tree = self.tree;
+        let members = self.choice_members(choice);
+
+        let default = tree.choices[choice]
+            .defaults
+            .iter()
+            .filter(|p| p.cond.eval(self) != Tristate::No)
+            .find_map(|p| match &p.expr {
+                Expr::Symbol(name) => tree
+                    .index
+                    .get(name)
+                    .copied()
+                    .filter(|i| members.contains(i)),
+                _ => None,
+            });
+
+        default.or_else(|| members.first().copied())
+    }
+
+    /// The selected member of a choice.
+    ///
+    /// A visible member set to `y` by the user wins over the default member.
+    /// Optional choices have no member selected by default.
+    fn choice_selection(&mut self, choice: usize) -> Option<usize> {
+        if let Some(selected) = self.choices.get(&choice) {
+            return *selected;
+        }
+
+        let tree = self.tree;
+        let user = self
+            .choice_members(choice)
+            .into_iter()
+            .find(|i| self.user_tristate(&tree.symbols[*i]) == Some(Tristate::Yes));
+
+        let selected = match (user, tree.choices[choice].optional) {
+            (Some(user), _) => Some(user),
+            (None, true) => None,
+            (None, false) => self.choice_default(choice),
+        };
+
+        self.choices.insert(choice, selected);
+        selected
+    }
+}
+
+impl SymbolLookup for Resolver<'_> {
+    fn tristate(&mut self, name: &str) -> Tristate {
+        match self.tree.index.get(name) {
+            Some(index) => self.value(*index).tristate,
+            None => Tristate::No,
+        }
+    }
+
+    fn string(&mut self, name: &str) -> String {
+        match self.tree.index.get(name) {
+            Some(index) if self.tree.symbols[*index].defined => self.value(*index).string,
+            // Undefined symbols are constants with their name as the value
+            _ => name.to_string(),
+        }
+    }
+
+    fn modules(&mut self) -> Tristate {
+        let tree = self.tree;
+        match &tree.modules {
+            Some(name) => self.tristate(name),
+            None => Tristate::No,
+        }
+    }
+}